@@ -0,0 +1,152 @@
+use std::collections::HashSet;
+
+use crate::config::Strategy;
+
+const SAMPLE_LEN: usize = 4096;
+const SAMPLES_NUM: usize = 16;
+/// Shortest run of one byte counted by `run_fraction`, shorter ones are left to LZ77.
+const MIN_RUN_LEN: usize = 8;
+/// Length of the prefix `repeat_fraction` looks for earlier in the sample.
+const REPEAT_LEN: usize = 4;
+
+const MAX_STORED_ENTROPY: f64 = 7.5;
+const MIN_RLE_RUN_FRACTION: f64 = 0.5;
+const MIN_TEXT_FRACTION: f64 = 0.95;
+const MIN_LZ77_REPEAT_FRACTION: f64 = 0.1;
+
+/// Formats whose content is compressed already, recognized by their magic number.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CompressedFormat {
+    Jpeg,
+    Zip,
+    Gzip,
+    Png,
+}
+
+impl CompressedFormat {
+    const MAGIC_NUMBERS: [(CompressedFormat, &'static [u8]); 4] = [
+        (CompressedFormat::Jpeg, &[0xFF, 0xD8, 0xFF]),
+        (CompressedFormat::Zip, b"PK\x03\x04"),
+        (CompressedFormat::Gzip, &[0x1F, 0x8B]),
+        (CompressedFormat::Png, b"\x89PNG"),
+    ];
+
+    pub fn detect(bytes: &[u8]) -> Option<CompressedFormat> {
+        CompressedFormat::MAGIC_NUMBERS
+            .iter()
+            .find(|(_, magic)| bytes.starts_with(magic))
+            .map(|(format, _)| *format)
+    }
+}
+
+/// Properties of the input estimated from at most `SAMPLES_NUM` evenly spaced samples
+/// of `SAMPLE_LEN` bytes, so analysis of large inputs stays cheap:
+///  - `entropy`: order-0 entropy of sampled bytes, in bits per byte,
+///  - `compressed_format`: format recognized by the magic number at the start of the input,
+///  - `run_fraction`: fraction of sampled bytes in runs of at least `MIN_RUN_LEN` equal bytes,
+///  - `text_fraction`: fraction of sampled bytes that are printable ascii or whitespace,
+///  - `repeat_fraction`: fraction of sampled positions whose next `REPEAT_LEN` bytes occurred
+///    earlier in the same sample, i.e. that LZ77 could replace by a reference.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct InputAnalysis {
+    pub sample_len: usize,
+    pub entropy: f64,
+    pub compressed_format: Option<CompressedFormat>,
+    pub run_fraction: f64,
+    pub text_fraction: f64,
+    pub repeat_fraction: f64,
+}
+
+impl InputAnalysis {
+    pub fn of(bytes: &[u8]) -> Self {
+        let mut counts = [0usize; 256];
+        let mut run_bytes = 0;
+        let mut text_bytes = 0;
+        let mut repeats = 0;
+
+        let samples = InputAnalysis::samples(bytes);
+        for sample in samples.iter() {
+            sample.iter().for_each(|&byte| counts[byte as usize] += 1);
+            text_bytes += sample.iter().filter(|&&byte| is_text(byte)).count();
+            run_bytes += InputAnalysis::run_bytes(sample);
+            repeats += InputAnalysis::repeats(sample);
+        }
+
+        let sample_len: usize = samples.iter().map(|sample| sample.len()).sum();
+        let fraction = |count: usize| match sample_len {
+            0 => 0.0,
+            _ => count as f64 / sample_len as f64,
+        };
+        let entropy = counts
+            .iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let p = fraction(count);
+                -p * p.log2()
+            })
+            .sum();
+
+        InputAnalysis {
+            sample_len,
+            entropy,
+            compressed_format: CompressedFormat::detect(bytes),
+            run_fraction: fraction(run_bytes),
+            text_fraction: fraction(text_bytes),
+            repeat_fraction: fraction(repeats),
+        }
+    }
+
+    /// Strategy expected to compress the input best:
+    ///  - `Stored` for compressed formats and data close to random, which no stage would shrink,
+    ///  - `Rle` for data made mostly of runs,
+    ///  - `Default` for text and data with repeats LZ77 can find,
+    ///  - `HuffmanOnly` for data with skewed byte frequencies but few repeats.
+    pub fn strategy(&self) -> Strategy {
+        if self.sample_len == 0 || self.compressed_format.is_some() {
+            return Strategy::Stored;
+        }
+        if self.entropy > MAX_STORED_ENTROPY && self.repeat_fraction < MIN_LZ77_REPEAT_FRACTION {
+            return Strategy::Stored;
+        }
+        if self.run_fraction > MIN_RLE_RUN_FRACTION {
+            return Strategy::Rle;
+        }
+        if self.text_fraction > MIN_TEXT_FRACTION {
+            return Strategy::Default;
+        }
+        match self.repeat_fraction < MIN_LZ77_REPEAT_FRACTION {
+            true => Strategy::HuffmanOnly,
+            false => Strategy::Default,
+        }
+    }
+
+    fn samples(bytes: &[u8]) -> Vec<&[u8]> {
+        if bytes.len() <= SAMPLE_LEN * SAMPLES_NUM {
+            return vec![bytes];
+        }
+        let step = (bytes.len() - SAMPLE_LEN) / (SAMPLES_NUM - 1);
+        (0..SAMPLES_NUM)
+            .map(|i| &bytes[i * step..i * step + SAMPLE_LEN])
+            .collect()
+    }
+
+    fn run_bytes(sample: &[u8]) -> usize {
+        sample
+            .chunk_by(|a, b| a == b)
+            .map(|run| run.len())
+            .filter(|&len| len >= MIN_RUN_LEN)
+            .sum()
+    }
+
+    fn repeats(sample: &[u8]) -> usize {
+        let mut seen = HashSet::new();
+        sample
+            .windows(REPEAT_LEN)
+            .filter(|prefix| !seen.insert(*prefix))
+            .count()
+    }
+}
+
+fn is_text(byte: u8) -> bool {
+    byte.is_ascii_graphic() || matches!(byte, b' ' | b'\t' | b'\n' | b'\r')
+}
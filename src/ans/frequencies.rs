@@ -0,0 +1,81 @@
+pub const PRECISION_BITS: u32 = 12;
+const TOTAL_FREQUENCY: u32 = 1 << PRECISION_BITS;
+
+/// Symbol frequencies scaled so that they sum up to `2^PRECISION_BITS`,
+/// with every occurring symbol keeping a non-zero frequency.
+pub struct NormalizedFrequencies {
+    frequencies: Vec<u32>,
+    starts: Vec<u32>,
+    slot_to_symbol: Vec<u16>,
+}
+
+impl NormalizedFrequencies {
+    pub fn new(counts: &[usize]) -> Self {
+        let total: usize = counts.iter().sum();
+        let mut frequencies: Vec<u32> = counts
+            .iter()
+            .map(|&count| match count {
+                0 => 0,
+                _ => ((count as u64 * TOTAL_FREQUENCY as u64 / total as u64) as u32).max(1),
+            })
+            .collect();
+
+        if total > 0 {
+            let mut sum: u32 = frequencies.iter().sum();
+            while sum > TOTAL_FREQUENCY {
+                let biggest = NormalizedFrequencies::biggest(&frequencies);
+                frequencies[biggest] -= 1;
+                sum -= 1;
+            }
+            let biggest = NormalizedFrequencies::biggest(&frequencies);
+            frequencies[biggest] += TOTAL_FREQUENCY - sum;
+        }
+
+        NormalizedFrequencies::from_frequencies(frequencies).unwrap()
+    }
+
+    /// Frequencies read from a header; they have to sum up to `2^PRECISION_BITS`
+    /// unless all of them are zero, otherwise `None` is returned.
+    pub fn from_frequencies(frequencies: Vec<u32>) -> Option<Self> {
+        let mut starts = Vec::with_capacity(frequencies.len());
+        let mut slot_to_symbol = Vec::with_capacity(TOTAL_FREQUENCY as usize);
+        let mut start = 0;
+        for (symbol, &frequency) in frequencies.iter().enumerate() {
+            starts.push(start);
+            start += frequency;
+            if start > TOTAL_FREQUENCY {
+                return None;
+            }
+            slot_to_symbol.extend(std::iter::repeat_n(symbol as u16, frequency as usize));
+        }
+
+        if start != 0 && start != TOTAL_FREQUENCY {
+            return None;
+        }
+        Some(NormalizedFrequencies {
+            frequencies,
+            starts,
+            slot_to_symbol,
+        })
+    }
+
+    fn biggest(frequencies: &[u32]) -> usize {
+        (0..frequencies.len())
+            .max_by_key(|&symbol| frequencies[symbol])
+            .unwrap()
+    }
+
+    pub fn frequencies(&self) -> &[u32] {
+        &self.frequencies
+    }
+
+    pub fn interval(&self, symbol: usize) -> (u32, u32) {
+        (self.starts[symbol], self.frequencies[symbol])
+    }
+
+    pub fn symbol_at(&self, slot: u32) -> Option<usize> {
+        self.slot_to_symbol
+            .get(slot as usize)
+            .map(|&symbol| symbol as usize)
+    }
+}
@@ -0,0 +1,293 @@
+const STATE_LOWER_BOUND: u32 = 1 << 23;
+const STATE_BYTES_NUM: usize = 4;
+const MAX_RAW_BITS_AT_ONCE: u32 = 16;
+/// Estimated bits of a length or distance symbol, extra bits excluded.
+const ESTIMATED_SYMBOL_BITS: usize = 5;
+
+use super::frequencies::{NormalizedFrequencies, PRECISION_BITS};
+use crate::error::DecodeError;
+use crate::limits::{DecompressLimits, OutputBudget};
+use crate::lz77::{
+    alphabet::{SymbolCode, DISTANCE_SYMBOLS_NUM, END_OF_BLOCK, LIT_LEN_SYMBOLS_NUM},
+    cost::{LiteralFitting, SymbolCosts},
+    implementation::{ResultEncoding, ResultEncodingVec},
+};
+
+/// Byte-wise rANS encoder. Coding is last in, first out, so symbols are buffered
+/// and written in reverse by `finish`, letting the decoder read them in push order.
+#[derive(Default)]
+pub struct RansEncoder {
+    intervals: Vec<(u32, u32, u32)>,
+}
+
+impl RansEncoder {
+    pub fn new() -> Self {
+        RansEncoder {
+            intervals: Vec::new(),
+        }
+    }
+
+    pub fn push_symbol(&mut self, frequencies: &NormalizedFrequencies, symbol: usize) {
+        let (start, frequency) = frequencies.interval(symbol);
+        assert!(frequency > 0, "Symbol without frequency cannot be coded.");
+        self.intervals.push((start, frequency, PRECISION_BITS));
+    }
+
+    /// Pushes `n` lowest bits of `value`, each with probability 1/2.
+    pub fn push_raw_bits(&mut self, value: u64, n: u32) {
+        let mut remaining = n;
+        while remaining > 0 {
+            let chunk = remaining.min(MAX_RAW_BITS_AT_ONCE);
+            remaining -= chunk;
+            let bits = ((value >> remaining) & ((1 << chunk) - 1)) as u32;
+            self.intervals.push((bits, 1, chunk));
+        }
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut state = STATE_LOWER_BOUND;
+        for &(start, frequency, scale_bits) in self.intervals.iter().rev() {
+            let state_max = ((STATE_LOWER_BOUND >> scale_bits) << 8) * frequency;
+            while state >= state_max {
+                bytes.push(state as u8);
+                state >>= 8;
+            }
+            state = ((state / frequency) << scale_bits) + state % frequency + start;
+        }
+
+        bytes.extend(state.to_le_bytes());
+        bytes.reverse();
+        bytes
+    }
+}
+
+pub struct RansDecoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    state: u32,
+}
+
+impl<'a> RansDecoder<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        let mut decoder = RansDecoder {
+            bytes,
+            position: 0,
+            state: 0,
+        };
+        (0..STATE_BYTES_NUM)
+            .for_each(|_| decoder.state = (decoder.state << 8) | decoder.next_byte() as u32);
+        decoder
+    }
+
+    /// Returns `false` once the decoder read past the end of input.
+    pub fn is_within_input(&self) -> bool {
+        self.position <= self.bytes.len()
+    }
+
+    /// State and position in input, which determine the symbols decoded next.
+    fn progress(&self) -> (u32, usize) {
+        (self.state, self.position)
+    }
+
+    /// Returns `None` if no symbol has a frequency.
+    pub fn decode_symbol(&mut self, frequencies: &NormalizedFrequencies) -> Option<usize> {
+        let slot = self.state & ((1 << PRECISION_BITS) - 1);
+        let symbol = frequencies.symbol_at(slot)?;
+        let (start, frequency) = frequencies.interval(symbol);
+        self.advance(start, frequency, PRECISION_BITS);
+        Some(symbol)
+    }
+
+    pub fn decode_raw_bits(&mut self, n: u32) -> u64 {
+        let mut value = 0;
+        let mut remaining = n;
+        while remaining > 0 {
+            let chunk = remaining.min(MAX_RAW_BITS_AT_ONCE);
+            remaining -= chunk;
+            let bits = self.state & ((1 << chunk) - 1);
+            self.advance(bits, 1, chunk);
+            value = (value << chunk) | bits as u64;
+        }
+
+        value
+    }
+
+    fn advance(&mut self, start: u32, frequency: u32, scale_bits: u32) {
+        let mask = (1 << scale_bits) - 1;
+        // Wrapping, since a corrupted state may not fit after the update.
+        self.state = frequency
+            .wrapping_mul(self.state >> scale_bits)
+            .wrapping_add((self.state & mask) - start);
+        while self.state < STATE_LOWER_BOUND {
+            self.state = (self.state << 8) | self.next_byte() as u32;
+        }
+    }
+
+    fn next_byte(&mut self) -> u8 {
+        let byte = self.bytes.get(self.position).copied().unwrap_or(0);
+        self.position += 1;
+        byte
+    }
+}
+
+/// rANS coder over LZ77 tokens. Output starts with normalized frequencies
+/// of literals/lengths and distances, followed by the rANS stream.
+pub struct AnsCompressor;
+
+impl AnsCompressor {
+    /// Costs of tokens coded by the stage, with fractions of bits fitted to the frequencies of literals.
+    pub fn symbol_costs() -> SymbolCosts {
+        SymbolCosts::fitted(
+            LiteralFitting::Fractional,
+            ESTIMATED_SYMBOL_BITS,
+            ESTIMATED_SYMBOL_BITS,
+        )
+    }
+
+    pub fn compress(ascii_bytes: &[u8]) -> Vec<u8> {
+        let tokens: ResultEncodingVec = ascii_bytes
+            .iter()
+            .map(|c| ResultEncoding::Ascii(*c))
+            .collect();
+        AnsCompressor::compress_tokens(&tokens)
+    }
+
+    pub fn decompress(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        AnsCompressor::decompress_tokens(bytes)?.try_expand()
+    }
+
+    pub fn compress_tokens(tokens: &ResultEncodingVec) -> Vec<u8> {
+        let mut lit_len_counts = vec![0; LIT_LEN_SYMBOLS_NUM as usize];
+        let mut distance_counts = vec![0; DISTANCE_SYMBOLS_NUM as usize];
+        lit_len_counts[END_OF_BLOCK as usize] += 1;
+        for token in tokens.iter() {
+            let (lit_len_code, distance_code) = token.to_symbol_codes();
+            lit_len_counts[lit_len_code.symbol as usize] += 1;
+            if let Some(code) = distance_code {
+                distance_counts[code.symbol as usize] += 1;
+            }
+        }
+        let lit_len = NormalizedFrequencies::new(&lit_len_counts);
+        let distance = NormalizedFrequencies::new(&distance_counts);
+
+        let mut encoder = RansEncoder::new();
+        for token in tokens.iter() {
+            let (lit_len_code, distance_code) = token.to_symbol_codes();
+            encoder.push_symbol(&lit_len, lit_len_code.symbol as usize);
+            encoder.push_raw_bits(lit_len_code.extra_bits, lit_len_code.extra_bits_num);
+            if let Some(code) = distance_code {
+                encoder.push_symbol(&distance, code.symbol as usize);
+                encoder.push_raw_bits(code.extra_bits, code.extra_bits_num);
+            }
+        }
+        encoder.push_symbol(&lit_len, END_OF_BLOCK as usize);
+
+        let mut bytes = Vec::new();
+        AnsCompressor::append_frequencies(&mut bytes, &lit_len);
+        AnsCompressor::append_frequencies(&mut bytes, &distance);
+        bytes.extend(encoder.finish());
+        bytes
+    }
+
+    pub fn decompress_tokens(bytes: &[u8]) -> Result<ResultEncodingVec, DecodeError> {
+        AnsCompressor::decompress_tokens_with_limits(bytes, &DecompressLimits::default())
+    }
+
+    /// Same as `decompress_tokens`, but fails as soon as expanded tokens
+    /// exceed `limits`, the ratio limit included.
+    pub fn decompress_tokens_with_limits(
+        bytes: &[u8],
+        limits: &DecompressLimits,
+    ) -> Result<ResultEncodingVec, DecodeError> {
+        let mut budget =
+            OutputBudget::new(&limits.with_max_output_len(limits.output_limit(bytes.len())));
+        let mut i = 0;
+        let lit_len = AnsCompressor::read_frequencies(bytes, &mut i, LIT_LEN_SYMBOLS_NUM)?;
+        let distance = AnsCompressor::read_frequencies(bytes, &mut i, DISTANCE_SYMBOLS_NUM)?;
+        // Without the end of block symbol no sequence ends, and with it every other
+        // literal/length symbol has less than the full frequency, so it shrinks the state
+        // until input is read.
+        if lit_len.interval(END_OF_BLOCK as usize).1 == 0 {
+            return Err(DecodeError::new("end of block symbol without frequency"));
+        }
+        let mut decoder = RansDecoder::new(&bytes[i..]);
+        let no_symbol = || DecodeError::new("symbol without frequency");
+
+        let mut tokens = ResultEncodingVec::new();
+        loop {
+            if !decoder.is_within_input() {
+                return Err(DecodeError::new(
+                    "sequence ended before the end of block symbol",
+                ));
+            }
+            let progress = decoder.progress();
+            let symbol = decoder.decode_symbol(&lit_len).ok_or_else(no_symbol)? as u16;
+            if symbol == END_OF_BLOCK {
+                return Ok(tokens);
+            }
+            if !SymbolCode::is_length_symbol(symbol) {
+                budget.take_bytes(1)?;
+                tokens.push(ResultEncoding::Ascii(symbol as u8));
+            } else {
+                let length_extra =
+                    decoder.decode_raw_bits(SymbolCode::length_extra_bits_num(symbol));
+                let len = SymbolCode::length_value(symbol, length_extra);
+                let distance_symbol =
+                    decoder.decode_symbol(&distance).ok_or_else(no_symbol)? as u16;
+                let distance_extra =
+                    decoder.decode_raw_bits(SymbolCode::distance_extra_bits_num(distance_symbol));
+                let token = SymbolCode::reference(len, distance_symbol, distance_extra);
+                budget.take(&token)?;
+                tokens.push(token);
+            }
+
+            // Decoding is a function of the state and position, so a token read without
+            // changing them repeats forever, never reaching the end of block.
+            if decoder.progress() == progress {
+                return Err(DecodeError::new("token decoded without reading input"));
+            }
+        }
+    }
+
+    /// Writes number of occurring symbols, then every one of them with its frequency.
+    fn append_frequencies(bytes: &mut Vec<u8>, frequencies: &NormalizedFrequencies) {
+        let occurring: Vec<(usize, &u32)> = frequencies
+            .frequencies()
+            .iter()
+            .enumerate()
+            .filter(|(_, frequency)| **frequency > 0)
+            .collect();
+
+        bytes.extend((occurring.len() as u16).to_be_bytes());
+        for (symbol, frequency) in occurring {
+            bytes.extend((symbol as u16).to_be_bytes());
+            bytes.extend((*frequency as u16).to_be_bytes());
+        }
+    }
+
+    fn read_frequencies(
+        bytes: &[u8],
+        i: &mut usize,
+        symbols_num: u16,
+    ) -> Result<NormalizedFrequencies, DecodeError> {
+        let invalid_header = || DecodeError::new("invalid frequencies of rANS symbols");
+        let mut next_u16 = || {
+            let value = bytes
+                .get(*i..*i + 2)
+                .map(|chunk| u16::from_be_bytes([chunk[0], chunk[1]]))
+                .ok_or_else(invalid_header);
+            *i += 2;
+            value
+        };
+
+        let mut frequencies = vec![0; symbols_num as usize];
+        for _ in 0..next_u16()? {
+            let symbol = next_u16()? as usize;
+            let frequency = next_u16()? as u32;
+            *frequencies.get_mut(symbol).ok_or_else(invalid_header)? = frequency;
+        }
+
+        NormalizedFrequencies::from_frequencies(frequencies).ok_or_else(invalid_header)
+    }
+}
@@ -0,0 +1,5 @@
+mod frequencies;
+pub mod implementation;
+
+pub use frequencies::NormalizedFrequencies;
+pub use implementation::{AnsCompressor, RansDecoder, RansEncoder};
@@ -0,0 +1,248 @@
+const MAGIC: [u8; 4] = *b"DFLS";
+const FORMAT_VERSION: u8 = 1;
+const NAME_LEN_BYTES: usize = 2;
+const INDEX_LEN_BYTES: usize = 8;
+/// Length of byte strings whose hashes give the similarity of contents.
+const SHINGLE_LEN: usize = 8;
+const SHINGLE_HASH_MUL: u64 = 0x9E37_79B9_7F4A_7C15;
+
+use std::collections::HashMap;
+use std::{error::Error, fmt};
+
+use crate::container::ContainerError;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ArchiveError {
+    BadMagic,
+    UnsupportedVersion(u8),
+    Truncated,
+    InvalidName { entry: usize },
+    NameTooLong { entry: usize },
+    TooManyEntries(usize),
+    EntryOutOfBounds { entry: usize },
+    Container(ContainerError),
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArchiveError::BadMagic => write!(f, "Input is not an archive"),
+            ArchiveError::UnsupportedVersion(version) => write!(
+                f,
+                "Unsupported archive version {}, expected {}",
+                version, FORMAT_VERSION
+            ),
+            ArchiveError::Truncated => write!(f, "Archive is truncated"),
+            ArchiveError::InvalidName { entry } => {
+                write!(f, "Name of entry {} is not valid UTF-8", entry)
+            }
+            ArchiveError::NameTooLong { entry } => write!(
+                f,
+                "Name of entry {} is longer than {} bytes",
+                entry,
+                u16::MAX
+            ),
+            ArchiveError::TooManyEntries(entries_num) => write!(
+                f,
+                "Archive of {} entries exceeds the limit of {}",
+                entries_num,
+                u32::MAX
+            ),
+            ArchiveError::EntryOutOfBounds { entry } => {
+                write!(f, "Entry {} lies outside of the archived data", entry)
+            }
+            ArchiveError::Container(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for ArchiveError {}
+
+/// File of an archive, named e.g. by its path relative to the archived directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// Entry of `ArchiveIndex`: the name of a file and where its data lies in the solid data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexEntry {
+    pub name: String,
+    pub offset: u64,
+    pub len: u64,
+}
+
+/// Index of a solid archive, listing its entries. Archive starts with 4 bytes of magic,
+/// 1 byte of format version and 8 bytes of length of the compressed index, followed by
+/// two compressed streams, see `ContainerHeader`: of the index and of the solid data,
+/// so entries are listed without decompressing their data. Decompressed index holds
+/// 4 bytes of number of entries, then every entry as its name prefixed by its 2 byte
+/// length, 8 bytes of offset and 8 bytes of length of its data, all big endian.
+///
+/// Data of all entries is concatenated into solid data compressed as one stream,
+/// so that matches span the files. Entries are kept in the order they were given,
+/// while their data is ordered by `build`; entries of identical data share one offset.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArchiveIndex {
+    pub entries: Vec<IndexEntry>,
+}
+
+impl ArchiveIndex {
+    pub fn version() -> u8 {
+        FORMAT_VERSION
+    }
+
+    /// Index of `entries` with their solid data. Data of files with the same extension
+    /// and then of similar contents is placed next to each other, so repeats between them
+    /// fall within the window, while data of identical files is stored only once.
+    pub fn build(entries: &[ArchiveEntry]) -> (Self, Vec<u8>) {
+        let mut first_with_data: HashMap<&[u8], usize> = HashMap::new();
+        let unique: Vec<usize> = (0..entries.len())
+            .filter(|&i| *first_with_data.entry(&entries[i].data).or_insert(i) == i)
+            .collect();
+        let mut ordered: Vec<(String, u64, &str, usize)> = unique
+            .into_iter()
+            .map(|i| {
+                let entry = &entries[i];
+                (
+                    extension(&entry.name),
+                    min_hash(&entry.data),
+                    &*entry.name,
+                    i,
+                )
+            })
+            .collect();
+        ordered.sort();
+
+        let mut solid = Vec::new();
+        let mut offsets = HashMap::new();
+        for (_, _, _, i) in ordered {
+            offsets.insert(i, solid.len() as u64);
+            solid.extend(&entries[i].data);
+        }
+        let index_entries = entries
+            .iter()
+            .map(|entry| IndexEntry {
+                name: entry.name.clone(),
+                offset: offsets[&first_with_data[entry.data.as_slice()]],
+                len: entry.data.len() as u64,
+            })
+            .collect();
+        let index = ArchiveIndex {
+            entries: index_entries,
+        };
+        (index, solid)
+    }
+
+    /// Archive of the compressed index and the compressed solid data.
+    pub fn archive(index: &[u8], solid: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        bytes.extend((index.len() as u64).to_be_bytes());
+        bytes.extend(index);
+        bytes.extend(solid);
+        bytes
+    }
+
+    /// Splits archive into the compressed index and the compressed solid data.
+    pub fn split_archive(bytes: &[u8]) -> Result<(&[u8], &[u8]), ArchiveError> {
+        let header_len = MAGIC.len() + 1 + INDEX_LEN_BYTES;
+        if bytes.get(..MAGIC.len()).ok_or(ArchiveError::Truncated)? != MAGIC {
+            return Err(ArchiveError::BadMagic);
+        }
+        let header = bytes.get(..header_len).ok_or(ArchiveError::Truncated)?;
+        let version = header[MAGIC.len()];
+        if version != FORMAT_VERSION {
+            return Err(ArchiveError::UnsupportedVersion(version));
+        }
+        let index_len = u64::from_be_bytes(header[MAGIC.len() + 1..].try_into().unwrap());
+        let rest = &bytes[header_len..];
+        match usize::try_from(index_len) {
+            Ok(index_len) if index_len <= rest.len() => Ok(rest.split_at(index_len)),
+            _ => Err(ArchiveError::Truncated),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ArchiveError> {
+        let mut bytes = Vec::new();
+        let entries_num = u32::try_from(self.entries.len())
+            .map_err(|_| ArchiveError::TooManyEntries(self.entries.len()))?;
+        bytes.extend(entries_num.to_be_bytes());
+        for (i, entry) in self.entries.iter().enumerate() {
+            let name_len = u16::try_from(entry.name.len())
+                .map_err(|_| ArchiveError::NameTooLong { entry: i })?;
+            bytes.extend(name_len.to_be_bytes());
+            bytes.extend(entry.name.as_bytes());
+            bytes.extend(entry.offset.to_be_bytes());
+            bytes.extend(entry.len.to_be_bytes());
+        }
+        Ok(bytes)
+    }
+
+    /// Reads index written by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ArchiveError> {
+        let mut i = 0;
+        let mut take = |n: usize| {
+            let taken = bytes.get(i..i + n).ok_or(ArchiveError::Truncated);
+            i += n;
+            taken
+        };
+
+        let entries_num = u32::from_be_bytes(take(4)?.try_into().unwrap());
+        let mut entries = Vec::new();
+        for entry in 0..entries_num as usize {
+            let name_len = u16::from_be_bytes(take(NAME_LEN_BYTES)?.try_into().unwrap());
+            let name = String::from_utf8(take(name_len as usize)?.to_vec())
+                .map_err(|_| ArchiveError::InvalidName { entry })?;
+            let offset = u64::from_be_bytes(take(8)?.try_into().unwrap());
+            let len = u64::from_be_bytes(take(8)?.try_into().unwrap());
+            entries.push(IndexEntry { name, offset, len });
+        }
+        Ok(ArchiveIndex { entries })
+    }
+
+    /// Cuts entries out of the decompressed `solid` data.
+    pub fn extract(&self, solid: &[u8]) -> Result<Vec<ArchiveEntry>, ArchiveError> {
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let data = entry
+                    .offset
+                    .checked_add(entry.len)
+                    .and_then(|end| solid.get(entry.offset as usize..end as usize))
+                    .ok_or(ArchiveError::EntryOutOfBounds { entry: i })?;
+                Ok(ArchiveEntry {
+                    name: entry.name.clone(),
+                    data: data.to_vec(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Lowercase extension of the last component of `name`, empty if it has none.
+fn extension(name: &str) -> String {
+    let file_name = name.rsplit('/').next().unwrap_or(name);
+    match file_name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => extension.to_lowercase(),
+        _ => String::new(),
+    }
+}
+
+/// Smallest hash of strings of `SHINGLE_LEN` bytes in `data`. Two files get the same one
+/// with probability equal to the fraction of such strings they share, so sorting by it
+/// puts near-identical files together.
+fn min_hash(data: &[u8]) -> u64 {
+    let hash = |shingle: &[u8]| {
+        let mut word = [0u8; SHINGLE_LEN];
+        word[..shingle.len()].copy_from_slice(shingle);
+        u64::from_le_bytes(word).wrapping_mul(SHINGLE_HASH_MUL)
+    };
+    match data.len() < SHINGLE_LEN {
+        true => hash(data),
+        false => data.windows(SHINGLE_LEN).map(hash).min().unwrap(),
+    }
+}
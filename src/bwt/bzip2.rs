@@ -0,0 +1,467 @@
+const STREAM_MAGIC: u64 = 0x42_5A_68;
+const STREAM_MAGIC_BITS: u32 = 24;
+const BLOCK_MAGIC: u64 = 0x3141_5926_5359;
+const END_MAGIC: u64 = 0x1772_4538_5090;
+const MAGIC_BITS: u32 = 48;
+/// Block size written, in units of 100 000 bytes, the largest allowed.
+const BLOCK_SIZE_LEVEL: u8 = 9;
+const BLOCK_SIZE_UNIT: usize = 100_000;
+/// Bytes bzip2 keeps free in every block after the initial run-length encoding.
+const BLOCK_OVERHEAD: usize = 19;
+/// Bytes of a run written before its length, and the longest run written at once.
+const RUN_PREFIX_LEN: usize = 4;
+const MAX_RUN_LEN: usize = 255;
+const RUN_A: u16 = 0;
+const RUN_B: u16 = 1;
+/// Symbols coded with the table of a single selector.
+const GROUP_LEN: usize = 50;
+const GROUPS_NUM: usize = 2;
+const MAX_GROUPS_NUM: usize = 6;
+/// Longest code written, as by bzip2, and read.
+const MAX_CODE_LEN: u8 = 17;
+const MAX_DECODED_CODE_LEN: u64 = 20;
+const CRC_POLYNOMIAL: u32 = 0x04C1_1DB7;
+const CRC_TABLE: [u32; 256] = make_crc_table();
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::iter;
+
+use bit_vec::BitVec;
+use rayon::prelude::*;
+
+use super::BwtCompressor;
+use crate::error::DecodeError;
+use crate::huffman::utils::push_bits;
+use crate::huffman::{BitReader, HuffmanDecoder};
+use crate::limits::{DecompressLimits, OutputBudget};
+
+/// Non-reflected CRC-32 of bzip2, processing bits of every byte from the highest.
+const fn make_crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u32) << 24;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = match crc & 0x8000_0000 {
+                0 => crc << 1,
+                _ => (crc << 1) ^ CRC_POLYNOMIAL,
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+fn block_crc(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, &byte| {
+        (crc << 8) ^ CRC_TABLE[((crc >> 24) as u8 ^ byte) as usize]
+    })
+}
+
+/// Reads and writes `.bz2` files, compatible with bzip2. Blocks of up to 900 kB go
+/// through the same transforms as the `bwt`, `mtf` and `zrle` stages: runs of 4 to 255
+/// bytes are shortened, then the Burrows-Wheeler and move-to-front transforms are applied,
+/// runs of zeros are written in bijective base 2 and symbols are Huffman coded.
+/// Written blocks use a single code table, while read ones may use up to 6 of them.
+#[derive(Clone, Copy, Default)]
+pub struct Bzip2;
+
+impl Bzip2 {
+    pub fn compress(bytes: &[u8]) -> Vec<u8> {
+        let blocks: Vec<(u32, BitVec)> = Bzip2::split_blocks(bytes)
+            .into_par_iter()
+            .map(|(block, original)| {
+                let crc = block_crc(original);
+                let mut bits = BitVec::new();
+                Bzip2::write_block(&mut bits, &block, crc);
+                (crc, bits)
+            })
+            .collect();
+
+        let mut bits = BitVec::new();
+        push_bits(&mut bits, STREAM_MAGIC, STREAM_MAGIC_BITS);
+        push_bits(&mut bits, (b'0' + BLOCK_SIZE_LEVEL) as u64, 8);
+        let mut combined_crc = 0u32;
+        for (crc, block) in blocks {
+            combined_crc = combined_crc.rotate_left(1) ^ crc;
+            bits.extend(block.iter());
+        }
+        push_bits(&mut bits, END_MAGIC, MAGIC_BITS);
+        push_bits(&mut bits, combined_crc as u64, 32);
+        bits.to_bytes()
+    }
+
+    /// Decompresses concatenated bzip2 streams, failing once decompressed data
+    /// would exceed `limits`.
+    pub fn decompress(bytes: &[u8], limits: &DecompressLimits) -> Result<Vec<u8>, DecodeError> {
+        let limits = limits.with_max_output_len(limits.output_limit(bytes.len()));
+        let mut budget = OutputBudget::new(&limits);
+        let mut reader = BitReader::new(bytes, bytes.len() * 8);
+        let mut output = Vec::new();
+        while !reader.is_empty() {
+            if reader.try_read(STREAM_MAGIC_BITS)? != STREAM_MAGIC {
+                return Err(DecodeError::new("bzip2 stream header is missing"));
+            }
+            let level = reader.try_read(8)? as u8;
+            if !(b'1'..=b'9').contains(&level) {
+                return Err(DecodeError::new("bzip2 block size is invalid"));
+            }
+            let max_block_len = (level - b'0') as usize * BLOCK_SIZE_UNIT;
+
+            let mut combined_crc = 0u32;
+            loop {
+                match reader.try_read(MAGIC_BITS)? {
+                    BLOCK_MAGIC => {
+                        let crc = reader.try_read(32)? as u32;
+                        let block = Bzip2::read_block(&mut reader, max_block_len)?;
+                        let start = output.len();
+                        undo_initial_runs(&block, &mut output, &mut budget)?;
+                        if block_crc(&output[start..]) != crc {
+                            return Err(DecodeError::new("bzip2 block checksum does not match"));
+                        }
+                        combined_crc = combined_crc.rotate_left(1) ^ crc;
+                    }
+                    END_MAGIC => {
+                        if reader.try_read(32)? as u32 != combined_crc {
+                            return Err(DecodeError::new("bzip2 stream checksum does not match"));
+                        }
+                        break;
+                    }
+                    _ => return Err(DecodeError::new("bzip2 block header is missing")),
+                }
+            }
+            reader.consume((8 - reader.position() % 8) % 8);
+        }
+
+        Ok(output)
+    }
+
+    /// Blocks after the initial run-length encoding, with the bytes they encode.
+    fn split_blocks(bytes: &[u8]) -> Vec<(Vec<u8>, &[u8])> {
+        let max_block_len = BLOCK_SIZE_LEVEL as usize * BLOCK_SIZE_UNIT - BLOCK_OVERHEAD;
+        let mut blocks = Vec::new();
+        let mut block = Vec::new();
+        let mut start = 0;
+        let mut i = 0;
+        while i < bytes.len() {
+            let run_len = bytes[i..]
+                .iter()
+                .take(MAX_RUN_LEN)
+                .take_while(|byte| **byte == bytes[i])
+                .count();
+            let encoded_len = match run_len >= RUN_PREFIX_LEN {
+                true => RUN_PREFIX_LEN + 1,
+                false => run_len,
+            };
+            if block.len() + encoded_len > max_block_len {
+                blocks.push((std::mem::take(&mut block), &bytes[start..i]));
+                start = i;
+            }
+            block.extend(iter::repeat_n(bytes[i], run_len.min(RUN_PREFIX_LEN)));
+            if run_len >= RUN_PREFIX_LEN {
+                block.push((run_len - RUN_PREFIX_LEN) as u8);
+            }
+            i += run_len;
+        }
+        if !block.is_empty() {
+            blocks.push((block, &bytes[start..]));
+        }
+        blocks
+    }
+
+    fn write_block(bits: &mut BitVec, block: &[u8], crc: u32) {
+        push_bits(bits, BLOCK_MAGIC, MAGIC_BITS);
+        push_bits(bits, crc as u64, 32);
+        bits.push(false);
+        let (last, primary_index) = BwtCompressor::forward(block);
+        push_bits(bits, primary_index as u64, 24);
+
+        let mut used = [false; 256];
+        block.iter().for_each(|&byte| used[byte as usize] = true);
+        let ranges: Vec<&[bool]> = used.chunks(16).collect();
+        ranges
+            .iter()
+            .for_each(|range| bits.push(range.contains(&true)));
+        for range in ranges.iter().filter(|range| range.contains(&true)) {
+            range.iter().for_each(|&is_used| bits.push(is_used));
+        }
+
+        let used_bytes: Vec<u8> = (0..=255).filter(|&byte| used[byte as usize]).collect();
+        let symbols = mtf_symbols(&last, used_bytes);
+        let alphabet_len = used.iter().filter(|&&is_used| is_used).count() + 2;
+        let lengths = code_lengths(&symbols, alphabet_len);
+
+        push_bits(bits, GROUPS_NUM as u64, 3);
+        push_bits(bits, symbols.len().div_ceil(GROUP_LEN) as u64, 15);
+        (0..symbols.len().div_ceil(GROUP_LEN)).for_each(|_| bits.push(false));
+        for _ in 0..GROUPS_NUM {
+            let mut len = lengths[0];
+            push_bits(bits, len as u64, 5);
+            for &symbol_len in lengths.iter() {
+                while len != symbol_len {
+                    bits.push(true);
+                    bits.push(len > symbol_len);
+                    len = match len > symbol_len {
+                        true => len - 1,
+                        false => len + 1,
+                    };
+                }
+                bits.push(false);
+            }
+        }
+
+        let codes = canonical_codes(&lengths);
+        for symbol in symbols {
+            let symbol = symbol as usize;
+            push_bits(bits, codes[symbol] as u64, lengths[symbol] as u32);
+        }
+    }
+
+    /// Last bytes of sorted rotations of the block, with the index of the block among them.
+    fn read_block(reader: &mut BitReader, max_block_len: usize) -> Result<Vec<u8>, DecodeError> {
+        if reader.try_read(1)? == 1 {
+            return Err(DecodeError::new(
+                "randomized bzip2 blocks are not supported",
+            ));
+        }
+        let primary_index = reader.try_read(24)? as usize;
+
+        let ranges = reader.try_read(16)?;
+        let mut list = Vec::new();
+        for range in (0..16).filter(|range| ranges >> (15 - range) & 1 == 1) {
+            let used = reader.try_read(16)?;
+            let bytes = (0..16).filter(|byte| used >> (15 - byte) & 1 == 1);
+            list.extend(bytes.map(|byte| (range * 16 + byte) as u8));
+        }
+        if list.is_empty() {
+            return Err(DecodeError::new("bzip2 block uses no bytes"));
+        }
+        let alphabet_len = list.len() + 2;
+
+        let groups_num = reader.try_read(3)? as usize;
+        if !(GROUPS_NUM..=MAX_GROUPS_NUM).contains(&groups_num) {
+            return Err(DecodeError::new("number of bzip2 code tables is invalid"));
+        }
+        let selectors = read_selectors(reader, groups_num)?;
+        let decoders = (0..groups_num)
+            .map(|_| read_decoder(reader, alphabet_len))
+            .collect::<Result<Vec<HuffmanDecoder<u16>>, DecodeError>>()?;
+
+        let end_of_block = alphabet_len as u16 - 1;
+        let mut last = Vec::new();
+        let mut run_len = 0;
+        let mut run_bit = 0;
+        for i in 0.. {
+            let group = selectors
+                .get(i / GROUP_LEN)
+                .ok_or_else(|| DecodeError::new("bzip2 block has too few selectors"))?;
+            let symbol = decoders[*group]
+                .decode(reader)
+                .ok_or_else(|| DecodeError::new("invalid bzip2 code"))?;
+            if symbol == RUN_A || symbol == RUN_B {
+                run_len += ((symbol + 1) as usize) << run_bit;
+                run_bit += 1;
+                if run_len > max_block_len {
+                    return Err(DecodeError::new("bzip2 block is too long"));
+                }
+                continue;
+            }
+
+            last.extend(iter::repeat_n(list[0], run_len));
+            (run_len, run_bit) = (0, 0);
+            if symbol == end_of_block {
+                break;
+            }
+            let position = symbol as usize - 1;
+            let byte = list[position];
+            list.copy_within(..position, 1);
+            list[0] = byte;
+            last.push(byte);
+            if last.len() > max_block_len {
+                return Err(DecodeError::new("bzip2 block is too long"));
+            }
+        }
+
+        if last.is_empty() || last.len() > max_block_len {
+            return Err(DecodeError::new("bzip2 block length is invalid"));
+        }
+        BwtCompressor::inverse(&last, primary_index)
+    }
+}
+
+/// Symbols of the move-to-front transform of `last` over `list` of bytes used,
+/// with runs of zeros in bijective base 2 of `RUN_A` and `RUN_B`, ended by end of block.
+fn mtf_symbols(last: &[u8], mut list: Vec<u8>) -> Vec<u16> {
+    let end_of_block = list.len() as u16 + 1;
+    let mut symbols = Vec::new();
+    let mut run_len = 0;
+    for &byte in last {
+        let position = list.iter().position(|&value| value == byte).unwrap();
+        if position == 0 {
+            run_len += 1;
+            continue;
+        }
+        push_run(&mut symbols, run_len);
+        run_len = 0;
+        list.copy_within(..position, 1);
+        list[0] = byte;
+        symbols.push(position as u16 + 1);
+    }
+    push_run(&mut symbols, run_len);
+    symbols.push(end_of_block);
+    symbols
+}
+
+fn push_run(symbols: &mut Vec<u16>, mut run_len: usize) {
+    while run_len > 0 {
+        run_len -= 1;
+        symbols.push(match run_len & 1 {
+            0 => RUN_A,
+            _ => RUN_B,
+        });
+        run_len >>= 1;
+    }
+}
+
+/// Lengths of Huffman codes of all symbols of the alphabet, at most `MAX_CODE_LEN`.
+/// Counts are halved until the codes fit, like bzip2 does.
+fn code_lengths(symbols: &[u16], alphabet_len: usize) -> Vec<u8> {
+    let mut counts = vec![0usize; alphabet_len];
+    symbols
+        .iter()
+        .for_each(|&symbol| counts[symbol as usize] += 1);
+    loop {
+        let mut heap: BinaryHeap<Reverse<(usize, usize)>> = counts
+            .iter()
+            .enumerate()
+            .map(|(symbol, &count)| Reverse((count.max(1), symbol)))
+            .collect();
+        let mut parents: Vec<usize> = vec![usize::MAX; alphabet_len];
+        while heap.len() > 1 {
+            let Reverse((left_count, left)) = heap.pop().unwrap();
+            let Reverse((right_count, right)) = heap.pop().unwrap();
+            parents[left] = parents.len();
+            parents[right] = parents.len();
+            heap.push(Reverse((left_count + right_count, parents.len())));
+            parents.push(usize::MAX);
+        }
+
+        let lengths: Vec<u8> = (0..alphabet_len)
+            .map(|symbol| {
+                let mut len = 0;
+                let mut node = symbol;
+                while parents[node] != usize::MAX {
+                    node = parents[node];
+                    len += 1;
+                }
+                len
+            })
+            .collect();
+        if lengths.iter().all(|&len| len <= MAX_CODE_LEN) {
+            return lengths;
+        }
+        counts.iter_mut().for_each(|count| *count = 1 + *count / 2);
+    }
+}
+
+/// Codes given to symbols in order of their lengths, then of their values.
+fn canonical_codes(lengths: &[u8]) -> Vec<u32> {
+    let mut codes = vec![0; lengths.len()];
+    let mut code = 0;
+    for len in 1..=lengths.iter().copied().max().unwrap_or(0) {
+        for (symbol, _) in lengths.iter().enumerate().filter(|(_, &l)| l == len) {
+            codes[symbol] = code;
+            code += 1;
+        }
+        code <<= 1;
+    }
+    codes
+}
+
+/// Code tables of groups of symbols, written as their moves to the front of a list.
+fn read_selectors(reader: &mut BitReader, groups_num: usize) -> Result<Vec<usize>, DecodeError> {
+    let selectors_num = reader.try_read(15)?;
+    if selectors_num == 0 {
+        return Err(DecodeError::new("bzip2 block has no selectors"));
+    }
+    let mut list: Vec<usize> = (0..groups_num).collect();
+    (0..selectors_num)
+        .map(|_| {
+            let mut position = 0;
+            while reader.try_read(1)? == 1 {
+                position += 1;
+                if position >= groups_num {
+                    return Err(DecodeError::new("bzip2 selector is invalid"));
+                }
+            }
+            let group = list.remove(position);
+            list.insert(0, group);
+            Ok(group)
+        })
+        .collect()
+}
+
+/// Decoder of code lengths written as differences from the previous one.
+fn read_decoder(
+    reader: &mut BitReader,
+    alphabet_len: usize,
+) -> Result<HuffmanDecoder<u16>, DecodeError> {
+    let mut len = reader.try_read(5)?;
+    let mut lengths = Vec::with_capacity(alphabet_len);
+    for _ in 0..alphabet_len {
+        loop {
+            if !(1..=MAX_DECODED_CODE_LEN).contains(&len) {
+                return Err(DecodeError::new("bzip2 code length is invalid"));
+            }
+            if reader.try_read(1)? == 0 {
+                break;
+            }
+            match reader.try_read(1)? {
+                0 => len += 1,
+                _ => len -= 1,
+            }
+        }
+        lengths.push(len as u8);
+    }
+
+    let codes: HashMap<u16, BitVec> = canonical_codes(&lengths)
+        .into_iter()
+        .zip(lengths)
+        .enumerate()
+        .map(|(symbol, (code, len))| {
+            let mut bits = BitVec::new();
+            push_bits(&mut bits, code as u64, len as u32);
+            (symbol as u16, bits)
+        })
+        .collect();
+    Ok(HuffmanDecoder::new(&codes))
+}
+
+/// Expands runs of 4 equal bytes followed by the number of their further repeats.
+fn undo_initial_runs(
+    block: &[u8],
+    output: &mut Vec<u8>,
+    budget: &mut OutputBudget,
+) -> Result<(), DecodeError> {
+    let mut run_len = 0;
+    let mut previous = None;
+    for &byte in block {
+        if run_len == RUN_PREFIX_LEN {
+            budget.take_bytes(byte as usize)?;
+            output.extend(iter::repeat_n(previous.unwrap_or(0), byte as usize));
+            run_len = 0;
+            continue;
+        }
+        match previous == Some(byte) {
+            true => run_len += 1,
+            false => (previous, run_len) = (Some(byte), 1),
+        }
+        budget.take_bytes(1)?;
+        output.push(byte);
+    }
+    Ok(())
+}
@@ -0,0 +1,144 @@
+const PRIMARY_INDEX_BYTES: usize = 4;
+/// Bytes rotations are sorted by before doubling.
+const INITIAL_PREFIX_LEN: usize = 4;
+
+use rayon::prelude::*;
+
+use crate::error::DecodeError;
+use crate::limits::DecompressLimits;
+
+/// Burrows-Wheeler transform of the whole input, as in bzip2: rotations of the input
+/// are sorted and the last byte of every one is written, preceded by 4 bytes of index
+/// of the input among the sorted rotations. Bytes followed by the same context end up
+/// next to each other, so `MoveToFront` turns them into runs of small values.
+#[derive(Clone, Copy, Default)]
+pub struct BwtCompressor;
+
+impl BwtCompressor {
+    pub fn compress(bytes: &[u8]) -> Vec<u8> {
+        let (last, primary_index) = BwtCompressor::forward(bytes);
+        let mut compressed = (primary_index as u32).to_be_bytes().to_vec();
+        compressed.extend(last);
+        compressed
+    }
+
+    /// Reverts `compress`, failing on data longer than `limits` or an invalid index.
+    pub fn decompress(bytes: &[u8], limits: &DecompressLimits) -> Result<Vec<u8>, DecodeError> {
+        let (index, last) = bytes
+            .split_at_checked(PRIMARY_INDEX_BYTES)
+            .ok_or_else(|| DecodeError::new("index of the transform is missing"))?;
+        if last.len() as u64 > limits.max_output_len() {
+            return Err(DecodeError::new("decoded data exceeds the output limit"));
+        }
+        let primary_index = u32::from_be_bytes(index.try_into().unwrap()) as usize;
+        BwtCompressor::inverse(last, primary_index)
+    }
+
+    /// Last bytes of the sorted rotations of `bytes` and the index of `bytes` among them.
+    pub fn forward(bytes: &[u8]) -> (Vec<u8>, usize) {
+        let n = bytes.len();
+        let order = BwtCompressor::sorted_rotations(bytes);
+        let last = order
+            .iter()
+            .map(|&start| bytes[(start as usize + n - 1) % n])
+            .collect();
+        let primary_index = order.iter().position(|&start| start == 0).unwrap_or(0);
+        (last, primary_index)
+    }
+
+    /// Reverts `forward`, following every rotation to the one starting a byte later.
+    pub fn inverse(last: &[u8], primary_index: usize) -> Result<Vec<u8>, DecodeError> {
+        if last.is_empty() {
+            return Ok(Vec::new());
+        }
+        if primary_index >= last.len() {
+            return Err(DecodeError::new("index of the transform is out of range"));
+        }
+
+        let mut starts = [0usize; 256];
+        last.iter().for_each(|&byte| starts[byte as usize] += 1);
+        let mut sum = 0;
+        for start in starts.iter_mut() {
+            (*start, sum) = (sum, sum + *start);
+        }
+        let mut first = vec![0u8; last.len()];
+        let mut next = vec![0u32; last.len()];
+        for (i, &byte) in last.iter().enumerate() {
+            let row = &mut starts[byte as usize];
+            first[*row] = byte;
+            next[*row] = i as u32;
+            *row += 1;
+        }
+
+        let mut row = primary_index;
+        Ok((0..last.len())
+            .map(|_| {
+                let byte = first[row];
+                row = next[row] as usize;
+                byte
+            })
+            .collect())
+    }
+
+    /// Starts of rotations in sorted order, by prefix doubling: rotations sorted by their
+    /// first `k` bytes are sorted by `2k` within every group of equal ones, by the rank
+    /// of the group of the rotation starting `k` bytes later, until no group is left.
+    /// Equal rotations of periodic inputs stay in one group, which the transform allows.
+    fn sorted_rotations(bytes: &[u8]) -> Vec<u32> {
+        let n = bytes.len();
+        let prefix = |start: usize| {
+            (0..INITIAL_PREFIX_LEN).fold(0u32, |prefix, i| {
+                (prefix << 8) | bytes[(start + i) % n] as u32
+            })
+        };
+        let mut order: Vec<(u64, u32)> = (0..n).map(|i| (prefix(i) as u64, i as u32)).collect();
+        order.par_sort_unstable();
+        let mut rank = vec![0u32; n];
+        let mut groups = Vec::new();
+        BwtCompressor::rank_groups(&order, 0, &mut rank, &mut groups);
+
+        let mut k = INITIAL_PREFIX_LEN;
+        while !groups.is_empty() && k < n {
+            let mut new_groups = Vec::new();
+            let mut new_ranks = Vec::new();
+            for (start, end) in groups {
+                let group = &mut order[start..end];
+                for (key, i) in group.iter_mut() {
+                    *key = rank[(*i as usize + k) % n] as u64;
+                }
+                group.sort_unstable();
+                new_ranks.push((start, end));
+            }
+            for (start, end) in new_ranks {
+                BwtCompressor::rank_groups(&order[start..end], start, &mut rank, &mut new_groups);
+            }
+            groups = new_groups;
+            k *= 2;
+        }
+
+        order.into_iter().map(|(_, start)| start).collect()
+    }
+
+    /// Ranks every rotation of sorted `order`, starting at `offset` of the whole order,
+    /// by the position of the first one with the same key, and adds groups of equal keys.
+    fn rank_groups(
+        order: &[(u64, u32)],
+        offset: usize,
+        rank: &mut [u32],
+        groups: &mut Vec<(usize, usize)>,
+    ) {
+        let mut group_start = 0;
+        for i in 0..order.len() {
+            if order[i].0 != order[group_start].0 {
+                if i - group_start > 1 {
+                    groups.push((offset + group_start, offset + i));
+                }
+                group_start = i;
+            }
+            rank[order[i].1 as usize] = (offset + group_start) as u32;
+        }
+        if order.len() - group_start > 1 {
+            groups.push((offset + group_start, offset + order.len()));
+        }
+    }
+}
@@ -0,0 +1,9 @@
+pub mod bzip2;
+pub mod implementation;
+pub mod mtf;
+pub mod zero_run;
+
+pub use bzip2::Bzip2;
+pub use implementation::BwtCompressor;
+pub use mtf::MoveToFront;
+pub use zero_run::ZeroRunCompressor;
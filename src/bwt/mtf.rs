@@ -0,0 +1,37 @@
+/// Move-to-front transform: every byte is replaced by its position in a list of all
+/// byte values, then moved to the front of the list. Bytes repeated close to each other,
+/// as in the output of `BwtCompressor`, become small values and runs of zeros.
+#[derive(Clone, Copy, Default)]
+pub struct MoveToFront;
+
+impl MoveToFront {
+    pub fn encode(bytes: &[u8]) -> Vec<u8> {
+        let mut list = MoveToFront::initial_list();
+        bytes
+            .iter()
+            .map(|&byte| {
+                let position = list.iter().position(|&value| value == byte).unwrap();
+                list.copy_within(..position, 1);
+                list[0] = byte;
+                position as u8
+            })
+            .collect()
+    }
+
+    pub fn decode(bytes: &[u8]) -> Vec<u8> {
+        let mut list = MoveToFront::initial_list();
+        bytes
+            .iter()
+            .map(|&position| {
+                let byte = list[position as usize];
+                list.copy_within(..position as usize, 1);
+                list[0] = byte;
+                byte
+            })
+            .collect()
+    }
+
+    fn initial_list() -> [u8; 256] {
+        std::array::from_fn(|i| i as u8)
+    }
+}
@@ -0,0 +1,14 @@
+use crate::lz77::implementation::ResultEncodingVec;
+use crate::lz77::RleCompressor;
+
+/// Runs of zeros, left by `MoveToFront` wherever a byte repeats, written as a zero
+/// followed by a reference of distance 1, like bzip2 codes only runs of zeros.
+/// Other bytes stay literals, so their statistics are not mixed with run lengths.
+#[derive(Clone, Copy, Default)]
+pub struct ZeroRunCompressor;
+
+impl ZeroRunCompressor {
+    pub fn compress_to_tokens(bytes: &[u8]) -> ResultEncodingVec {
+        RleCompressor::compress_runs_of(bytes, |byte| byte == 0)
+    }
+}
@@ -0,0 +1,156 @@
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+const SLICES_NUM: usize = 8;
+
+#[cfg(target_arch = "x86_64")]
+use super::pclmul;
+
+const TABLES: [[u32; 256]; SLICES_NUM] = make_tables();
+const X_POW_2_POW_N: [u32; 32] = make_x_pow_2_pow_n();
+
+/// `TABLES[0]` is the classic byte-wise table, `TABLES[k][i]` is the CRC of byte `i`
+/// followed by `k` zero bytes, so 8 bytes can be processed with 8 independent lookups.
+const fn make_tables() -> [[u32; 256]; SLICES_NUM] {
+    let mut tables = [[0; 256]; SLICES_NUM];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ POLYNOMIAL,
+                _ => crc >> 1,
+            };
+            bit += 1;
+        }
+        tables[0][i] = crc;
+        i += 1;
+    }
+
+    let mut k = 1;
+    while k < SLICES_NUM {
+        let mut i = 0;
+        while i < 256 {
+            let prev = tables[k - 1][i];
+            tables[k][i] = (prev >> 8) ^ tables[0][(prev & 0xFF) as usize];
+            i += 1;
+        }
+        k += 1;
+    }
+
+    tables
+}
+
+/// Product of polynomials `a` and `b` modulo the CRC polynomial, in the reflected notation.
+const fn multiply_mod_p(a: u32, mut b: u32) -> u32 {
+    let mut product = 0;
+    let mut m = 1 << 31;
+    while m != 0 {
+        if a & m != 0 {
+            product ^= b;
+        }
+        m >>= 1;
+        b = match b & 1 {
+            1 => (b >> 1) ^ POLYNOMIAL,
+            _ => b >> 1,
+        };
+    }
+
+    product
+}
+
+/// `x^(2^n)` modulo the CRC polynomial.
+const fn make_x_pow_2_pow_n() -> [u32; 32] {
+    let mut table = [0; 32];
+    let mut p = 1 << 30;
+    let mut n = 0;
+    while n < 32 {
+        table[n] = p;
+        p = multiply_mod_p(p, p);
+        n += 1;
+    }
+
+    table
+}
+
+/// CRC-32 with the reflected polynomial used by gzip, ZIP and PNG.
+///
+/// Bytes are processed 8 at a time with slicing-by-8 tables, or with carry-less
+/// multiplication on x86_64 processors supporting PCLMULQDQ.
+#[derive(Clone, Copy)]
+pub struct Crc32 {
+    crc: u32,
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Crc32 {
+    pub fn new() -> Self {
+        Crc32 { crc: u32::MAX }
+    }
+
+    pub fn checksum(bytes: &[u8]) -> u32 {
+        let mut crc = Crc32::new();
+        crc.update(bytes);
+        crc.finalize()
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        #[cfg(target_arch = "x86_64")]
+        if pclmul::is_supported() && bytes.len() >= pclmul::MIN_LEN {
+            self.crc = pclmul::update(self.crc, bytes);
+            return;
+        }
+
+        self.crc = Crc32::update_sliced(self.crc, bytes);
+    }
+
+    pub fn finalize(&self) -> u32 {
+        !self.crc
+    }
+
+    /// CRC of two concatenated sequences, given their CRCs and the length of the second one.
+    pub fn combine(crc1: u32, crc2: u32, len2: u64) -> u32 {
+        let mut x_pow = 1 << 31;
+        let mut n = len2;
+        let mut k = 3;
+        while n != 0 {
+            if n & 1 == 1 {
+                x_pow = multiply_mod_p(X_POW_2_POW_N[k & 31], x_pow);
+            }
+            n >>= 1;
+            k += 1;
+        }
+
+        multiply_mod_p(x_pow, crc1) ^ crc2
+    }
+
+    pub(super) fn update_sliced(mut crc: u32, bytes: &[u8]) -> u32 {
+        let mut chunks = bytes.chunks_exact(SLICES_NUM);
+        for chunk in chunks.by_ref() {
+            let low = crc ^ u32::from_le_bytes(chunk[..4].try_into().unwrap());
+            let high = u32::from_le_bytes(chunk[4..].try_into().unwrap());
+            crc = TABLES[7][(low & 0xFF) as usize]
+                ^ TABLES[6][((low >> 8) & 0xFF) as usize]
+                ^ TABLES[5][((low >> 16) & 0xFF) as usize]
+                ^ TABLES[4][(low >> 24) as usize]
+                ^ TABLES[3][(high & 0xFF) as usize]
+                ^ TABLES[2][((high >> 8) & 0xFF) as usize]
+                ^ TABLES[1][((high >> 16) & 0xFF) as usize]
+                ^ TABLES[0][(high >> 24) as usize];
+        }
+
+        Crc32::update_bytewise(crc, chunks.remainder())
+    }
+
+    pub(super) fn update_bytewise(mut crc: u32, bytes: &[u8]) -> u32 {
+        for &byte in bytes {
+            crc = (crc >> 8) ^ TABLES[0][((crc ^ byte as u32) & 0xFF) as usize];
+        }
+
+        crc
+    }
+}
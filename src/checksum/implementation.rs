@@ -0,0 +1,60 @@
+const CHECKSUM_LEN: usize = 4;
+
+use serde::{Deserialize, Serialize};
+
+use super::{crc32::Crc32, xxhash::xxh32};
+
+/// Checksum written after every frame of uncompressed data and after the header.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Checksum {
+    None,
+    #[default]
+    Crc32,
+    XxHash32,
+}
+
+impl Checksum {
+    pub fn id(&self) -> u8 {
+        match self {
+            Checksum::None => 0,
+            Checksum::Crc32 => 1,
+            Checksum::XxHash32 => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Checksum::None),
+            1 => Some(Checksum::Crc32),
+            2 => Some(Checksum::XxHash32),
+            _ => None,
+        }
+    }
+
+    /// Number of bytes taken by the checksum.
+    pub fn bytes_num(&self) -> usize {
+        match self {
+            Checksum::None => 0,
+            _ => CHECKSUM_LEN,
+        }
+    }
+
+    pub fn compute(&self, bytes: &[u8]) -> u32 {
+        match self {
+            Checksum::None => 0,
+            Checksum::Crc32 => Crc32::checksum(bytes),
+            Checksum::XxHash32 => xxh32(bytes, 0),
+        }
+    }
+
+    pub fn append(&self, bytes: &mut Vec<u8>, checked: &[u8]) {
+        let checksum = self.compute(checked).to_be_bytes();
+        bytes.extend(&checksum[..self.bytes_num()]);
+    }
+
+    /// Checks value written by `append`.
+    pub fn verify(&self, checksum: &[u8], checked: &[u8]) -> bool {
+        checksum == &self.compute(checked).to_be_bytes()[..self.bytes_num()]
+    }
+}
@@ -0,0 +1,9 @@
+pub mod crc32;
+pub mod implementation;
+#[cfg(target_arch = "x86_64")]
+mod pclmul;
+pub mod xxhash;
+
+pub use crc32::Crc32;
+pub use implementation::Checksum;
+pub use xxhash::xxh32;
@@ -0,0 +1,83 @@
+//! CRC-32 folding with carry-less multiplication, following Intel's
+//! "Fast CRC Computation for Generic Polynomials Using PCLMULQDQ Instruction".
+
+pub const MIN_LEN: usize = 128;
+
+const K1: i64 = 0x1_5444_2bd4;
+const K2: i64 = 0x1_c6e4_1596;
+const K3: i64 = 0x1_7519_97d0;
+const K4: i64 = 0x0_ccaa_009e;
+const K5: i64 = 0x1_63cd_6124;
+const P_X: i64 = 0x1_DB71_0641;
+const U_PRIME: i64 = 0x1_F701_1641;
+const LANE_LEN: usize = 16;
+
+use std::arch::x86_64::*;
+
+use super::crc32::Crc32;
+
+pub fn is_supported() -> bool {
+    is_x86_feature_detected!("pclmulqdq") && is_x86_feature_detected!("sse4.1")
+}
+
+/// Updates non-finalized `crc` with `bytes`, which have to be at least `MIN_LEN` long.
+pub fn update(crc: u32, bytes: &[u8]) -> u32 {
+    assert!(is_supported() && bytes.len() >= MIN_LEN);
+    // SAFETY: required processor features are checked above.
+    unsafe { update_folded(crc, bytes) }
+}
+
+#[target_feature(enable = "pclmulqdq,sse2,sse4.1")]
+unsafe fn update_folded(crc: u32, mut bytes: &[u8]) -> u32 {
+    let mut x3 = next_lane(&mut bytes);
+    let mut x2 = next_lane(&mut bytes);
+    let mut x1 = next_lane(&mut bytes);
+    let mut x0 = next_lane(&mut bytes);
+    x3 = _mm_xor_si128(x3, _mm_cvtsi32_si128(crc as i32));
+
+    let k1k2 = _mm_set_epi64x(K2, K1);
+    while bytes.len() >= 4 * LANE_LEN {
+        x3 = fold(x3, next_lane(&mut bytes), k1k2);
+        x2 = fold(x2, next_lane(&mut bytes), k1k2);
+        x1 = fold(x1, next_lane(&mut bytes), k1k2);
+        x0 = fold(x0, next_lane(&mut bytes), k1k2);
+    }
+
+    let k3k4 = _mm_set_epi64x(K4, K3);
+    let mut x = fold(x3, x2, k3k4);
+    x = fold(x, x1, k3k4);
+    x = fold(x, x0, k3k4);
+    while bytes.len() >= LANE_LEN {
+        x = fold(x, next_lane(&mut bytes), k3k4);
+    }
+
+    // Reduction from 128 to 64 bits.
+    let low_32_mask = _mm_set_epi32(0, 0, 0, !0);
+    let x = _mm_xor_si128(_mm_clmulepi64_si128(x, k3k4, 0x10), _mm_srli_si128(x, 8));
+    let x = _mm_xor_si128(
+        _mm_clmulepi64_si128(_mm_and_si128(x, low_32_mask), _mm_set_epi64x(0, K5), 0x00),
+        _mm_srli_si128(x, 4),
+    );
+
+    // Barrett reduction from 64 to 32 bits.
+    let pu = _mm_set_epi64x(U_PRIME, P_X);
+    let t1 = _mm_clmulepi64_si128(_mm_and_si128(x, low_32_mask), pu, 0x10);
+    let t2 = _mm_clmulepi64_si128(_mm_and_si128(t1, low_32_mask), pu, 0x00);
+    let crc = _mm_extract_epi32(_mm_xor_si128(x, t2), 1) as u32;
+
+    Crc32::update_sliced(crc, bytes)
+}
+
+#[target_feature(enable = "pclmulqdq,sse2")]
+unsafe fn fold(a: __m128i, b: __m128i, keys: __m128i) -> __m128i {
+    let low = _mm_clmulepi64_si128(a, keys, 0x00);
+    let high = _mm_clmulepi64_si128(a, keys, 0x11);
+    _mm_xor_si128(_mm_xor_si128(b, low), high)
+}
+
+#[target_feature(enable = "sse2")]
+unsafe fn next_lane(bytes: &mut &[u8]) -> __m128i {
+    let lane = _mm_loadu_si128(bytes.as_ptr() as *const __m128i);
+    *bytes = &bytes[LANE_LEN..];
+    lane
+}
@@ -0,0 +1,64 @@
+const PRIME_1: u32 = 0x9E37_79B1;
+const PRIME_2: u32 = 0x85EB_CA77;
+const PRIME_3: u32 = 0xC2B2_AE3D;
+const PRIME_4: u32 = 0x27D4_EB2F;
+const PRIME_5: u32 = 0x1656_67B1;
+const STRIPE_LEN: usize = 16;
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[..4].try_into().unwrap())
+}
+
+fn round(acc: u32, lane: u32) -> u32 {
+    acc.wrapping_add(lane.wrapping_mul(PRIME_2))
+        .rotate_left(13)
+        .wrapping_mul(PRIME_1)
+}
+
+/// 32-bit xxHash of `bytes`.
+pub fn xxh32(bytes: &[u8], seed: u32) -> u32 {
+    let mut stripes = bytes.chunks_exact(STRIPE_LEN);
+    let mut hash = match bytes.len() >= STRIPE_LEN {
+        true => {
+            let mut acc = [
+                seed.wrapping_add(PRIME_1).wrapping_add(PRIME_2),
+                seed.wrapping_add(PRIME_2),
+                seed,
+                seed.wrapping_sub(PRIME_1),
+            ];
+            for stripe in stripes.by_ref() {
+                for (i, lane) in acc.iter_mut().enumerate() {
+                    *lane = round(*lane, read_u32(&stripe[4 * i..]));
+                }
+            }
+            acc[0]
+                .rotate_left(1)
+                .wrapping_add(acc[1].rotate_left(7))
+                .wrapping_add(acc[2].rotate_left(12))
+                .wrapping_add(acc[3].rotate_left(18))
+        }
+        false => seed.wrapping_add(PRIME_5),
+    };
+    hash = hash.wrapping_add(bytes.len() as u32);
+
+    let mut tail = stripes.remainder();
+    while tail.len() >= 4 {
+        hash = hash
+            .wrapping_add(read_u32(tail).wrapping_mul(PRIME_3))
+            .rotate_left(17)
+            .wrapping_mul(PRIME_4);
+        tail = &tail[4..];
+    }
+    for &byte in tail {
+        hash = hash
+            .wrapping_add((byte as u32).wrapping_mul(PRIME_5))
+            .rotate_left(11)
+            .wrapping_mul(PRIME_1);
+    }
+
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(PRIME_2);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(PRIME_3);
+    hash ^ (hash >> 16)
+}
@@ -0,0 +1,439 @@
+pub(crate) const MAX_WINDOW_SIZE: usize = 1 << 24;
+const DEFAULT_FRAME_SIZE: usize = 1 << 20;
+const MAX_FRAME_SIZE: usize = 1 << 30;
+/// Shortest match kept by `Strategy::Filtered`, shorter ones are written as literals.
+const FILTERED_MIN_MATCH_LEN: usize = 12;
+
+use std::{error::Error, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ans::AnsCompressor;
+use crate::checksum::Checksum;
+use crate::container::MAX_SPEC_LEN;
+use crate::huffman::{AdaptiveHuffmanCompressor, HuffmanCompressor};
+use crate::lz77::{CostModel, LZ77Compressor, LongRangeMatcher, RleCompressor};
+use crate::pipeline::{Codec, Pipeline, UnknownStageError};
+use crate::range_coder::RangeCompressor;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    WindowSize(usize),
+    MaxMatchLen {
+        max_match_len: usize,
+        window_size: usize,
+    },
+    EntropyCoder(usize),
+    Strategy(usize),
+    FrameSize(usize),
+    Checksum(usize),
+    UnknownStage(UnknownStageError),
+    SpecTooLong(usize),
+    PatchFiles,
+    ArchiveFiles,
+    Bzip2Files,
+    UnknownOption(String),
+    InvalidValue {
+        option: String,
+        value: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::WindowSize(window_size) => write!(
+                f,
+                "Window size {} is out of range 1..={}",
+                window_size, MAX_WINDOW_SIZE
+            ),
+            ConfigError::MaxMatchLen {
+                max_match_len,
+                window_size,
+            } => write!(
+                f,
+                "Max match length {} is out of range 1..={}, bounded by the window size",
+                max_match_len, window_size
+            ),
+            ConfigError::EntropyCoder(value) => write!(f, "Unknown entropy coder: {}", value),
+            ConfigError::Strategy(value) => write!(f, "Unknown strategy: {}", value),
+            ConfigError::FrameSize(frame_size) => write!(
+                f,
+                "Frame size {} is out of range 1..={}",
+                frame_size, MAX_FRAME_SIZE
+            ),
+            ConfigError::Checksum(value) => write!(f, "Unknown checksum: {}", value),
+            ConfigError::UnknownStage(err) => write!(f, "{}", err),
+            ConfigError::SpecTooLong(len) => write!(
+                f,
+                "Pipeline spec of {} bytes exceeds the limit of {}",
+                len, MAX_SPEC_LEN
+            ),
+            ConfigError::PatchFiles => write!(f, "-patch_from needs both -input and -output"),
+            ConfigError::Bzip2Files => write!(f, "-bz2 needs both -input and -output"),
+            ConfigError::UnknownOption(option) => write!(f, "Unknown option: {}", option),
+            ConfigError::InvalidValue { option, value } => {
+                write!(f, "Invalid value of {}: {}", option, value)
+            }
+            ConfigError::ArchiveFiles => {
+                write!(
+                    f,
+                    "-archive needs both -input and -output, -list needs -input"
+                )
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+/// Stages applied by the default pipeline, the first ones mirror zlib strategies:
+/// `Default`, `HuffmanOnly` (`Z_HUFFMAN_ONLY`), `Rle` (`Z_RLE`), `Filtered` (`Z_FILTERED`)
+/// and `Fixed` (`Z_FIXED`).
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Strategy {
+    /// LZ77 followed by the entropy coder.
+    #[default]
+    Default,
+    /// Entropy coder applied on literals only.
+    HuffmanOnly,
+    /// LZ77 tokens written in their ascii form.
+    Lz77Only,
+    /// Input copied without any transformation.
+    Stored,
+    /// Runs of a repeated byte, i.e. matches of distance 1 only, followed by the entropy coder.
+    Rle,
+    /// LZ77 keeping only matches of at least `FILTERED_MIN_MATCH_LEN` bytes, followed by
+    /// the entropy coder. Suits data like filtered images or numbers, where short matches
+    /// are mostly noise and cost more than the literals they replace.
+    Filtered,
+    /// LZ77 followed by Huffman codes forced to the predefined ones, whatever the entropy
+    /// coder. Predefined codes cover all literals, so only blocks of matches longer than
+    /// the predefined lengths get dynamic codes.
+    Fixed,
+    /// One of the above picked for every input by `InputAnalysis`. Pipeline built for it
+    /// on its own is the one of `Default`.
+    Auto,
+}
+
+impl Strategy {
+    pub fn from_stages(apply_lz77: bool, apply_entropy_coder: bool) -> Self {
+        match (apply_lz77, apply_entropy_coder) {
+            (true, true) => Strategy::Default,
+            (false, true) => Strategy::HuffmanOnly,
+            (true, false) => Strategy::Lz77Only,
+            (false, false) => Strategy::Stored,
+        }
+    }
+
+    /// Strategy given by its command line value.
+    pub fn from_param(value: usize) -> Result<Self, ConfigError> {
+        match value {
+            0 => Ok(Strategy::Default),
+            1 => Ok(Strategy::HuffmanOnly),
+            2 => Ok(Strategy::Lz77Only),
+            3 => Ok(Strategy::Stored),
+            4 => Ok(Strategy::Rle),
+            5 => Ok(Strategy::Filtered),
+            6 => Ok(Strategy::Fixed),
+            7 => Ok(Strategy::Auto),
+            _ => Err(ConfigError::Strategy(value)),
+        }
+    }
+
+    pub fn applies_lz77(&self) -> bool {
+        matches!(
+            self,
+            Strategy::Default
+                | Strategy::Lz77Only
+                | Strategy::Filtered
+                | Strategy::Fixed
+                | Strategy::Auto
+        )
+    }
+
+    pub fn applies_entropy_coder(&self) -> bool {
+        !matches!(self, Strategy::Lz77Only | Strategy::Stored)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntropyCoder {
+    #[default]
+    Huffman,
+    AdaptiveHuffman,
+    RangeCoder,
+    Rans,
+}
+
+impl EntropyCoder {
+    pub fn from_param(value: usize) -> Result<Self, ConfigError> {
+        match value {
+            0 => Ok(EntropyCoder::Huffman),
+            1 => Ok(EntropyCoder::AdaptiveHuffman),
+            2 => Ok(EntropyCoder::RangeCoder),
+            3 => Ok(EntropyCoder::Rans),
+            _ => Err(ConfigError::EntropyCoder(value)),
+        }
+    }
+
+    pub fn stage(&self, predefined_codes: bool) -> Box<dyn Codec> {
+        match self {
+            EntropyCoder::Huffman => Box::new(HuffmanCompressor::new(predefined_codes)),
+            EntropyCoder::AdaptiveHuffman => Box::new(AdaptiveHuffmanCompressor),
+            EntropyCoder::RangeCoder => Box::new(RangeCompressor),
+            EntropyCoder::Rans => Box::new(AnsCompressor),
+        }
+    }
+}
+
+/// Validated configuration of the compression, created with `DeflateConfig::builder()`.
+/// Deserialization goes through the builder, so it validates values as well.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(try_from = "DeflateConfigBuilder")]
+pub struct DeflateConfig {
+    window_size: usize,
+    max_match_len: Option<usize>,
+    strategy: Strategy,
+    entropy_coder: EntropyCoder,
+    predefined_codes: bool,
+    long_range: bool,
+    pipeline: Option<String>,
+    frame_size: usize,
+    checksum: Checksum,
+}
+
+impl Default for DeflateConfig {
+    fn default() -> Self {
+        DeflateConfig::builder().build().unwrap()
+    }
+}
+
+impl DeflateConfig {
+    pub fn builder() -> DeflateConfigBuilder {
+        DeflateConfigBuilder::new()
+    }
+
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    /// If not set, LZ77 picks square root of the window size.
+    pub fn max_match_len(&self) -> Option<usize> {
+        self.max_match_len
+    }
+
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    pub fn entropy_coder(&self) -> EntropyCoder {
+        self.entropy_coder
+    }
+
+    pub fn predefined_codes(&self) -> bool {
+        self.predefined_codes
+    }
+
+    /// Whether LZ77 also finds matches anywhere earlier in the frame, see `LongRangeMatcher`.
+    pub fn long_range(&self) -> bool {
+        self.long_range
+    }
+
+    /// Spec of a custom pipeline, which overrides `strategy` and `entropy_coder`.
+    pub fn pipeline(&self) -> Option<&str> {
+        self.pipeline.as_deref()
+    }
+
+    /// Size of independently compressed frames the input is split into.
+    pub fn frame_size(&self) -> usize {
+        self.frame_size
+    }
+
+    pub fn checksum(&self) -> Checksum {
+        self.checksum
+    }
+
+    /// Same config with `strategy` instead, e.g. the one picked for an input.
+    pub fn with_strategy(&self, strategy: Strategy) -> DeflateConfig {
+        DeflateConfig {
+            strategy,
+            ..self.clone()
+        }
+    }
+
+    pub fn lz77_compressor(&self) -> LZ77Compressor {
+        let cost_model = match self.strategy {
+            Strategy::Fixed => HuffmanCompressor::new(true).cost_model(),
+            strategy if strategy.applies_entropy_coder() => {
+                self.entropy_coder.stage(self.predefined_codes).cost_model()
+            }
+            _ => Some(CostModel::Ascii),
+        };
+        let mut compressor = LZ77Compressor::new(Some(self.window_size), self.max_match_len)
+            .with_cost_model(cost_model.unwrap_or_default());
+        if self.long_range {
+            compressor = compressor.with_long_range(LongRangeMatcher::default());
+        }
+        match self.strategy {
+            Strategy::Filtered => compressor.with_min_match_len(FILTERED_MIN_MATCH_LEN),
+            _ => compressor,
+        }
+    }
+
+    /// Pipeline given by the spec, or otherwise by the strategy and the entropy coder.
+    pub fn build_pipeline(&self) -> Pipeline<'static> {
+        if let Some(spec) = &self.pipeline {
+            return Pipeline::from_spec(spec, self).expect("Pipeline spec is validated on build.");
+        }
+
+        let mut pipeline = Pipeline::new();
+        if self.strategy.applies_lz77() {
+            pipeline.push(Box::new(self.lz77_compressor()));
+        } else if self.strategy == Strategy::Rle {
+            pipeline.push(Box::new(RleCompressor));
+        }
+        if self.strategy == Strategy::Fixed {
+            pipeline.push(Box::new(HuffmanCompressor::new(true)));
+        } else if self.strategy.applies_entropy_coder() {
+            pipeline.push(self.entropy_coder.stage(self.predefined_codes));
+        }
+        pipeline
+    }
+
+    /// Pipeline compressing patches against `reference`, see `Pipeline::patch_spec`.
+    pub fn build_patch_pipeline<'a>(&self, reference: &'a [u8]) -> Pipeline<'a> {
+        let spec = Pipeline::patch_spec(&self.build_pipeline().spec());
+        Pipeline::from_patch_spec(&spec, self, reference).expect("Patch spec has known stages.")
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeflateConfigBuilder {
+    window_size: usize,
+    max_match_len: Option<usize>,
+    strategy: Strategy,
+    entropy_coder: EntropyCoder,
+    predefined_codes: bool,
+    long_range: bool,
+    pipeline: Option<String>,
+    frame_size: usize,
+    checksum: Checksum,
+}
+
+impl Default for DeflateConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DeflateConfigBuilder {
+    pub fn new() -> Self {
+        DeflateConfigBuilder {
+            window_size: LZ77Compressor::default_window_size(),
+            max_match_len: None,
+            strategy: Strategy::default(),
+            entropy_coder: EntropyCoder::default(),
+            predefined_codes: false,
+            long_range: false,
+            pipeline: None,
+            frame_size: DEFAULT_FRAME_SIZE,
+            checksum: Checksum::default(),
+        }
+    }
+
+    pub fn window_size(mut self, window_size: usize) -> Self {
+        self.window_size = window_size;
+        self
+    }
+
+    pub fn max_match_len(mut self, max_match_len: usize) -> Self {
+        self.max_match_len = Some(max_match_len);
+        self
+    }
+
+    pub fn strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn entropy_coder(mut self, entropy_coder: EntropyCoder) -> Self {
+        self.entropy_coder = entropy_coder;
+        self
+    }
+
+    pub fn predefined_codes(mut self, predefined_codes: bool) -> Self {
+        self.predefined_codes = predefined_codes;
+        self
+    }
+
+    pub fn long_range(mut self, long_range: bool) -> Self {
+        self.long_range = long_range;
+        self
+    }
+
+    pub fn pipeline(mut self, spec: &str) -> Self {
+        self.pipeline = Some(spec.to_string());
+        self
+    }
+
+    pub fn frame_size(mut self, frame_size: usize) -> Self {
+        self.frame_size = frame_size;
+        self
+    }
+
+    pub fn checksum(mut self, checksum: Checksum) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    pub fn build(self) -> Result<DeflateConfig, ConfigError> {
+        if !(1..=MAX_WINDOW_SIZE).contains(&self.window_size) {
+            return Err(ConfigError::WindowSize(self.window_size));
+        }
+        if let Some(max_match_len) = self
+            .max_match_len
+            .filter(|len| !(1..=self.window_size).contains(len))
+        {
+            return Err(ConfigError::MaxMatchLen {
+                max_match_len,
+                window_size: self.window_size,
+            });
+        }
+        if !(1..=MAX_FRAME_SIZE).contains(&self.frame_size) {
+            return Err(ConfigError::FrameSize(self.frame_size));
+        }
+
+        let config = DeflateConfig {
+            window_size: self.window_size,
+            max_match_len: self.max_match_len,
+            strategy: self.strategy,
+            entropy_coder: self.entropy_coder,
+            predefined_codes: self.predefined_codes,
+            long_range: self.long_range,
+            pipeline: self.pipeline,
+            frame_size: self.frame_size,
+            checksum: self.checksum,
+        };
+        if let Some(spec) = &config.pipeline {
+            let pipeline = Pipeline::from_spec(spec, &config).map_err(ConfigError::UnknownStage)?;
+            let spec_len = pipeline.spec().len();
+            if spec_len > MAX_SPEC_LEN {
+                return Err(ConfigError::SpecTooLong(spec_len));
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+impl TryFrom<DeflateConfigBuilder> for DeflateConfig {
+    type Error = ConfigError;
+
+    fn try_from(builder: DeflateConfigBuilder) -> Result<Self, Self::Error> {
+        builder.build()
+    }
+}
@@ -0,0 +1,6 @@
+pub mod implementation;
+
+pub(crate) use implementation::MAX_WINDOW_SIZE;
+pub use implementation::{
+    ConfigError, DeflateConfig, DeflateConfigBuilder, EntropyCoder, Strategy,
+};
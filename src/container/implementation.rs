@@ -0,0 +1,287 @@
+const MAGIC: [u8; 4] = *b"DFLR";
+const FORMAT_VERSION: u8 = 7;
+const FRAME_LEN_BYTES: usize = 4;
+const STORED_FRAME_FLAG: u32 = 1 << 31;
+/// Longest pipeline spec written in the header, after a byte of its length.
+pub(crate) const MAX_SPEC_LEN: usize = u8::MAX as usize;
+
+use std::{error::Error, fmt};
+
+use crate::checksum::Checksum;
+use crate::error::{CancelledError, DecodeError};
+use crate::pipeline::UnknownStageError;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ContainerError {
+    BadMagic,
+    UnsupportedVersion(u8),
+    UnknownChecksum(u8),
+    Truncated,
+    TrailingData,
+    HeaderChecksumMismatch,
+    FrameChecksumMismatch { frame: usize },
+    UnknownStage(UnknownStageError),
+    Decode(DecodeError),
+    LengthMismatch { expected: u64, actual: u64 },
+    InvalidUtf8,
+    Cancelled(CancelledError),
+    OutputLimit { declared: u64, limit: u64 },
+    ReferenceRequired,
+}
+
+impl fmt::Display for ContainerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ContainerError::BadMagic => write!(f, "Input is not a compressed stream"),
+            ContainerError::UnsupportedVersion(version) => write!(
+                f,
+                "Unsupported format version {}, expected {}",
+                version, FORMAT_VERSION
+            ),
+            ContainerError::UnknownChecksum(id) => write!(f, "Unknown checksum: {}", id),
+            ContainerError::Truncated => write!(f, "Compressed stream is truncated"),
+            ContainerError::TrailingData => write!(f, "Data found after the last frame"),
+            ContainerError::HeaderChecksumMismatch => {
+                write!(f, "Checksum of the header does not match")
+            }
+            ContainerError::FrameChecksumMismatch { frame } => {
+                write!(f, "Checksum of frame {} does not match", frame)
+            }
+            ContainerError::UnknownStage(err) => write!(f, "{}", err),
+            ContainerError::Decode(err) => write!(f, "{}", err),
+            ContainerError::LengthMismatch { expected, actual } => write!(
+                f,
+                "Decompressed {} bytes, while the header declares {}",
+                actual, expected
+            ),
+            ContainerError::InvalidUtf8 => write!(f, "Decompressed data is not valid UTF-8"),
+            ContainerError::Cancelled(err) => write!(f, "{}", err),
+            ContainerError::OutputLimit { declared, limit } => write!(
+                f,
+                "Stream declares {} bytes of decompressed data, over the limit of {}",
+                declared, limit
+            ),
+            ContainerError::ReferenceRequired => {
+                write!(
+                    f,
+                    "Stream is a patch, it has to be applied to its reference"
+                )
+            }
+        }
+    }
+}
+
+impl From<DecodeError> for ContainerError {
+    fn from(err: DecodeError) -> Self {
+        ContainerError::Decode(err)
+    }
+}
+
+impl Error for ContainerError {}
+
+impl ContainerError {
+    /// Error of a stream with an unknown stage, where the `patch` stage is known,
+    /// but needs the reference of the patch.
+    pub fn from_unknown_stage(err: UnknownStageError) -> Self {
+        match err.0.as_str() {
+            "patch" => ContainerError::ReferenceRequired,
+            _ => ContainerError::UnknownStage(err),
+        }
+    }
+}
+
+/// Frame of a compressed stream, with the checksum of its uncompressed data.
+/// Data of `stored` frames is not compressed.
+pub struct Frame<'a> {
+    pub compressed: &'a [u8],
+    pub checksum: &'a [u8],
+    pub stored: bool,
+}
+
+impl<'a> Frame<'a> {
+    /// Bytes taken by the frame in the stream, its length and checksum included.
+    pub fn total_len(&self) -> usize {
+        FRAME_LEN_BYTES + self.compressed.len() + self.checksum.len()
+    }
+}
+
+/// Header preceding every compressed stream, so it can be decoded without knowing
+/// the settings it was compressed with. Multi-byte fields are big endian:
+///  - 4 bytes of magic and 1 byte of format version,
+///  - 1 byte identifying the checksum,
+///  - spec of the pipeline (e.g. `lz77,huffman`) prefixed by its 1 byte length,
+///  - 4 bytes of window size of the LZ77 stage, 0 if there is none,
+///  - 8 bytes of length of the uncompressed data,
+///  - 4 bytes of size of uncompressed frames,
+///  - checksum of all the above.
+///
+/// Frames follow the header, each being 4 bytes of length of its compressed data,
+/// the compressed data and the checksum of its uncompressed data. Frames which would
+/// not get shorter are stored uncompressed instead, marked by the highest bit of the length.
+/// Hence compressed stream is never longer than `max_compressed_len`: the header, the input
+/// and 4 bytes of length and a checksum for every frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContainerHeader {
+    pub pipeline_spec: String,
+    pub window_size: u32,
+    pub original_len: u64,
+    pub frame_size: u32,
+    pub checksum: Checksum,
+}
+
+impl ContainerHeader {
+    pub fn version() -> u8 {
+        FORMAT_VERSION
+    }
+
+    pub fn append(&self, bytes: &mut Vec<u8>) {
+        assert!(
+            self.pipeline_spec.len() <= MAX_SPEC_LEN,
+            "Pipeline spec is too long to be written in the header, checked by `DeflateConfigBuilder::build`."
+        );
+        let start = bytes.len();
+        bytes.extend(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        bytes.push(self.checksum.id());
+        bytes.push(self.pipeline_spec.len() as u8);
+        bytes.extend(self.pipeline_spec.as_bytes());
+        bytes.extend(self.window_size.to_be_bytes());
+        bytes.extend(self.original_len.to_be_bytes());
+        bytes.extend(self.frame_size.to_be_bytes());
+        let header = bytes[start..].to_vec();
+        self.checksum.append(bytes, &header);
+    }
+
+    /// Reads header from the start of `bytes`, returning it with the number of bytes it took.
+    pub fn read(bytes: &[u8]) -> Result<(Self, usize), ContainerError> {
+        let mut i = 0;
+        let mut take = |n: usize| {
+            let taken = bytes.get(i..i + n).ok_or(ContainerError::Truncated);
+            i += n;
+            taken
+        };
+
+        if take(MAGIC.len())? != MAGIC {
+            return Err(ContainerError::BadMagic);
+        }
+        let version = take(1)?[0];
+        if version != FORMAT_VERSION {
+            return Err(ContainerError::UnsupportedVersion(version));
+        }
+        let checksum_id = take(1)?[0];
+        let checksum =
+            Checksum::from_id(checksum_id).ok_or(ContainerError::UnknownChecksum(checksum_id))?;
+        let spec_len = take(1)?[0] as usize;
+        let pipeline_spec = String::from_utf8_lossy(take(spec_len)?).to_string();
+        let window_size = u32::from_be_bytes(take(4)?.try_into().unwrap());
+        let original_len = u64::from_be_bytes(take(8)?.try_into().unwrap());
+        let frame_size = u32::from_be_bytes(take(4)?.try_into().unwrap());
+        let header_checksum = take(checksum.bytes_num())?;
+
+        let header_len = i;
+        if !checksum.verify(header_checksum, &bytes[..header_len - checksum.bytes_num()]) {
+            return Err(ContainerError::HeaderChecksumMismatch);
+        }
+        if frame_size == 0 && original_len > 0 {
+            return Err(ContainerError::LengthMismatch {
+                expected: original_len,
+                actual: 0,
+            });
+        }
+
+        let header = ContainerHeader {
+            pipeline_spec,
+            window_size,
+            original_len,
+            frame_size,
+            checksum,
+        };
+        Ok((header, header_len))
+    }
+
+    /// Bytes taken by the header.
+    pub fn encoded_len(&self) -> usize {
+        let mut bytes = Vec::new();
+        self.append(&mut bytes);
+        bytes.len()
+    }
+
+    /// Bytes taken by a frame in addition to its data.
+    pub fn frame_overhead(&self) -> usize {
+        FRAME_LEN_BYTES + self.checksum.bytes_num()
+    }
+
+    /// Worst-case length of the stream, reached when all frames are stored.
+    pub fn max_compressed_len(&self) -> u64 {
+        self.encoded_len() as u64
+            + self.original_len
+            + self.frames_num() * self.frame_overhead() as u64
+    }
+
+    pub fn frames_num(&self) -> u64 {
+        match self.original_len {
+            0 => 0,
+            len => (len - 1) / self.frame_size as u64 + 1,
+        }
+    }
+
+    /// Length of uncompressed data of the `frame`-th frame.
+    pub fn frame_len(&self, frame: usize) -> u64 {
+        let start = frame as u64 * self.frame_size as u64;
+        (self.original_len - start).min(self.frame_size as u64)
+    }
+
+    /// Appends frame with `compressed` data, or with `uncompressed` data if it is not longer.
+    /// Returns whether the frame was stored uncompressed.
+    pub fn append_frame(
+        &self,
+        bytes: &mut Vec<u8>,
+        compressed: &[u8],
+        uncompressed: &[u8],
+    ) -> bool {
+        let stored = compressed.len() >= uncompressed.len();
+        let (data, flag) = match stored {
+            true => (uncompressed, STORED_FRAME_FLAG),
+            false => (compressed, 0),
+        };
+        assert!(
+            data.len() < STORED_FRAME_FLAG as usize,
+            "Frame is too long to be written."
+        );
+        bytes.extend((data.len() as u32 | flag).to_be_bytes());
+        bytes.extend(data);
+        self.checksum.append(bytes, uncompressed);
+        stored
+    }
+
+    /// Splits `bytes` following the header into compressed data and checksums of frames.
+    pub fn split_frames<'a>(&self, bytes: &'a [u8]) -> Result<Vec<Frame<'a>>, ContainerError> {
+        let mut frames = Vec::new();
+        let mut i = 0;
+        for _ in 0..self.frames_num() {
+            let len_bytes = bytes
+                .get(i..i + FRAME_LEN_BYTES)
+                .ok_or(ContainerError::Truncated)?;
+            let len_field = u32::from_be_bytes(len_bytes.try_into().unwrap());
+            let len = (len_field & !STORED_FRAME_FLAG) as usize;
+            i += FRAME_LEN_BYTES;
+
+            let compressed = bytes.get(i..i + len).ok_or(ContainerError::Truncated)?;
+            i += len;
+            let checksum = bytes
+                .get(i..i + self.checksum.bytes_num())
+                .ok_or(ContainerError::Truncated)?;
+            i += self.checksum.bytes_num();
+            frames.push(Frame {
+                compressed,
+                checksum,
+                stored: len_field & STORED_FRAME_FLAG != 0,
+            });
+        }
+
+        if i != bytes.len() {
+            return Err(ContainerError::TrailingData);
+        }
+        Ok(frames)
+    }
+}
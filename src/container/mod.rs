@@ -0,0 +1,4 @@
+pub mod implementation;
+
+pub(crate) use implementation::MAX_SPEC_LEN;
+pub use implementation::{ContainerError, ContainerHeader, Frame};
@@ -0,0 +1,31 @@
+use std::{error::Error, fmt};
+
+/// Compressed data which cannot be decoded, because it is corrupted or truncated.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DecodeError(pub String);
+
+impl DecodeError {
+    pub fn new(message: &str) -> Self {
+        DecodeError(message.to_string())
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Cannot decode compressed sequence: {}", self.0)
+    }
+}
+
+impl Error for DecodeError {}
+
+/// Operation stopped early, because its `CancellationToken` was cancelled.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct CancelledError;
+
+impl fmt::Display for CancelledError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Operation was cancelled")
+    }
+}
+
+impl Error for CancelledError {}
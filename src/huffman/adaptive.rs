@@ -0,0 +1,324 @@
+/// Raw bits of symbols sent for the first time, enough for every symbol of the alphabet.
+const LIT_LEN_SYMBOL_BITS: u32 = (LIT_LEN_SYMBOLS_NUM - 1).ilog2() + 1;
+const DISTANCE_SYMBOL_BITS: u32 = (DISTANCE_SYMBOLS_NUM - 1).ilog2() + 1;
+/// Estimated bits of a length or distance symbol, more than of codes fitted to a block,
+/// since new symbols are escaped and codes follow their statistics with a delay.
+const ESTIMATED_SYMBOL_BITS: usize = 6;
+
+use std::collections::HashMap;
+
+use bit_vec::BitVec;
+
+use super::{decoder::BitReader, utils};
+use crate::error::DecodeError;
+use crate::limits::{DecompressLimits, OutputBudget};
+use crate::lz77::{
+    alphabet::{SymbolCode, DISTANCE_SYMBOLS_NUM, END_OF_BLOCK, LIT_LEN_SYMBOLS_NUM},
+    cost::{LiteralFitting, SymbolCosts},
+    implementation::{ResultEncoding, ResultEncodingVec},
+};
+
+struct AdaptiveNode {
+    weight: u64,
+    parent: Option<usize>,
+    children: Option<(usize, usize)>,
+    symbol: Option<u16>,
+}
+
+/// Huffman tree updated after every symbol with the FGK algorithm.
+///
+/// Nodes are kept in a vector ordered by non-increasing weights, with siblings next
+/// to each other, so the index of a node is its (reversed) number from the sibling property.
+/// Symbols seen for the first time are sent as the code of the "not yet transmitted"
+/// leaf, which is always the last node, followed by `symbol_bits` raw bits.
+pub struct AdaptiveHuffmanTree {
+    nodes: Vec<AdaptiveNode>,
+    leaves: HashMap<u16, usize>,
+    symbol_bits: u32,
+}
+
+impl AdaptiveHuffmanTree {
+    pub fn new(symbol_bits: u32) -> Self {
+        AdaptiveHuffmanTree {
+            nodes: vec![AdaptiveNode {
+                weight: 0,
+                parent: None,
+                children: None,
+                symbol: None,
+            }],
+            leaves: HashMap::new(),
+            symbol_bits,
+        }
+    }
+
+    fn not_yet_transmitted(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    pub fn encode(&mut self, bits: &mut BitVec, symbol: u16) {
+        match self.leaves.get(&symbol) {
+            Some(&leaf) => self.push_path(bits, leaf),
+            None => {
+                self.push_path(bits, self.not_yet_transmitted());
+                utils::push_bits(bits, symbol as u64, self.symbol_bits);
+            }
+        }
+        self.update(symbol);
+    }
+
+    pub fn decode(&mut self, reader: &mut BitReader) -> Option<u16> {
+        let mut node = 0;
+        while let Some((left, right)) = self.nodes[node].children {
+            if reader.is_empty() {
+                return None;
+            }
+            node = if reader.read(1) == 0 { left } else { right };
+        }
+
+        let symbol = match self.nodes[node].symbol {
+            Some(symbol) => symbol,
+            None if reader.remaining() >= self.symbol_bits as usize => {
+                let symbol = reader.read(self.symbol_bits) as u16;
+                if self.leaves.contains_key(&symbol) {
+                    return None;
+                }
+                symbol
+            }
+            None => return None,
+        };
+        self.update(symbol);
+        Some(symbol)
+    }
+
+    fn push_path(&self, bits: &mut BitVec, mut node: usize) {
+        let mut path = Vec::new();
+        while let Some(parent) = self.nodes[node].parent {
+            path.push(self.nodes[parent].children.unwrap().1 == node);
+            node = parent;
+        }
+        bits.extend(path.into_iter().rev());
+    }
+
+    fn update(&mut self, symbol: u16) {
+        let mut node = match self.leaves.get(&symbol) {
+            Some(&leaf) => leaf,
+            None => self.add_symbol(symbol),
+        };
+
+        loop {
+            let leader = self.leader(node);
+            if leader != node && Some(leader) != self.nodes[node].parent {
+                self.swap(node, leader);
+                node = leader;
+            }
+
+            self.nodes[node].weight += 1;
+            match self.nodes[node].parent {
+                Some(parent) => node = parent,
+                None => return,
+            }
+        }
+    }
+
+    /// Splits "not yet transmitted" leaf into a leaf of `symbol` and a new empty leaf.
+    fn add_symbol(&mut self, symbol: u16) -> usize {
+        let old = self.not_yet_transmitted();
+        let (leaf, not_yet_transmitted) = (old + 1, old + 2);
+        for index in [leaf, not_yet_transmitted] {
+            self.nodes.push(AdaptiveNode {
+                weight: 0,
+                parent: Some(old),
+                children: None,
+                symbol: None,
+            });
+            debug_assert_eq!(self.nodes.len() - 1, index);
+        }
+
+        self.nodes[old].children = Some((not_yet_transmitted, leaf));
+        self.nodes[leaf].symbol = Some(symbol);
+        self.leaves.insert(symbol, leaf);
+        leaf
+    }
+
+    /// Returns the node with the same weight as `node` and the highest number.
+    fn leader(&self, node: usize) -> usize {
+        let weight = self.nodes[node].weight;
+        let mut leader = node;
+        while leader > 0 && self.nodes[leader - 1].weight == weight {
+            leader -= 1;
+        }
+
+        leader
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        let (parent_a, parent_b) = (self.nodes[a].parent, self.nodes[b].parent);
+        self.nodes.swap(a, b);
+        self.nodes[a].parent = parent_a;
+        self.nodes[b].parent = parent_b;
+
+        for index in [a, b] {
+            if let Some((left, right)) = self.nodes[index].children {
+                self.nodes[left].parent = Some(index);
+                self.nodes[right].parent = Some(index);
+            }
+            if let Some(symbol) = self.nodes[index].symbol {
+                self.leaves.insert(symbol, index);
+            }
+        }
+    }
+}
+
+/// Codes tokens in a single pass: no table is written and tokens can be pushed one by one.
+pub struct AdaptiveTokenEncoder {
+    lit_len: AdaptiveHuffmanTree,
+    distance: AdaptiveHuffmanTree,
+    bits: BitVec,
+}
+
+impl Default for AdaptiveTokenEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AdaptiveTokenEncoder {
+    pub fn new() -> Self {
+        AdaptiveTokenEncoder {
+            lit_len: AdaptiveHuffmanTree::new(LIT_LEN_SYMBOL_BITS),
+            distance: AdaptiveHuffmanTree::new(DISTANCE_SYMBOL_BITS),
+            bits: BitVec::new(),
+        }
+    }
+
+    pub fn push(&mut self, token: &ResultEncoding) {
+        let (lit_len_code, distance_code) = token.to_symbol_codes();
+        self.lit_len.encode(&mut self.bits, lit_len_code.symbol);
+        utils::push_bits(
+            &mut self.bits,
+            lit_len_code.extra_bits,
+            lit_len_code.extra_bits_num,
+        );
+        if let Some(code) = distance_code {
+            self.distance.encode(&mut self.bits, code.symbol);
+            utils::push_bits(&mut self.bits, code.extra_bits, code.extra_bits_num);
+        }
+    }
+
+    pub fn finish(mut self) -> BitVec {
+        self.lit_len.encode(&mut self.bits, END_OF_BLOCK);
+        self.bits
+    }
+}
+
+pub struct AdaptiveHuffmanCompressor;
+
+impl AdaptiveHuffmanCompressor {
+    /// Costs of tokens coded by the stage, with codes of whole bits adapting to literals.
+    pub fn symbol_costs() -> SymbolCosts {
+        SymbolCosts::fitted(
+            LiteralFitting::WholeBits,
+            ESTIMATED_SYMBOL_BITS,
+            ESTIMATED_SYMBOL_BITS,
+        )
+    }
+
+    pub fn compress(ascii_bytes: &[u8]) -> BitVec {
+        let mut encoder = AdaptiveTokenEncoder::new();
+        ascii_bytes
+            .iter()
+            .for_each(|c| encoder.push(&ResultEncoding::Ascii(*c)));
+        encoder.finish()
+    }
+
+    pub fn decompress(bits: &BitVec) -> Result<Vec<u8>, DecodeError> {
+        AdaptiveHuffmanCompressor::decompress_tokens(bits)?.try_expand()
+    }
+
+    pub fn compress_tokens(tokens: &ResultEncodingVec) -> BitVec {
+        let mut encoder = AdaptiveTokenEncoder::new();
+        tokens.iter().for_each(|token| encoder.push(token));
+        encoder.finish()
+    }
+
+    pub fn decompress_tokens(bits: &BitVec) -> Result<ResultEncodingVec, DecodeError> {
+        let bytes = bits.to_bytes();
+        AdaptiveHuffmanCompressor::decode_tokens(
+            &mut BitReader::new(&bytes, bits.len()),
+            &DecompressLimits::default(),
+        )
+    }
+
+    /// Same as `compress`, but with the last byte padded with zeros. Decoding stops
+    /// at the end of block symbol, so padding is never decoded.
+    pub fn compress_to_bytes(ascii_bytes: &[u8]) -> Vec<u8> {
+        AdaptiveHuffmanCompressor::compress(ascii_bytes).to_bytes()
+    }
+
+    pub fn decompress_from_bytes(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        AdaptiveHuffmanCompressor::decompress_tokens_from_bytes(bytes)?.try_expand()
+    }
+
+    pub fn compress_tokens_to_bytes(tokens: &ResultEncodingVec) -> Vec<u8> {
+        AdaptiveHuffmanCompressor::compress_tokens(tokens).to_bytes()
+    }
+
+    pub fn decompress_tokens_from_bytes(bytes: &[u8]) -> Result<ResultEncodingVec, DecodeError> {
+        AdaptiveHuffmanCompressor::decompress_tokens_with_limits(
+            bytes,
+            &DecompressLimits::default(),
+        )
+    }
+
+    /// Same as `decompress_tokens_from_bytes`, but fails as soon as expanded tokens
+    /// exceed `limits`, the ratio limit included.
+    pub fn decompress_tokens_with_limits(
+        bytes: &[u8],
+        limits: &DecompressLimits,
+    ) -> Result<ResultEncodingVec, DecodeError> {
+        let limits = limits.with_max_output_len(limits.output_limit(bytes.len()));
+        AdaptiveHuffmanCompressor::decode_tokens(
+            &mut BitReader::new(bytes, bytes.len() * 8),
+            &limits,
+        )
+    }
+
+    fn decode_tokens(
+        reader: &mut BitReader,
+        limits: &DecompressLimits,
+    ) -> Result<ResultEncodingVec, DecodeError> {
+        let mut budget = OutputBudget::new(limits);
+        let mut lit_len = AdaptiveHuffmanTree::new(LIT_LEN_SYMBOL_BITS);
+        let mut distance = AdaptiveHuffmanTree::new(DISTANCE_SYMBOL_BITS);
+        let no_token =
+            || DecodeError::new("bits do not match any token for current adaptive huffman tree");
+
+        let mut tokens = ResultEncodingVec::new();
+        loop {
+            let symbol = lit_len
+                .decode(reader)
+                .filter(|symbol| *symbol < LIT_LEN_SYMBOLS_NUM)
+                .ok_or_else(no_token)?;
+            if symbol == END_OF_BLOCK {
+                return Ok(tokens);
+            }
+            if !SymbolCode::is_length_symbol(symbol) {
+                budget.take_bytes(1)?;
+                tokens.push(ResultEncoding::Ascii(symbol as u8));
+                continue;
+            }
+
+            let length_extra = reader.try_read(SymbolCode::length_extra_bits_num(symbol))?;
+            let len = SymbolCode::length_value(symbol, length_extra);
+            let distance_symbol = distance
+                .decode(reader)
+                .filter(|symbol| *symbol < DISTANCE_SYMBOLS_NUM)
+                .ok_or_else(no_token)?;
+            let distance_extra =
+                reader.try_read(SymbolCode::distance_extra_bits_num(distance_symbol))?;
+            let token = SymbolCode::reference(len, distance_symbol, distance_extra);
+            budget.take(&token)?;
+            tokens.push(token);
+        }
+    }
+}
@@ -0,0 +1,338 @@
+const SEGMENT_TOKENS_NUM: usize = 256;
+const BLOCK_TYPE_BITS: u32 = 2;
+const BLOCK_HEADER_BITS: usize = 1 + BLOCK_TYPE_BITS as usize;
+const STORED_LEN_BITS: u32 = 32;
+const NUM_OF_BITS_IN_BYTE: u32 = 8;
+
+use std::{collections::HashMap, ops::Range};
+
+use bit_vec::BitVec;
+
+use super::{decoder::BitReader, tokens::TokenHuffmanCodes, utils};
+use crate::error::DecodeError;
+use crate::limits::{DecompressLimits, OutputBudget};
+use crate::lz77::implementation::{ResultEncoding, ResultEncodingVec};
+use crate::stats::CompressionStats;
+use crate::trace::scope;
+
+/// Way the tokens of a single block are written.
+///  - `Stored`: bytes covered by the block, without any compression, only for blocks
+///    without matches, since later repeat tokens may refer to their distances,
+///  - `Fixed`: tokens coded with predefined codes, no table is written,
+///  - `Dynamic`: tokens coded with codes fitted to the block, preceded by their weights.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlockType {
+    Stored,
+    Fixed,
+    Dynamic,
+}
+
+impl BlockType {
+    fn id(&self) -> u64 {
+        match self {
+            BlockType::Stored => 0,
+            BlockType::Fixed => 1,
+            BlockType::Dynamic => 2,
+        }
+    }
+
+    fn from_id(id: u64) -> Result<Self, DecodeError> {
+        match id {
+            0 => Ok(BlockType::Stored),
+            1 => Ok(BlockType::Fixed),
+            2 => Ok(BlockType::Dynamic),
+            _ => Err(DecodeError::new("unknown type of block")),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct SymbolsCounts {
+    lit_len: HashMap<u16, usize>,
+    distance: HashMap<u16, usize>,
+    extra_bits_len: usize,
+    bytes_num: usize,
+}
+
+impl SymbolsCounts {
+    fn of_tokens(tokens: &[ResultEncoding]) -> Self {
+        let mut counts = SymbolsCounts::default();
+        for token in tokens {
+            let (lit_len_code, distance_code) = token.to_symbol_codes();
+            *counts.lit_len.entry(lit_len_code.symbol).or_default() += 1;
+            counts.extra_bits_len += lit_len_code.extra_bits_num as usize;
+            if let Some(code) = distance_code {
+                *counts.distance.entry(code.symbol).or_default() += 1;
+                counts.extra_bits_len += code.extra_bits_num as usize;
+            }
+            counts.bytes_num += token.expanded_len();
+        }
+
+        counts
+    }
+
+    fn merged(&self, other: &SymbolsCounts) -> Self {
+        let mut counts = self.clone();
+        for (merged, added) in [
+            (&mut counts.lit_len, &other.lit_len),
+            (&mut counts.distance, &other.distance),
+        ] {
+            added
+                .iter()
+                .for_each(|(symbol, count)| *merged.entry(*symbol).or_default() += count);
+        }
+        counts.extra_bits_len += other.extra_bits_len;
+        counts.bytes_num += other.bytes_num;
+        counts
+    }
+}
+
+struct BlockEncoding {
+    block_type: BlockType,
+    codes: Option<TokenHuffmanCodes>,
+    len: usize,
+}
+
+impl BlockEncoding {
+    /// Stored encoding of a block of `counts`, the only way to choose `BlockType::Stored`.
+    /// `None` unless the block has no matches, since later repeat tokens may refer to their
+    /// distances, and its bytes fit in the stored length.
+    fn stored(counts: &SymbolsCounts) -> Option<Self> {
+        let has_matches = !counts.distance.is_empty();
+        (!has_matches && counts.bytes_num < 1 << STORED_LEN_BITS).then(|| BlockEncoding {
+            block_type: BlockType::Stored,
+            codes: None,
+            len: BLOCK_HEADER_BITS
+                + STORED_LEN_BITS as usize
+                + counts.bytes_num * NUM_OF_BITS_IN_BYTE as usize,
+        })
+    }
+}
+
+struct Block {
+    tokens: Range<usize>,
+    counts: SymbolsCounts,
+    encoding: BlockEncoding,
+}
+
+/// Splits tokens into blocks wherever coding them separately is cheaper than together,
+/// and writes every block in the way taking the least bits.
+pub struct BlocksEncoder<'a> {
+    tokens: &'a [ResultEncoding],
+    fixed_codes: TokenHuffmanCodes,
+    force_fixed: bool,
+}
+
+impl<'a> BlocksEncoder<'a> {
+    pub fn new(tokens: &'a ResultEncodingVec, force_fixed: bool) -> Self {
+        BlocksEncoder {
+            tokens: tokens.as_slice(),
+            fixed_codes: TokenHuffmanCodes::new_predefined(),
+            force_fixed,
+        }
+    }
+
+    pub fn encode(&self) -> BitVec {
+        self.encode_recording(None)
+    }
+
+    pub fn encode_with_stats(&self, stats: &mut CompressionStats) -> BitVec {
+        self.encode_recording(Some(stats))
+    }
+
+    fn encode_recording(&self, mut stats: Option<&mut CompressionStats>) -> BitVec {
+        let scope = scope!(DEBUG, "huffman", tokens = self.tokens.len());
+        let mut bits = BitVec::new();
+
+        let blocks = self.split_on_blocks();
+        let blocks_num = blocks.len();
+        for (i, block) in blocks.into_iter().enumerate() {
+            let is_final = i + 1 == blocks_num;
+            self.encode_block(&mut bits, &block, is_final, stats.as_deref_mut());
+        }
+        scope.finish(bits.len().div_ceil(NUM_OF_BITS_IN_BYTE as usize));
+
+        bits
+    }
+
+    fn split_on_blocks(&self) -> Vec<Block> {
+        let mut segments = (0..self.tokens.len().max(1))
+            .step_by(SEGMENT_TOKENS_NUM)
+            .map(|start| self.block(start..(start + SEGMENT_TOKENS_NUM).min(self.tokens.len())));
+
+        let mut blocks = Vec::new();
+        let mut current = segments.next().unwrap();
+        for segment in segments {
+            let counts = current.counts.merged(&segment.counts);
+            let encoding = self.best_encoding(&counts);
+            if encoding.len <= current.encoding.len + segment.encoding.len {
+                current = Block {
+                    tokens: current.tokens.start..segment.tokens.end,
+                    counts,
+                    encoding,
+                };
+            } else {
+                blocks.push(current);
+                current = segment;
+            }
+        }
+        blocks.push(current);
+
+        blocks
+    }
+
+    fn block(&self, tokens: Range<usize>) -> Block {
+        let counts = SymbolsCounts::of_tokens(&self.tokens[tokens.clone()]);
+        let encoding = self.best_encoding(&counts);
+        Block {
+            tokens,
+            counts,
+            encoding,
+        }
+    }
+
+    fn best_encoding(&self, counts: &SymbolsCounts) -> BlockEncoding {
+        let fixed_len = self
+            .fixed_codes
+            .symbols_len(&counts.lit_len, &counts.distance)
+            .map(|len| {
+                BLOCK_HEADER_BITS
+                    + len
+                    + counts.extra_bits_len
+                    + self.fixed_codes.end_of_block_len()
+            });
+        if let (Some(len), true) = (fixed_len, self.force_fixed) {
+            return BlockEncoding {
+                block_type: BlockType::Fixed,
+                codes: None,
+                len,
+            };
+        }
+
+        let dynamic_codes =
+            TokenHuffmanCodes::new_calc_on_counts(&counts.lit_len, &counts.distance);
+        let dynamic_len = BLOCK_HEADER_BITS
+            + dynamic_codes.weights_encoding_len()
+            + dynamic_codes
+                .symbols_len(&counts.lit_len, &counts.distance)
+                .unwrap()
+            + counts.extra_bits_len
+            + dynamic_codes.end_of_block_len();
+
+        let mut best = BlockEncoding {
+            block_type: BlockType::Dynamic,
+            codes: Some(dynamic_codes),
+            len: dynamic_len,
+        };
+        if let Some(len) = fixed_len.filter(|len| *len <= best.len) {
+            best = BlockEncoding {
+                block_type: BlockType::Fixed,
+                codes: None,
+                len,
+            };
+        }
+        if let Some(stored) = BlockEncoding::stored(counts).filter(|stored| stored.len < best.len) {
+            best = stored;
+        }
+
+        best
+    }
+
+    fn encode_block(
+        &self,
+        bits: &mut BitVec,
+        block: &Block,
+        is_final: bool,
+        stats: Option<&mut CompressionStats>,
+    ) {
+        let header_start = bits.len();
+        bits.push(is_final);
+        let block_type = block.encoding.block_type;
+        utils::push_bits(bits, block_type.id(), BLOCK_TYPE_BITS);
+
+        let tokens = &self.tokens[block.tokens.clone()];
+        let codes = match block_type {
+            BlockType::Stored => {
+                utils::push_bits(bits, tokens.len() as u64, STORED_LEN_BITS);
+                if let Some(stats) = stats {
+                    stats.header_bits += (bits.len() - header_start) as u64;
+                }
+                let bytes: Vec<u8> = tokens
+                    .iter()
+                    .filter_map(|token| match token {
+                        ResultEncoding::Ascii(byte) => Some(*byte),
+                        _ => None,
+                    })
+                    .collect();
+                debug_assert_eq!(
+                    bytes.len(),
+                    tokens.len(),
+                    "`BlockEncoding::stored` stores blocks without matches only."
+                );
+                bits.extend(BitVec::from_bytes(&bytes).iter());
+                return;
+            }
+            BlockType::Fixed => &self.fixed_codes,
+            BlockType::Dynamic => {
+                let codes = block.encoding.codes.as_ref().unwrap();
+                codes.append_weights_encoding(bits);
+                codes
+            }
+        };
+
+        if let Some(stats) = stats {
+            stats.header_bits += (bits.len() - header_start) as u64;
+            for token in tokens {
+                let (lit_len_len, distance_len) = codes.token_code_lens(token);
+                stats.record_code_len(lit_len_len);
+                distance_len
+                    .into_iter()
+                    .for_each(|len| stats.record_code_len(len));
+            }
+            stats.record_code_len(codes.end_of_block_len());
+        }
+
+        tokens
+            .iter()
+            .for_each(|token| codes.encode_token(bits, token));
+        codes.encode_end_of_block(bits);
+    }
+}
+
+pub struct BlocksDecoder;
+
+impl BlocksDecoder {
+    /// Decodes blocks until the final one, failing once expanded tokens exceed `limits`.
+    pub fn decode(
+        reader: &mut BitReader,
+        limits: &DecompressLimits,
+    ) -> Result<ResultEncodingVec, DecodeError> {
+        let mut budget = OutputBudget::new(limits);
+        let mut tokens = ResultEncodingVec::new();
+        let mut fixed_codes: Option<TokenHuffmanCodes> = None;
+        loop {
+            let is_final = reader.try_read(1)? == 1;
+            match BlockType::from_id(reader.try_read(BLOCK_TYPE_BITS)?)? {
+                BlockType::Stored => {
+                    let bytes_num = reader.try_read(STORED_LEN_BITS)?;
+                    if (reader.remaining() as u64) < bytes_num * NUM_OF_BITS_IN_BYTE as u64 {
+                        return Err(DecodeError::new("sequence ended inside of a stored block"));
+                    }
+                    budget.take_bytes(bytes_num as usize)?;
+                    (0..bytes_num).for_each(|_| {
+                        tokens.push(ResultEncoding::Ascii(reader.read(NUM_OF_BITS_IN_BYTE) as u8))
+                    });
+                }
+                BlockType::Fixed => fixed_codes
+                    .get_or_insert_with(TokenHuffmanCodes::new_predefined)
+                    .decode_block(reader, &mut tokens, &mut budget)?,
+                BlockType::Dynamic => TokenHuffmanCodes::new_from_weights_encoding(reader)?
+                    .decode_block(reader, &mut tokens, &mut budget)?,
+            }
+
+            if is_final {
+                return Ok(tokens);
+            }
+        }
+    }
+}
@@ -0,0 +1,171 @@
+const PRIMARY_TABLE_BITS: u32 = 9;
+const MAX_PEEK_BITS: u32 = 56;
+
+use std::{cmp::min, collections::HashMap};
+
+use bit_vec::BitVec;
+
+use crate::error::DecodeError;
+
+pub struct BitReader<'a> {
+    bytes: &'a [u8],
+    len: usize,
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(bytes: &'a [u8], len: usize) -> Self {
+        BitReader {
+            bytes,
+            len: min(len, bytes.len() * 8),
+            position: 0,
+        }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.len.saturating_sub(self.position)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.position >= self.len
+    }
+
+    /// Returns next `n` bits (most significant first) without consuming them.
+    /// Bits past the end of the stream are read as zeros.
+    pub fn peek(&self, n: u32) -> u64 {
+        debug_assert!(n <= MAX_PEEK_BITS);
+        if n == 0 {
+            return 0;
+        }
+
+        let byte_index = self.position / 8;
+        let word = match self.bytes.get(byte_index..byte_index + 8) {
+            Some(chunk) => u64::from_be_bytes(chunk.try_into().unwrap()),
+            None => {
+                let mut chunk = [0_u8; 8];
+                let tail = &self.bytes[byte_index.min(self.bytes.len())..];
+                chunk[..tail.len()].copy_from_slice(tail);
+                u64::from_be_bytes(chunk)
+            }
+        };
+
+        (word << (self.position % 8)) >> (64 - n)
+    }
+
+    pub fn consume(&mut self, n: usize) {
+        self.position += n;
+    }
+
+    pub fn read(&mut self, n: u32) -> u64 {
+        if n > MAX_PEEK_BITS {
+            let high = self.read(n - MAX_PEEK_BITS);
+            return (high << MAX_PEEK_BITS) | self.read(MAX_PEEK_BITS);
+        }
+
+        let value = self.peek(n);
+        self.consume(n as usize);
+        value
+    }
+
+    /// Same as `read`, but fails instead of reading past the end of the stream.
+    pub fn try_read(&mut self, n: u32) -> Result<u64, DecodeError> {
+        match n as usize <= self.remaining() {
+            true => Ok(self.read(n)),
+            false => Err(DecodeError::new("sequence ended unexpectedly")),
+        }
+    }
+
+    /// Reads value written with `utils::push_gamma`.
+    pub fn read_gamma(&mut self) -> u64 {
+        let mut highest_bit = 0;
+        while self.peek(1) == 0 && highest_bit < MAX_PEEK_BITS - 1 && !self.is_empty() {
+            highest_bit += 1;
+            self.consume(1);
+        }
+
+        self.read(highest_bit + 1)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum TableEntry<S: Copy> {
+    Empty,
+    Leaf { symbol: S, len: u32 },
+    Link { offset: usize, bits: u32 },
+}
+
+/// Decodes prefix codes by peeking `PRIMARY_TABLE_BITS` bits at a time.
+/// Codes longer than that are resolved in a secondary table linked from the primary one.
+pub struct HuffmanDecoder<S: Copy> {
+    table: Vec<TableEntry<S>>,
+}
+
+impl<S: Copy> HuffmanDecoder<S> {
+    pub fn new(mapping_on_bits: &HashMap<S, BitVec>) -> Self {
+        let codes: Vec<(S, u64, u32)> = mapping_on_bits
+            .iter()
+            .map(|(symbol, bits)| {
+                assert!(
+                    bits.len() <= MAX_PEEK_BITS as usize,
+                    "Huffman code is too long to be decoded."
+                );
+                let value = bits.iter().fold(0_u64, |acc, bit| (acc << 1) | bit as u64);
+                (*symbol, value, bits.len() as u32)
+            })
+            .collect();
+
+        let mut table = vec![TableEntry::Empty; 1 << PRIMARY_TABLE_BITS];
+        let mut long_codes_by_prefix: HashMap<usize, Vec<(S, u64, u32)>> = HashMap::new();
+        for &(symbol, value, len) in codes.iter() {
+            if len <= PRIMARY_TABLE_BITS {
+                let shift = PRIMARY_TABLE_BITS - len;
+                let start = (value << shift) as usize;
+                table[start..start + (1 << shift)].fill(TableEntry::Leaf { symbol, len });
+            } else {
+                let prefix = (value >> (len - PRIMARY_TABLE_BITS)) as usize;
+                long_codes_by_prefix
+                    .entry(prefix)
+                    .or_default()
+                    .push((symbol, value, len));
+            }
+        }
+
+        for (prefix, group) in long_codes_by_prefix {
+            let bits = group.iter().map(|(_, _, len)| *len).max().unwrap() - PRIMARY_TABLE_BITS;
+            let offset = table.len();
+            table.resize(offset + (1 << bits), TableEntry::Empty);
+            table[prefix] = TableEntry::Link { offset, bits };
+
+            for (symbol, value, len) in group {
+                let suffix_len = len - PRIMARY_TABLE_BITS;
+                let suffix = value & ((1 << suffix_len) - 1);
+                let shift = bits - suffix_len;
+                let start = offset + (suffix << shift) as usize;
+                table[start..start + (1 << shift)].fill(TableEntry::Leaf { symbol, len });
+            }
+        }
+
+        HuffmanDecoder { table }
+    }
+
+    /// Decodes one symbol, or returns `None` if remaining bits do not form a valid code.
+    pub fn decode(&self, reader: &mut BitReader) -> Option<S> {
+        let mut entry = self.table[reader.peek(PRIMARY_TABLE_BITS) as usize];
+        if let TableEntry::Link { offset, bits } = entry {
+            let suffix = reader.peek(PRIMARY_TABLE_BITS + bits) & ((1 << bits) - 1);
+            entry = self.table[offset + suffix as usize];
+        }
+
+        match entry {
+            TableEntry::Leaf { symbol, len } if len as usize <= reader.remaining() => {
+                reader.consume(len as usize);
+                Some(symbol)
+            }
+            _ => None,
+        }
+    }
+}
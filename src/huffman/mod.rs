@@ -1,8 +1,15 @@
+pub mod adaptive;
+mod blocks;
+mod decoder;
 pub mod implementation;
+mod tokens;
 mod trees;
-mod utils;
+pub(crate) mod utils;
 mod weights;
 
-pub use implementation::HuffmanCodes;
+pub use adaptive::{AdaptiveHuffmanCompressor, AdaptiveHuffmanTree, AdaptiveTokenEncoder};
+pub use blocks::BlockType;
+pub use decoder::{BitReader, HuffmanDecoder};
 pub use implementation::HuffmanCompressor;
+pub use tokens::TokenHuffmanCodes;
 pub use trees::HuffmanTreeCreator;
@@ -0,0 +1,221 @@
+const SYMBOLS_NUM_BITS: u32 = 16;
+const WEIGHT_BITS: u32 = 8;
+
+use std::{collections::HashMap, ops::Range};
+
+use bit_vec::BitVec;
+
+use super::{
+    decoder::{BitReader, HuffmanDecoder},
+    trees::HuffmanTreeCreator,
+    utils,
+    weights::WeightsCalculator,
+};
+use crate::error::DecodeError;
+use crate::limits::OutputBudget;
+use crate::lz77::{
+    alphabet::{
+        SymbolCode, DISTANCE_SYMBOLS_NUM, END_OF_BLOCK, FIRST_LENGTH_SYMBOL, LIT_LEN_SYMBOLS_NUM,
+    },
+    cost::SymbolCosts,
+    implementation::{ResultEncoding, ResultEncodingVec},
+};
+
+/// Two Huffman codes over LZ77 tokens: one shared by literals and match lengths,
+/// the other one for match distances. Values refined by extra bits are written raw.
+pub struct TokenHuffmanCodes {
+    lit_len_weights: HashMap<u16, u8>,
+    distance_weights: HashMap<u16, u8>,
+    lit_len_on_bits: HashMap<u16, BitVec>,
+    distance_on_bits: HashMap<u16, BitVec>,
+}
+
+impl TokenHuffmanCodes {
+    pub fn new_predefined() -> Self {
+        TokenHuffmanCodes::new(
+            WeightsCalculator::default_lit_len(),
+            WeightsCalculator::default_distance(),
+        )
+    }
+
+    /// Codes fitted to symbol counts of a block. End of block symbol is always added.
+    pub fn new_calc_on_counts(
+        lit_len_counts: &HashMap<u16, usize>,
+        distance_counts: &HashMap<u16, usize>,
+    ) -> Self {
+        let mut lit_len_counts = lit_len_counts.clone();
+        lit_len_counts.entry(END_OF_BLOCK).or_insert(1);
+        TokenHuffmanCodes::new(
+            WeightsCalculator::fitted_to_counts(&lit_len_counts),
+            WeightsCalculator::fitted_to_counts(distance_counts),
+        )
+    }
+
+    pub fn new_from_weights_encoding(reader: &mut BitReader) -> Result<Self, DecodeError> {
+        let invalid_header = || DecodeError::new("invalid weights of huffman codes");
+        let mut weights = [HashMap::new(), HashMap::new()];
+        for (alphabet_weights, symbols_limit) in weights
+            .iter_mut()
+            .zip([LIT_LEN_SYMBOLS_NUM, DISTANCE_SYMBOLS_NUM])
+        {
+            let symbols_num = reader.try_read(SYMBOLS_NUM_BITS)?;
+            let mut symbol: u64 = 0;
+            for i in 0..symbols_num {
+                let gap = reader.read_gamma();
+                symbol = match i {
+                    0 => gap.checked_sub(1),
+                    _ => (gap > 0).then_some(symbol + gap),
+                }
+                .filter(|symbol| *symbol < symbols_limit as u64)
+                .ok_or_else(invalid_header)?;
+                let weight = reader.try_read(WEIGHT_BITS)?;
+                if weight == 0 {
+                    return Err(invalid_header());
+                }
+                alphabet_weights.insert(symbol as u16, weight as u8);
+            }
+        }
+
+        let [lit_len_weights, distance_weights] = weights;
+        Ok(TokenHuffmanCodes::new(lit_len_weights, distance_weights))
+    }
+
+    fn new(lit_len_weights: HashMap<u16, u8>, distance_weights: HashMap<u16, u8>) -> Self {
+        TokenHuffmanCodes {
+            lit_len_on_bits: HuffmanTreeCreator::get_mapping_on_bits(&lit_len_weights),
+            distance_on_bits: HuffmanTreeCreator::get_mapping_on_bits(&distance_weights),
+            lit_len_weights,
+            distance_weights,
+        }
+    }
+
+    /// Weights are written in ascending order of symbols, each preceded by
+    /// the gamma-coded gap from the previous one.
+    pub fn append_weights_encoding(&self, bits: &mut BitVec) {
+        for weights in [&self.lit_len_weights, &self.distance_weights] {
+            utils::push_bits(bits, weights.len() as u64, SYMBOLS_NUM_BITS);
+            let mut sorted_weights: Vec<(&u16, &u8)> = weights.iter().collect();
+            sorted_weights.sort();
+
+            let mut prev_symbol: Option<u16> = None;
+            for (&symbol, &weight) in sorted_weights {
+                let gap = match prev_symbol {
+                    Some(prev) => symbol - prev,
+                    None => symbol + 1,
+                };
+                utils::push_gamma(bits, gap as u64);
+                utils::push_bits(bits, weight as u64, WEIGHT_BITS);
+                prev_symbol = Some(symbol);
+            }
+        }
+    }
+
+    pub fn weights_encoding_len(&self) -> usize {
+        let mut bits = BitVec::new();
+        self.append_weights_encoding(&mut bits);
+        bits.len()
+    }
+
+    /// Bits needed for symbols with given counts (extra bits and end of block excluded),
+    /// or `None` if some of them have no code.
+    pub fn symbols_len(
+        &self,
+        lit_len_counts: &HashMap<u16, usize>,
+        distance_counts: &HashMap<u16, usize>,
+    ) -> Option<usize> {
+        let mut len = 0;
+        for (mapping_on_bits, counts) in [
+            (&self.lit_len_on_bits, lit_len_counts),
+            (&self.distance_on_bits, distance_counts),
+        ] {
+            for (symbol, count) in counts {
+                len += mapping_on_bits.get(symbol)?.len() * count;
+            }
+        }
+
+        Some(len)
+    }
+
+    /// Costs of tokens coded with these codes, which have to cover all literals, as
+    /// the predefined ones do. Length and distance symbols cost as their longest codes.
+    pub fn symbol_costs(&self) -> SymbolCosts {
+        let longest = |mapping_on_bits: &HashMap<u16, BitVec>, symbols: Range<u16>| {
+            symbols
+                .filter_map(|symbol| mapping_on_bits.get(&symbol))
+                .map(BitVec::len)
+                .max()
+                .unwrap_or(0)
+        };
+        SymbolCosts::predefined(
+            |byte| self.lit_len_on_bits[&(byte as u16)].len(),
+            longest(
+                &self.lit_len_on_bits,
+                FIRST_LENGTH_SYMBOL..LIT_LEN_SYMBOLS_NUM,
+            ),
+            longest(&self.distance_on_bits, 0..DISTANCE_SYMBOLS_NUM),
+        )
+    }
+
+    pub fn end_of_block_len(&self) -> usize {
+        self.lit_len_on_bits[&END_OF_BLOCK].len()
+    }
+
+    pub fn encode_token(&self, bits: &mut BitVec, token: &ResultEncoding) {
+        let (lit_len_code, distance_code) = token.to_symbol_codes();
+        TokenHuffmanCodes::push_code(bits, &self.lit_len_on_bits, lit_len_code);
+        if let Some(code) = distance_code {
+            TokenHuffmanCodes::push_code(bits, &self.distance_on_bits, code);
+        }
+    }
+
+    /// Lengths of codes of symbols the token is written with, extra bits excluded.
+    pub fn token_code_lens(&self, token: &ResultEncoding) -> (usize, Option<usize>) {
+        let (lit_len_code, distance_code) = token.to_symbol_codes();
+        (
+            self.lit_len_on_bits[&lit_len_code.symbol].len(),
+            distance_code.map(|code| self.distance_on_bits[&code.symbol].len()),
+        )
+    }
+
+    pub fn encode_end_of_block(&self, bits: &mut BitVec) {
+        bits.extend(self.lit_len_on_bits[&END_OF_BLOCK].iter());
+    }
+
+    fn push_code(bits: &mut BitVec, mapping_on_bits: &HashMap<u16, BitVec>, code: SymbolCode) {
+        bits.extend(mapping_on_bits[&code.symbol].iter());
+        utils::push_bits(bits, code.extra_bits, code.extra_bits_num);
+    }
+
+    /// Decodes tokens until the end of block symbol, charging them to `budget`.
+    pub fn decode_block(
+        &self,
+        reader: &mut BitReader,
+        tokens: &mut ResultEncodingVec,
+        budget: &mut OutputBudget,
+    ) -> Result<(), DecodeError> {
+        let lit_len_decoder = HuffmanDecoder::new(&self.lit_len_on_bits);
+        let distance_decoder = HuffmanDecoder::new(&self.distance_on_bits);
+        let no_token = || DecodeError::new("bits do not match any token for current huffman codes");
+
+        loop {
+            let symbol = lit_len_decoder.decode(reader).ok_or_else(no_token)?;
+            if symbol == END_OF_BLOCK {
+                return Ok(());
+            }
+            if !SymbolCode::is_length_symbol(symbol) {
+                budget.take_bytes(1)?;
+                tokens.push(ResultEncoding::Ascii(symbol as u8));
+                continue;
+            }
+
+            let length_extra = reader.try_read(SymbolCode::length_extra_bits_num(symbol))?;
+            let len = SymbolCode::length_value(symbol, length_extra);
+            let distance_symbol = distance_decoder.decode(reader).ok_or_else(no_token)?;
+            let distance_extra =
+                reader.try_read(SymbolCode::distance_extra_bits_num(distance_symbol))?;
+            let token = SymbolCode::reference(len, distance_symbol, distance_extra);
+            budget.take(&token)?;
+            tokens.push(token);
+        }
+    }
+}
@@ -1,18 +1,23 @@
 use bit_vec::BitVec;
 use std::cmp::min;
 use std::collections::HashMap;
+use std::hash::Hash;
+
+pub trait Symbol: Copy + Ord + Hash {}
+
+impl<S: Copy + Ord + Hash> Symbol for S {}
 
 #[derive(Hash, Eq, PartialEq, PartialOrd, Clone)]
-pub struct HuffmanTree {
-    val: Option<u8>,
-    whole_tree_min_val: u8,
+pub struct HuffmanTree<S: Symbol> {
+    val: Option<S>,
+    whole_tree_min_val: S,
     weigths_sum: usize,
-    left: Option<Box<HuffmanTree>>,
-    right: Option<Box<HuffmanTree>>,
+    left: Option<Box<HuffmanTree<S>>>,
+    right: Option<Box<HuffmanTree<S>>>,
 }
 
-impl HuffmanTree {
-    pub fn single_node(val: u8, weights_sum: usize) -> Self {
+impl<S: Symbol> HuffmanTree<S> {
+    pub fn single_node(val: S, weights_sum: usize) -> Self {
         HuffmanTree {
             val: Some(val),
             whole_tree_min_val: val,
@@ -22,7 +27,7 @@ impl HuffmanTree {
         }
     }
 
-    pub fn merge_right(&mut self, tree: HuffmanTree) -> Self {
+    pub fn merge_right(&mut self, tree: HuffmanTree<S>) -> Self {
         HuffmanTree {
             val: None,
             whole_tree_min_val: min(self.whole_tree_min_val, tree.whole_tree_min_val),
@@ -32,14 +37,14 @@ impl HuffmanTree {
         }
     }
 
-    pub fn extract_mapping(&self) -> HashMap<u8, BitVec> {
+    pub fn extract_mapping(&self) -> HashMap<S, BitVec> {
         let mut mapping = HashMap::new();
         let mut prev_bits = BitVec::from_elem(1, true);
         self.recursive_extract_mapping(&mut prev_bits, &mut mapping);
         mapping
     }
 
-    fn recursive_extract_mapping(&self, prev_bits: &mut BitVec, mapping: &mut HashMap<u8, BitVec>) {
+    fn recursive_extract_mapping(&self, prev_bits: &mut BitVec, mapping: &mut HashMap<S, BitVec>) {
         if let Some(val) = self.val {
             mapping.insert(val, prev_bits.clone());
         };
@@ -61,18 +66,25 @@ impl HuffmanTree {
 pub struct HuffmanTreeCreator;
 
 impl HuffmanTreeCreator {
-    pub fn get_mappings(weights: &HashMap<u8, u8>) -> (HashMap<u8, BitVec>, HashMap<BitVec, u8>) {
+    #[allow(clippy::type_complexity)]
+    pub fn get_mappings<S: Symbol>(
+        weights: &HashMap<S, u8>,
+    ) -> (HashMap<S, BitVec>, HashMap<BitVec, S>) {
         let on_bits = HuffmanTreeCreator::get_mapping_on_bits(weights);
         let on_bytes = HuffmanTreeCreator::get_mapping_on_bytes(weights);
         (on_bits, on_bytes)
     }
 
-    pub fn get_mapping_on_bits(weights: &HashMap<u8, u8>) -> HashMap<u8, BitVec> {
+    pub fn get_mapping_on_bits<S: Symbol>(weights: &HashMap<S, u8>) -> HashMap<S, BitVec> {
         let mut trees_seq = (*weights)
             .clone()
             .into_iter()
             .map(|(byte, w)| HuffmanTree::single_node(byte, w as usize))
-            .collect::<Vec<HuffmanTree>>();
+            .collect::<Vec<HuffmanTree<S>>>();
+
+        if trees_seq.is_empty() {
+            return HashMap::new();
+        }
 
         let operations_num = trees_seq.len() - 1;
         for _ in 0..operations_num {
@@ -86,10 +98,10 @@ impl HuffmanTreeCreator {
         trees_seq[0].extract_mapping()
     }
 
-    fn get_and_rm_next_elem(trees_seq: &mut Vec<HuffmanTree>) -> HuffmanTree {
+    fn get_and_rm_next_elem<S: Symbol>(trees_seq: &mut Vec<HuffmanTree<S>>) -> HuffmanTree<S> {
         let mut index = usize::default();
         let mut min_w = usize::MAX;
-        let mut min_val = u8::MAX;
+        let mut min_val = trees_seq[0].whole_tree_min_val;
 
         trees_seq.iter().enumerate().for_each(|(i, t)| {
             if min_w > t.weigths_sum || (min_w == t.weigths_sum && min_val > t.whole_tree_min_val) {
@@ -102,7 +114,7 @@ impl HuffmanTreeCreator {
         trees_seq.remove(index)
     }
 
-    pub fn get_mapping_on_bytes(weights: &HashMap<u8, u8>) -> HashMap<BitVec, u8> {
+    pub fn get_mapping_on_bytes<S: Symbol>(weights: &HashMap<S, u8>) -> HashMap<BitVec, S> {
         let on_bits = HuffmanTreeCreator::get_mapping_on_bits(weights);
         on_bits.into_iter().map(|(byte, bit)| (bit, byte)).collect()
     }
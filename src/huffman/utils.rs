@@ -1,6 +1,13 @@
-pub fn calc_distinct_symbols_num(bytes: &[u8]) -> u8 {
-    let mut bytes = bytes.to_owned();
-    bytes.sort();
-    bytes.dedup();
-    bytes.len() as u8
+use bit_vec::BitVec;
+
+/// Appends `n` lowest bits of `value`, most significant first.
+pub fn push_bits(bits: &mut BitVec, value: u64, n: u32) {
+    (0..n).rev().for_each(|i| bits.push((value >> i) & 1 == 1));
+}
+
+/// Appends Elias gamma code of `value`, which has to be positive.
+pub fn push_gamma(bits: &mut BitVec, value: u64) {
+    let highest_bit = u64::BITS - 1 - value.leading_zeros();
+    push_bits(bits, 0, highest_bit);
+    push_bits(bits, value, highest_bit + 1);
 }
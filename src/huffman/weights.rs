@@ -1,35 +1,56 @@
-const ASCII_SZ: u8 = 127;
+const FIXED_LENGTH_SYMBOLS_NUM: u16 = 32;
+const FIXED_DISTANCE_SYMBOLS_NUM: u16 = REPEAT_SLOTS_NUM + 32;
 
 use ::std::collections::HashMap;
 use std::cmp::max;
+use std::hash::Hash;
+
+use crate::lz77::alphabet::{END_OF_BLOCK, FIRST_LENGTH_SYMBOL, REPEAT_SLOTS_NUM};
+
 pub struct WeightsCalculator;
 
 impl WeightsCalculator {
     pub fn default() -> HashMap<u8, u8> {
         let mut mapping = HashMap::new();
-        (0..ASCII_SZ).for_each(|i| {
+        (0..=u8::MAX).for_each(|i| {
             mapping.insert(i, 1);
         });
 
         mapping
     }
 
-    pub fn fitted_to_text(bytes: &[u8]) -> HashMap<u8, u8> {
-        let mut symbol_counter: HashMap<&u8, usize> = HashMap::new();
-        bytes.iter().for_each(|s| match symbol_counter.get(s) {
-            Some(count) => {
-                symbol_counter.insert(s, count + 1);
-            }
-            None => {
-                symbol_counter.insert(s, 1);
-            }
-        });
+    pub fn uniform<S: Eq + Hash>(symbols: impl Iterator<Item = S>) -> HashMap<S, u8> {
+        symbols.map(|symbol| (symbol, 1)).collect()
+    }
+
+    /// Predefined literal/length weights: all literals, end of block
+    /// and lengths short enough to appear with default window parameters.
+    pub fn default_lit_len() -> HashMap<u16, u8> {
+        let mut mapping: HashMap<u16, u8> = WeightsCalculator::default()
+            .into_iter()
+            .map(|(symbol, weight)| (symbol as u16, weight))
+            .collect();
+        mapping.insert(END_OF_BLOCK, 1);
+        mapping.extend(WeightsCalculator::uniform(
+            FIRST_LENGTH_SYMBOL..FIRST_LENGTH_SYMBOL + FIXED_LENGTH_SYMBOLS_NUM,
+        ));
 
-        let count_max = symbol_counter.values().max().unwrap();
+        mapping
+    }
+
+    pub fn default_distance() -> HashMap<u16, u8> {
+        WeightsCalculator::uniform(0..FIXED_DISTANCE_SYMBOLS_NUM)
+    }
+
+    pub fn fitted_to_counts<S: Copy + Eq + Hash>(counts: &HashMap<S, usize>) -> HashMap<S, u8> {
+        let count_max = match counts.values().max() {
+            Some(count_max) => count_max,
+            None => return HashMap::new(),
+        };
         let scaler = count_max / u8::MAX as usize + 1;
 
-        symbol_counter
-            .into_iter()
+        counts
+            .iter()
             .map(|(symbol, occurences)| (*symbol, max((occurences / scaler) as u8, 1)))
             .collect()
     }
@@ -1,17 +1,37 @@
-const SIZE_FOR_ONE_WORKER: usize = 5000;
-
+pub mod analysis;
+pub mod ans;
+pub mod archive;
+pub mod bwt;
+pub mod checksum;
+pub mod config;
+pub mod container;
+pub mod error;
 pub mod huffman;
+pub mod limits;
 pub mod lz77;
-pub mod utils;
-use bit_vec::BitVec;
+pub mod pipeline;
+pub mod progress;
+pub mod range_coder;
+pub mod stats;
+mod trace;
 
+use analysis::InputAnalysis;
+pub use archive::{ArchiveEntry, ArchiveError, ArchiveIndex};
 use core::fmt;
 use std::collections::HashMap;
 
+pub use checksum::Checksum;
+pub use config::{ConfigError, DeflateConfig, DeflateConfigBuilder, EntropyCoder, Strategy};
+pub use container::ContainerError;
+use container::ContainerHeader;
+pub use error::{CancelledError, DecodeError};
 use fstrings::{format_args_f, format_f};
-use huffman::HuffmanCompressor;
-use lz77::LZ77Compressor;
+pub use limits::DecompressLimits;
+use pipeline::Pipeline;
+pub use progress::{CancellationToken, FrameControl, JobControl, Progress};
 use rayon::prelude::*;
+pub use stats::CompressionStats;
+use trace::{scope, Parent};
 
 #[derive(Hash, PartialEq, Eq, Clone)]
 pub enum Params {
@@ -20,6 +40,21 @@ pub enum Params {
     CodesPredef,
     ApplyHuffman,
     ApplyLZ77,
+    EntropyCoder,
+    Pipeline,
+    FrameSize,
+    Checksum,
+    Stats,
+    Auto,
+    Strategy,
+    LongRange,
+    PatchFrom,
+    Input,
+    Output,
+    Decompress,
+    Archive,
+    List,
+    Bzip2,
 }
 
 impl Params {
@@ -27,9 +62,24 @@ impl Params {
         match self {
             Params::WindowSize => "Length of the interval in which in case of identical words occurence, the later one will be compressed.",
             Params::MaxBlockSize => "Max length of word we will spot in sliding window.",
-            Params::CodesPredef => "If 0 then huffman codes won't be predefined, otherwise we will calculate it according to the given text.",
+            Params::CodesPredef => "If 0 then every huffman block picks stored, predefined or fitted codes, whichever is the shortest, otherwise predefined codes are forced wherever they can express the block.",
             Params::ApplyHuffman => "If 0 then huffman algorithm is not applied in compression, otherwise it is",
-            Params::ApplyLZ77 => "If 0 then lz77 algorithm is not applied in compression, otherwise it is"
+            Params::ApplyLZ77 => "If 0 then lz77 algorithm is not applied in compression, otherwise it is",
+            Params::EntropyCoder => "Coder applied when huffman is enabled: 0 for huffman codes written in blocks, 1 for one-pass adaptive huffman, 2 for adaptive range coder, 3 for rANS.",
+            Params::Pipeline => "Comma separated stages applied in order, e.g. 'lz77,huffman', overrides -lz77, -huff, -entropy and -auto. Stages are: lz77, lz77_long, rle, bwt, mtf, zrle, huffman, adaptive_huffman, range, rans.",
+            Params::FrameSize => "Length of frames the input is split into, each compressed independently.",
+            Params::Checksum => "Checksum of the header and of every frame: 0 for none, 1 for CRC-32, 2 for xxHash32.",
+            Params::Stats => "If 0 then only compressed output is printed, otherwise also statistics of the compression.",
+            Params::Auto => "If 0 then -lz77 and -huff pick the stages, otherwise stages are picked from a sample of the input: stored, huffman only, rle or lz77 with huffman.",
+            Params::Strategy => "Strategy overriding -lz77, -huff and -auto: 0 for default, 1 for huffman only, 2 for lz77 only, 3 for stored, 4 for rle, 5 for filtered, 6 for fixed codes, 7 for auto.",
+            Params::LongRange => "If 0 then lz77 finds matches within the window only, otherwise also repeats of at least 64 bytes anywhere earlier in the frame.",
+            Params::PatchFrom => "Reference file: the -input file is compressed into a patch against it, or with -decompress 1 the -input patch is applied to it.",
+            Params::Input => "File read by -patch_from and -bz2, directory or archive read by -archive, archive listed by -list.",
+            Params::Output => "File written by -patch_from and -bz2, archive or directory written by -archive.",
+            Params::Decompress => "If 0 then -patch_from creates a patch, -archive an archive and -bz2 a .bz2 file, otherwise they apply the patch, extract the archive or decompress the .bz2 file.",
+            Params::Archive => "If 0 then lines of the standard input are compressed, otherwise files of the -input directory into a solid -output archive, or with -decompress 1 the -input archive into the -output directory.",
+            Params::Bzip2 => "If 0 then lines of the standard input are compressed, otherwise the -input file into the -output .bz2 file, or with -decompress 1 the -input .bz2 file into the -output file.",
+            Params::List => "If 0 then nothing is listed, otherwise entries of the -input archive are listed from its index.",
         }
     }
 }
@@ -58,16 +108,56 @@ impl<'a> fmt::Display for HelpDisplayer<'a> {
             .collect();
         let t1 = "Usage is: cargo run -- [options value]";
         let t2 =
-            &format_f!("Possible 'options' are:\n{possible_options}    Value should be an integer");
+            &format_f!("Possible 'options' are:\n{possible_options}    Value should be an integer, except for -pipeline");
 
         let message = [info, sep, t1, t2, sep].join("\n");
         write!(f, "{}", message)
     }
 }
 
+/// Command line options, turned into `DeflateConfig` by `config`.
 pub struct CompressionParams {
     command_line_aliases: HashMap<String, Params>,
-    params: HashMap<Params, Option<usize>>,
+    builder: DeflateConfigBuilder,
+    apply_huffman: bool,
+    apply_lz77: bool,
+    entropy_coder: EntropyCoder,
+    print_stats: bool,
+    auto_strategy: bool,
+    strategy: Option<Strategy>,
+    patch_from: Option<String>,
+    input: Option<String>,
+    output: Option<String>,
+    decompress: bool,
+    archive: bool,
+    list: bool,
+    bzip2: bool,
+}
+
+/// Patch job given by `-patch_from`, run on files instead of lines of the standard input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PatchJob {
+    pub reference: String,
+    pub input: String,
+    pub output: String,
+    /// Whether `input` is a patch applied to `reference`, rather than compressed against it.
+    pub decompress: bool,
+}
+
+/// Archive job given by `-archive` or `-list`, run on files instead of lines of the standard input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ArchiveJob {
+    Create { directory: String, archive: String },
+    Extract { archive: String, directory: String },
+    List { archive: String },
+}
+
+/// Job given by `-bz2`, compressing or decompressing a `.bz2` file with `Bzip2`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bzip2Job {
+    pub input: String,
+    pub output: String,
+    pub decompress: bool,
 }
 
 impl Default for CompressionParams {
@@ -78,102 +168,510 @@ impl Default for CompressionParams {
 
 impl CompressionParams {
     pub fn new() -> Self {
-        let mut default_params: HashMap<Params, Option<usize>> = HashMap::new();
-        default_params.insert(Params::WindowSize, None);
-        default_params.insert(Params::MaxBlockSize, None);
-        default_params.insert(Params::CodesPredef, Some(0));
-        default_params.insert(Params::ApplyHuffman, Some(1));
-        default_params.insert(Params::ApplyLZ77, Some(1));
-
         let mut aliases: HashMap<String, Params> = HashMap::new();
         aliases.insert("-window_size".to_string(), Params::WindowSize);
         aliases.insert("-max_len_of_block".to_string(), Params::MaxBlockSize);
         aliases.insert("-codes_predef".to_string(), Params::CodesPredef);
         aliases.insert("-huff".to_string(), Params::ApplyHuffman);
         aliases.insert("-lz77".to_string(), Params::ApplyLZ77);
+        aliases.insert("-entropy".to_string(), Params::EntropyCoder);
+        aliases.insert("-pipeline".to_string(), Params::Pipeline);
+        aliases.insert("-frame_size".to_string(), Params::FrameSize);
+        aliases.insert("-checksum".to_string(), Params::Checksum);
+        aliases.insert("-stats".to_string(), Params::Stats);
+        aliases.insert("-auto".to_string(), Params::Auto);
+        aliases.insert("-strategy".to_string(), Params::Strategy);
+        aliases.insert("-long_range".to_string(), Params::LongRange);
+        aliases.insert("-patch_from".to_string(), Params::PatchFrom);
+        aliases.insert("-input".to_string(), Params::Input);
+        aliases.insert("-output".to_string(), Params::Output);
+        aliases.insert("-decompress".to_string(), Params::Decompress);
+        aliases.insert("-archive".to_string(), Params::Archive);
+        aliases.insert("-list".to_string(), Params::List);
+        aliases.insert("-bz2".to_string(), Params::Bzip2);
         Self {
             command_line_aliases: aliases,
-            params: default_params,
+            builder: DeflateConfigBuilder::new(),
+            apply_huffman: true,
+            apply_lz77: true,
+            entropy_coder: EntropyCoder::default(),
+            print_stats: false,
+            auto_strategy: false,
+            strategy: None,
+            patch_from: None,
+            input: None,
+            output: None,
+            decompress: false,
+            archive: false,
+            list: false,
+            bzip2: false,
         }
     }
 
-    pub fn update(&mut self, alias: &String, value: usize) {
-        let param = &self
+    /// Updates parameter of an integer option. `-pipeline`, `-patch_from`, `-input`
+    /// and `-output` take strings, so they are set only by `update_from_str`.
+    pub fn update(&mut self, alias: &String, value: usize) -> Result<(), ConfigError> {
+        let param = self
             .command_line_aliases
             .get(alias)
-            .unwrap_or_else(|| panic!("{}", HelpDisplayer::new(self)));
-        if let Some(old_val) = self.params.get_mut(param) {
-            *old_val = Some(value);
+            .ok_or_else(|| ConfigError::UnknownOption(alias.clone()))?;
+        let builder = self.builder.clone();
+        match param {
+            Params::WindowSize => self.builder = builder.window_size(value),
+            Params::MaxBlockSize => self.builder = builder.max_match_len(value),
+            Params::CodesPredef => self.builder = builder.predefined_codes(value > 0),
+            Params::ApplyHuffman => self.apply_huffman = value > 0,
+            Params::ApplyLZ77 => self.apply_lz77 = value > 0,
+            Params::EntropyCoder => self.entropy_coder = EntropyCoder::from_param(value)?,
+            Params::FrameSize => self.builder = builder.frame_size(value),
+            Params::Checksum => {
+                let checksum = u8::try_from(value)
+                    .ok()
+                    .and_then(Checksum::from_id)
+                    .ok_or(ConfigError::Checksum(value))?;
+                self.builder = builder.checksum(checksum);
+            }
+            Params::Stats => self.print_stats = value > 0,
+            Params::Auto => self.auto_strategy = value > 0,
+            Params::Strategy => self.strategy = Some(Strategy::from_param(value)?),
+            Params::LongRange => self.builder = builder.long_range(value > 0),
+            Params::Pipeline | Params::PatchFrom | Params::Input | Params::Output => {
+                return Err(ConfigError::InvalidValue {
+                    option: alias.clone(),
+                    value: value.to_string(),
+                })
+            }
+            Params::Decompress => self.decompress = value > 0,
+            Params::Archive => self.archive = value > 0,
+            Params::List => self.list = value > 0,
+            Params::Bzip2 => self.bzip2 = value > 0,
+        }
+
+        Ok(())
+    }
+
+    /// Updates parameter from its command line form, where `-pipeline` takes a spec,
+    /// `-patch_from`, `-input` and `-output` paths and every other option an integer.
+    pub fn update_from_str(&mut self, alias: &String, value: &str) -> Result<(), ConfigError> {
+        match self.command_line_aliases.get(alias) {
+            Some(Params::Pipeline) => {
+                self.builder = self.builder.clone().pipeline(value);
+                return Ok(());
+            }
+            Some(param @ (Params::PatchFrom | Params::Input | Params::Output)) => {
+                self.update_path(param.clone(), value.to_string());
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        let value = value.parse().map_err(|_| ConfigError::InvalidValue {
+            option: alias.clone(),
+            value: value.to_string(),
+        })?;
+        self.update(alias, value)
+    }
+
+    fn update_path(&mut self, param: Params, path: String) {
+        match param {
+            Params::PatchFrom => self.patch_from = Some(path),
+            Params::Input => self.input = Some(path),
+            _ => self.output = Some(path),
+        }
+    }
+
+    pub fn print_stats(&self) -> bool {
+        self.print_stats
+    }
+
+    /// Patch job if `-patch_from` is given, which needs `-input` and `-output` as well.
+    pub fn patch_job(&self) -> Result<Option<PatchJob>, ConfigError> {
+        let Some(reference) = &self.patch_from else {
+            return Ok(None);
+        };
+        match (&self.input, &self.output) {
+            (Some(input), Some(output)) => Ok(Some(PatchJob {
+                reference: reference.clone(),
+                input: input.clone(),
+                output: output.clone(),
+                decompress: self.decompress,
+            })),
+            _ => Err(ConfigError::PatchFiles),
+        }
+    }
+
+    /// Archive job if `-list` or `-archive` is given, which need `-input`,
+    /// and `-archive` also `-output`.
+    pub fn archive_job(&self) -> Result<Option<ArchiveJob>, ConfigError> {
+        let input = self.input.clone();
+        let output = self.output.clone();
+        match (self.list, self.archive, input, output) {
+            (false, false, _, _) => Ok(None),
+            (true, _, Some(archive), _) => Ok(Some(ArchiveJob::List { archive })),
+            (false, true, Some(input), Some(output)) => match self.decompress {
+                true => Ok(Some(ArchiveJob::Extract {
+                    archive: input,
+                    directory: output,
+                })),
+                false => Ok(Some(ArchiveJob::Create {
+                    directory: input,
+                    archive: output,
+                })),
+            },
+            _ => Err(ConfigError::ArchiveFiles),
+        }
+    }
+
+    /// Bzip2 job if `-bz2` is given, which needs `-input` and `-output` as well.
+    pub fn bzip2_job(&self) -> Result<Option<Bzip2Job>, ConfigError> {
+        if !self.bzip2 {
+            return Ok(None);
+        }
+        match (&self.input, &self.output) {
+            (Some(input), Some(output)) => Ok(Some(Bzip2Job {
+                input: input.clone(),
+                output: output.clone(),
+                decompress: self.decompress,
+            })),
+            _ => Err(ConfigError::Bzip2Files),
         }
     }
 
-    #[allow(dead_code)]
-    pub fn get_param(&self, param: &Params) -> Option<usize> {
-        *self.params.get(param).unwrap()
+    pub fn config(&self) -> Result<DeflateConfig, ConfigError> {
+        let strategy = match (self.strategy, self.auto_strategy) {
+            (Some(strategy), _) => strategy,
+            (None, true) => Strategy::Auto,
+            (None, false) => Strategy::from_stages(self.apply_lz77, self.apply_huffman),
+        };
+        self.builder
+            .clone()
+            .strategy(strategy)
+            .entropy_coder(self.entropy_coder)
+            .build()
     }
 }
 
 #[warn(dead_code)]
 pub struct DeflateCompression {
-    lz77_compressor: LZ77Compressor,
-    apply_huffman: bool,
-    huffman_codes_predefined: bool,
-    apply_lz77: bool,
+    pipeline: Pipeline<'static>,
+    config: DeflateConfig,
+    auto: Option<DeflateConfig>,
+    frame_size: usize,
+    checksum: Checksum,
+    limits: DecompressLimits,
 }
 
 impl DeflateCompression {
-    pub fn new(compression_params: &CompressionParams) -> Self {
-        let window_size = compression_params.get_param(&Params::WindowSize);
-        let max_block_size = compression_params.get_param(&Params::MaxBlockSize);
-        let predefined_codes = compression_params
-            .get_param(&Params::CodesPredef)
-            .unwrap_or(0)
-            > 0;
-        let apply_huffman = compression_params
-            .get_param(&Params::ApplyHuffman)
-            .unwrap_or(1)
-            > 0;
-        let apply_lz77 = compression_params
-            .get_param(&Params::ApplyLZ77)
-            .unwrap_or(1)
-            > 0;
+    pub fn new(config: &DeflateConfig) -> Self {
         DeflateCompression {
-            lz77_compressor: LZ77Compressor::new(window_size, max_block_size),
-            apply_huffman,
-            huffman_codes_predefined: predefined_codes,
-            apply_lz77,
+            pipeline: config.build_pipeline(),
+            config: config.clone(),
+            auto: (config.strategy() == Strategy::Auto && config.pipeline().is_none())
+                .then(|| config.clone()),
+            frame_size: config.frame_size(),
+            checksum: config.checksum(),
+            limits: DecompressLimits::default(),
         }
     }
 
-    pub fn deflate_compress(&mut self, text: &String) -> utils::TypeOr<BitVec, Vec<u8>> {
-        let mut result = Vec::from(text.as_bytes());
-        if self.apply_lz77 {
-            result = 
-                result.par_chunks(SIZE_FOR_ONE_WORKER)
-                .map(|chunk| self.lz77_compressor.compress(&chunk.to_vec()))
-                .flatten()
-                .collect()
+    /// Compression with a custom pipeline, otherwise default settings.
+    pub fn with_pipeline(pipeline: Pipeline<'static>) -> Self {
+        let config = DeflateConfig::default();
+        DeflateCompression {
+            pipeline,
+            config: config.clone(),
+            auto: None,
+            frame_size: config.frame_size(),
+            checksum: config.checksum(),
+            limits: DecompressLimits::default(),
+        }
+    }
+
+    /// Limits decompressed data has to fit in, `DecompressLimits::default()` unless set.
+    pub fn with_limits(mut self, limits: DecompressLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Pipeline given by the config. With `Strategy::Auto` it is the default one,
+    /// while every input is compressed by the pipeline of its own strategy.
+    pub fn pipeline(&self) -> &Pipeline<'static> {
+        &self.pipeline
+    }
+
+    pub fn limits(&self) -> &DecompressLimits {
+        &self.limits
+    }
+
+    /// Compresses `bytes` into a stream starting with `ContainerHeader`, followed by frames
+    /// compressed independently of each other.
+    pub fn compress(&self, bytes: &[u8]) -> Vec<u8> {
+        self.compress_with_control(bytes, &JobControl::new())
+            .expect("compression without cancellation token cannot be cancelled")
+    }
+
+    /// Same as `compress`, reporting progress after every chunk searched for matches
+    /// and every frame, and stopping early once the cancellation token of `control`
+    /// is cancelled, see `JobControl`.
+    pub fn compress_with_control(
+        &self,
+        bytes: &[u8],
+        control: &JobControl,
+    ) -> Result<Vec<u8>, CancelledError> {
+        let selected = self.select_pipeline(bytes);
+        let pipeline = selected.as_ref().unwrap_or(&self.pipeline);
+        self.compress_with_pipeline(pipeline, bytes, control, None)
+    }
+
+    /// Compresses `target` into a patch against `reference`, e.g. an earlier version of it,
+    /// with the stages of the config whose LZ77 stage is replaced by `PatchCompressor`.
+    /// Frames of `target` are compressed independently, each against the whole reference.
+    /// The patch is a stream like any other, whose header names the `patch` stage.
+    pub fn compress_patch(&self, reference: &[u8], target: &[u8]) -> Vec<u8> {
+        let pipeline = self.config.build_patch_pipeline(reference);
+        self.compress_with_pipeline(&pipeline, target, &JobControl::new(), None)
+            .expect("compression without cancellation token cannot be cancelled")
+    }
+
+    /// Reproduces the target of `patch` from the same `reference` it was compressed against.
+    /// Applied to another reference, it fails on checksums of frames, unless they are disabled.
+    pub fn apply_patch(&self, reference: &[u8], patch: &[u8]) -> Result<Vec<u8>, ContainerError> {
+        self.decompress_stream(patch, Some(reference), &JobControl::new())
+    }
+
+    /// Compresses `entries` into a solid archive, see `ArchiveIndex`: data of all entries
+    /// is compressed as one stream, ordered by type and similarity and with identical
+    /// files stored once, so that matches span the files. Fails if a name or the number
+    /// of entries does not fit in the index.
+    pub fn compress_archive(&self, entries: &[ArchiveEntry]) -> Result<Vec<u8>, ArchiveError> {
+        let (index, solid) = ArchiveIndex::build(entries);
+        let index = index.to_bytes()?;
+        Ok(ArchiveIndex::archive(
+            &self.compress(&index),
+            &self.compress(&solid),
+        ))
+    }
+
+    /// Entries of an archive written by `compress_archive`, in the order they were given.
+    pub fn decompress_archive(&self, bytes: &[u8]) -> Result<Vec<ArchiveEntry>, ArchiveError> {
+        let index = self.list_archive(bytes)?;
+        let (_, solid) = ArchiveIndex::split_archive(bytes)?;
+        let solid = self.decompress(solid).map_err(ArchiveError::Container)?;
+        index.extract(&solid)
+    }
+
+    /// Index of an archive written by `compress_archive`, decompressing none of the entries.
+    pub fn list_archive(&self, bytes: &[u8]) -> Result<ArchiveIndex, ArchiveError> {
+        let (index, _) = ArchiveIndex::split_archive(bytes)?;
+        let index = self.decompress(index).map_err(ArchiveError::Container)?;
+        ArchiveIndex::from_bytes(&index)
+    }
+
+    /// Compresses `bytes` into frames of `pipeline`, recording statistics of every frame
+    /// into `stats` if given. Frames recording statistics check cancellation only before
+    /// they start.
+    fn compress_with_pipeline(
+        &self,
+        pipeline: &Pipeline,
+        bytes: &[u8],
+        control: &JobControl,
+        mut stats: Option<&mut CompressionStats>,
+    ) -> Result<Vec<u8>, CancelledError> {
+        let scope =
+            scope!(INFO, "compress", input_bytes = bytes.len(), pipeline = %pipeline.spec());
+        let parent = Parent::current();
+        let cancellation = control.cancellation_token();
+        let tracker = control.tracker(bytes.len() as u64);
+        let header = self.header(pipeline, bytes.len());
+        let mut compressed = Vec::new();
+        header.append(&mut compressed);
+        let recording = stats.is_some();
+
+        let frames = bytes
+            .par_chunks(self.frame_size)
+            .map(|frame| {
+                cancellation.check()?;
+                let scope =
+                    scope!(parent: parent, DEBUG, "compress_frame", input_bytes = frame.len());
+                let control = FrameControl::reporting(cancellation, &tracker);
+                let mut frame_stats = recording.then(CompressionStats::new);
+                let mut compressed_frame = Vec::new();
+                let payload = match frame_stats.as_mut() {
+                    Some(frame_stats) => pipeline.encode_with_stats(frame.to_vec(), frame_stats),
+                    None => pipeline.encode_with_control(frame.to_vec(), &control)?,
+                };
+                let stored = header.append_frame(&mut compressed_frame, &payload, frame);
+                if let Some(frame_stats) = frame_stats.as_mut() {
+                    if stored {
+                        frame_stats.stored_frames_num += 1;
+                    }
+                    frame_stats.header_bits += header.frame_overhead() as u64 * 8;
+                }
+                scope.finish(compressed_frame.len());
+                control.finish(frame.len());
+                Ok((compressed_frame, frame_stats))
+            })
+            .collect::<Result<Vec<(Vec<u8>, Option<CompressionStats>)>, CancelledError>>()?;
+        if let Some(stats) = stats.as_deref_mut() {
+            stats.header_bits += compressed.len() as u64 * 8;
         }
+        for (frame, frame_stats) in frames.iter() {
+            compressed.extend(frame);
+            if let (Some(stats), Some(frame_stats)) = (stats.as_deref_mut(), frame_stats) {
+                stats.merge(frame_stats);
+            }
+        }
+        if let Some(stats) = stats {
+            stats.input_len = bytes.len() as u64;
+            stats.compressed_len = compressed.len() as u64;
+        }
+        scope.finish(compressed.len());
+        Ok(compressed)
+    }
+
+    /// Same as `compress`, additionally reporting statistics of the compression.
+    pub fn compress_with_stats(&self, bytes: &[u8]) -> (Vec<u8>, CompressionStats) {
+        let selected = self.select_pipeline(bytes);
+        let pipeline = selected.as_ref().unwrap_or(&self.pipeline);
+        let mut stats = CompressionStats::new();
+        let compressed = self
+            .compress_with_pipeline(pipeline, bytes, &JobControl::new(), Some(&mut stats))
+            .expect("compression without cancellation token cannot be cancelled");
+        (compressed, stats)
+    }
 
-        if self.apply_huffman {
-            return utils::TypeOr::Left(HuffmanCompressor::compress(
-                &result,
-                self.huffman_codes_predefined,
-            ));
+    /// Upper bound on the length of compressed `input_len` bytes, see `ContainerHeader`.
+    /// With `Strategy::Auto` it holds for every strategy picked, as none of them has
+    /// a longer spec than the default pipeline.
+    pub fn max_compressed_len(&self, input_len: usize) -> u64 {
+        self.header(&self.pipeline, input_len).max_compressed_len()
+    }
+
+    /// Pipeline of the strategy `InputAnalysis` picks for `bytes` with `Strategy::Auto`,
+    /// `None` when the configured pipeline is used.
+    fn select_pipeline(&self, bytes: &[u8]) -> Option<Pipeline<'static>> {
+        let config = self.auto.as_ref()?;
+        let strategy = InputAnalysis::of(bytes).strategy();
+        Some(config.with_strategy(strategy).build_pipeline())
+    }
+
+    fn header(&self, pipeline: &Pipeline, input_len: usize) -> ContainerHeader {
+        ContainerHeader {
+            pipeline_spec: pipeline.spec(),
+            window_size: pipeline.window_size() as u32,
+            original_len: input_len as u64,
+            frame_size: self.frame_size as u32,
+            checksum: self.checksum,
         }
-        utils::TypeOr::Right(result)
     }
 
-    pub fn deflate_decompress(&self, seq: &utils::TypeOr<BitVec, Vec<u8>>) -> String {
-        let result = match seq {
-            //utils::TypeOr::Left(bits) => self.huffman_compressor.decompress(bits),
-            utils::TypeOr::Left(bits) => HuffmanCompressor::decompress(bits),
-            utils::TypeOr::Right(bytes) => bytes.clone(),
-        };
+    /// Decompresses stream of any pipeline of known stages, whatever this instance
+    /// was configured with. Custom stages are decoded only by a matching pipeline.
+    /// Fails as soon as decompressed data would exceed `limits`.
+    pub fn decompress(&self, bytes: &[u8]) -> Result<Vec<u8>, ContainerError> {
+        self.decompress_with_control(bytes, &JobControl::new())
+    }
+
+    /// Same as `decompress`, reporting progress in bytes of compressed input after the header
+    /// and after every frame, and failing with `ContainerError::Cancelled` once the cancellation token
+    /// of `control` is cancelled, checked before every stage of every frame.
+    pub fn decompress_with_control(
+        &self,
+        bytes: &[u8],
+        control: &JobControl,
+    ) -> Result<Vec<u8>, ContainerError> {
+        self.decompress_stream(bytes, None, control)
+    }
 
-        if self.apply_lz77 {
-            return String::from_utf8(self.lz77_compressor.decompress(&result)).unwrap();
+    /// Decompresses stream, of a patch against `reference` if given.
+    fn decompress_stream(
+        &self,
+        bytes: &[u8],
+        reference: Option<&[u8]>,
+        control: &JobControl,
+    ) -> Result<Vec<u8>, ContainerError> {
+        let cancellation = control.cancellation_token();
+        let tracker = control.tracker(bytes.len() as u64);
+        let scope = scope!(INFO, "decompress", input_bytes = bytes.len());
+        let parent = Parent::current();
+        let (header, header_len) = ContainerHeader::read(bytes)?;
+        // Lengths of frames are verified, so the ratio is checked once for the whole stream.
+        let output_limit = self.limits.output_limit(bytes.len());
+        if header.original_len > output_limit {
+            return Err(ContainerError::OutputLimit {
+                declared: header.original_len,
+                limit: output_limit,
+            });
         }
-        String::from_utf8(result).unwrap()
+        tracker.advance(header_len);
+        let stream_pipeline;
+        let pipeline = match (reference, header.pipeline_spec == self.pipeline.spec()) {
+            (None, true) => &self.pipeline,
+            (None, false) => {
+                stream_pipeline =
+                    Pipeline::from_spec(&header.pipeline_spec, &DeflateConfig::default())
+                        .map_err(ContainerError::from_unknown_stage)?;
+                &stream_pipeline
+            }
+            (Some(reference), _) => {
+                stream_pipeline = Pipeline::from_patch_spec(
+                    &header.pipeline_spec,
+                    &DeflateConfig::default(),
+                    reference,
+                )
+                .map_err(ContainerError::UnknownStage)?;
+                &stream_pipeline
+            }
+        };
+
+        let frames = header
+            .split_frames(&bytes[header_len..])?
+            .into_par_iter()
+            .enumerate()
+            .map(|(i, compressed_frame)| {
+                cancellation.check().map_err(ContainerError::Cancelled)?;
+                let scope = scope!(
+                    parent: parent,
+                    DEBUG,
+                    "decompress_frame",
+                    frame = i,
+                    input_bytes = compressed_frame.compressed.len()
+                );
+                let frame = match compressed_frame.stored {
+                    true => compressed_frame.compressed.to_vec(),
+                    false => pipeline.decode_checked(
+                        compressed_frame.compressed.to_vec(),
+                        &DecompressLimits::unlimited()
+                            .with_max_output_len(header.frame_len(i))
+                            .with_max_reference_len(self.limits.max_reference_len()),
+                        || cancellation.check().map_err(ContainerError::Cancelled),
+                    )?,
+                };
+                if frame.len() as u64 != header.frame_len(i) {
+                    return Err(ContainerError::LengthMismatch {
+                        expected: header.frame_len(i),
+                        actual: frame.len() as u64,
+                    });
+                }
+                if !header.checksum.verify(compressed_frame.checksum, &frame) {
+                    return Err(ContainerError::FrameChecksumMismatch { frame: i });
+                }
+                scope.finish(frame.len());
+                tracker.advance(compressed_frame.total_len());
+                Ok(frame)
+            })
+            .collect::<Result<Vec<Vec<u8>>, ContainerError>>()?;
+
+        let decompressed = frames.concat();
+        scope.finish(decompressed.len());
+        Ok(decompressed)
+    }
+
+    pub fn deflate_compress(&mut self, text: &String) -> Vec<u8> {
+        self.compress(text.as_bytes())
+    }
+
+    pub fn deflate_decompress(&self, bytes: &[u8]) -> Result<String, ContainerError> {
+        String::from_utf8(self.decompress(bytes)?).map_err(|_| ContainerError::InvalidUtf8)
     }
 }
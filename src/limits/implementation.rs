@@ -0,0 +1,115 @@
+const DEFAULT_MAX_OUTPUT_LEN: u64 = 1 << 32;
+
+use crate::config::MAX_WINDOW_SIZE;
+use crate::error::DecodeError;
+use crate::lz77::implementation::ResultEncoding;
+
+/// Bounds on decompressed data, so a small crafted input cannot make decoding allocate
+/// without bound:
+///  - `max_output_len`: bytes of decompressed data,
+///  - `max_ratio`: bytes of decompressed data per byte of compressed input, unlimited by default,
+///  - `max_reference_len`: length of a single back reference, by default the largest
+///    window accepted by `DeflateConfig`, which no match can exceed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecompressLimits {
+    max_output_len: u64,
+    max_ratio: Option<u64>,
+    max_reference_len: usize,
+}
+
+impl Default for DecompressLimits {
+    fn default() -> Self {
+        DecompressLimits {
+            max_output_len: DEFAULT_MAX_OUTPUT_LEN,
+            max_ratio: None,
+            max_reference_len: MAX_WINDOW_SIZE,
+        }
+    }
+}
+
+impl DecompressLimits {
+    pub fn new() -> Self {
+        DecompressLimits::default()
+    }
+
+    pub fn unlimited() -> Self {
+        DecompressLimits {
+            max_output_len: u64::MAX,
+            max_ratio: None,
+            max_reference_len: usize::MAX,
+        }
+    }
+
+    pub fn with_max_output_len(mut self, max_output_len: u64) -> Self {
+        self.max_output_len = max_output_len;
+        self
+    }
+
+    pub fn with_max_ratio(mut self, max_ratio: u64) -> Self {
+        self.max_ratio = Some(max_ratio);
+        self
+    }
+
+    pub fn with_max_reference_len(mut self, max_reference_len: usize) -> Self {
+        self.max_reference_len = max_reference_len;
+        self
+    }
+
+    pub fn max_output_len(&self) -> u64 {
+        self.max_output_len
+    }
+
+    pub fn max_ratio(&self) -> Option<u64> {
+        self.max_ratio
+    }
+
+    pub fn max_reference_len(&self) -> usize {
+        self.max_reference_len
+    }
+
+    /// Bytes which may be decompressed from `input_len` bytes, by both the output and ratio limits.
+    pub fn output_limit(&self, input_len: usize) -> u64 {
+        match self.max_ratio {
+            Some(ratio) => self
+                .max_output_len
+                .min(ratio.saturating_mul(input_len as u64)),
+            None => self.max_output_len,
+        }
+    }
+}
+
+/// Output left to decode under `DecompressLimits`, charged token by token,
+/// so decoders fail before producing data over the limits.
+pub struct OutputBudget {
+    remaining: u64,
+    max_reference_len: usize,
+}
+
+impl OutputBudget {
+    pub fn new(limits: &DecompressLimits) -> Self {
+        OutputBudget {
+            remaining: limits.max_output_len,
+            max_reference_len: limits.max_reference_len,
+        }
+    }
+
+    pub fn take(&mut self, token: &ResultEncoding) -> Result<(), DecodeError> {
+        if let ResultEncoding::Reference(_, len) | ResultEncoding::Repeat(_, len) = token {
+            if *len > self.max_reference_len {
+                return Err(DecodeError(format!(
+                    "reference of length {} exceeds the limit of {}",
+                    len, self.max_reference_len
+                )));
+            }
+        }
+        self.take_bytes(token.expanded_len())
+    }
+
+    pub fn take_bytes(&mut self, bytes_num: usize) -> Result<(), DecodeError> {
+        self.remaining = self
+            .remaining
+            .checked_sub(bytes_num as u64)
+            .ok_or_else(|| DecodeError::new("decoded data exceeds the output limit"))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,3 @@
+pub mod implementation;
+
+pub use implementation::{DecompressLimits, OutputBudget};
@@ -0,0 +1,145 @@
+pub const LITERALS_NUM: u16 = 256;
+pub const END_OF_BLOCK: u16 = LITERALS_NUM;
+pub const FIRST_LENGTH_SYMBOL: u16 = END_OF_BLOCK + 1;
+/// Distance symbols below it stand for recent distances, see `RepeatDistances`.
+pub const REPEAT_SLOTS_NUM: u16 = 4;
+const BUCKETS_NUM: u16 = 2 * usize::BITS as u16;
+pub const DISTANCE_SYMBOLS_NUM: u16 = REPEAT_SLOTS_NUM + BUCKETS_NUM;
+pub const LIT_LEN_SYMBOLS_NUM: u16 = FIRST_LENGTH_SYMBOL + BUCKETS_NUM;
+const SYMBOLS_WITHOUT_EXTRA_BITS: usize = 4;
+
+use super::implementation::ResultEncoding;
+
+/// Symbol of a token together with raw bits refining its value.
+///
+/// Values are split into buckets in the same way DEFLATE encodes distances:
+/// values below 4 get their own symbol, bigger ones share a symbol with
+/// values having the same two most significant bits, and the rest goes to extra bits.
+/// Distance symbols come after `REPEAT_SLOTS_NUM` symbols of repeated distances.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SymbolCode {
+    pub symbol: u16,
+    pub extra_bits_num: u32,
+    pub extra_bits: u64,
+}
+
+impl SymbolCode {
+    pub fn literal(c: u8) -> Self {
+        SymbolCode {
+            symbol: c as u16,
+            extra_bits_num: 0,
+            extra_bits: 0,
+        }
+    }
+
+    pub fn length(len: usize) -> Self {
+        let mut code = SymbolCode::bucket(len - ResultEncoding::len_treshold());
+        code.symbol += FIRST_LENGTH_SYMBOL;
+        code
+    }
+
+    pub fn distance(dist: usize) -> Self {
+        let mut code = SymbolCode::bucket(dist - 1);
+        code.symbol += REPEAT_SLOTS_NUM;
+        code
+    }
+
+    pub fn repeat(slot: usize) -> Self {
+        SymbolCode {
+            symbol: slot as u16,
+            extra_bits_num: 0,
+            extra_bits: 0,
+        }
+    }
+
+    pub fn is_length_symbol(symbol: u16) -> bool {
+        symbol >= FIRST_LENGTH_SYMBOL
+    }
+
+    pub fn is_repeat_symbol(symbol: u16) -> bool {
+        symbol < REPEAT_SLOTS_NUM
+    }
+
+    pub fn length_extra_bits_num(symbol: u16) -> u32 {
+        SymbolCode::bucket_extra_bits_num(symbol - FIRST_LENGTH_SYMBOL)
+    }
+
+    pub fn distance_extra_bits_num(symbol: u16) -> u32 {
+        match SymbolCode::is_repeat_symbol(symbol) {
+            true => 0,
+            false => SymbolCode::bucket_extra_bits_num(symbol - REPEAT_SLOTS_NUM),
+        }
+    }
+
+    pub fn length_value(symbol: u16, extra_bits: u64) -> usize {
+        SymbolCode::bucket_value(symbol - FIRST_LENGTH_SYMBOL, extra_bits)
+            .saturating_add(ResultEncoding::len_treshold())
+    }
+
+    /// Distance of a symbol which is not a repeat symbol.
+    pub fn distance_value(symbol: u16, extra_bits: u64) -> usize {
+        SymbolCode::bucket_value(symbol - REPEAT_SLOTS_NUM, extra_bits).saturating_add(1)
+    }
+
+    /// Token of a match of length `len` with its distance coded by `distance_symbol`
+    /// and `distance_extra_bits`.
+    pub fn reference(len: usize, distance_symbol: u16, distance_extra_bits: u64) -> ResultEncoding {
+        match SymbolCode::is_repeat_symbol(distance_symbol) {
+            true => ResultEncoding::Repeat(distance_symbol as usize, len),
+            false => ResultEncoding::Reference(
+                SymbolCode::distance_value(distance_symbol, distance_extra_bits),
+                len,
+            ),
+        }
+    }
+
+    fn bucket(n: usize) -> Self {
+        if n < SYMBOLS_WITHOUT_EXTRA_BITS {
+            return SymbolCode {
+                symbol: n as u16,
+                extra_bits_num: 0,
+                extra_bits: 0,
+            };
+        }
+
+        let highest_bit = usize::BITS - 1 - n.leading_zeros();
+        let extra_bits_num = highest_bit - 1;
+        SymbolCode {
+            symbol: (2 * highest_bit as usize + ((n >> extra_bits_num) & 1)) as u16,
+            extra_bits_num,
+            extra_bits: (n & ((1 << extra_bits_num) - 1)) as u64,
+        }
+    }
+
+    fn bucket_extra_bits_num(symbol: u16) -> u32 {
+        match symbol as usize {
+            s if s < SYMBOLS_WITHOUT_EXTRA_BITS => 0,
+            s => s as u32 / 2 - 1,
+        }
+    }
+
+    fn bucket_value(symbol: u16, extra_bits: u64) -> usize {
+        let s = symbol as usize;
+        if s < SYMBOLS_WITHOUT_EXTRA_BITS {
+            return s;
+        }
+
+        let extra_bits_num = SymbolCode::bucket_extra_bits_num(symbol);
+        ((2 | (s & 1)) << extra_bits_num) + extra_bits as usize
+    }
+}
+
+impl ResultEncoding {
+    /// Splits token into literal/length code and, for references, distance code.
+    pub fn to_symbol_codes(&self) -> (SymbolCode, Option<SymbolCode>) {
+        match self {
+            ResultEncoding::Ascii(c) => (SymbolCode::literal(*c), None),
+            ResultEncoding::Reference(d, l) => {
+                (SymbolCode::length(*l), Some(SymbolCode::distance(*d)))
+            }
+            ResultEncoding::Repeat(slot, l) => {
+                (SymbolCode::length(*l), Some(SymbolCode::repeat(*slot)))
+            }
+        }
+    }
+}
@@ -0,0 +1,234 @@
+/// Estimated bits of a literal coded by an entropy coder, typical for text and binary data.
+const ENTROPY_LITERAL_BITS: usize = 6;
+/// Estimated bits of a length or distance symbol coded by an entropy coder, extra bits excluded.
+const ENTROPY_SYMBOL_BITS: usize = 5;
+const ASCII_LITERAL_BITS: usize = 8;
+/// Separators around and between distance and length of a reference in the ascii form.
+const ASCII_SEPARATORS_NUM: usize = 3;
+/// Costs are counted in this fraction of a bit, so literals coded in fractions of bits
+/// are weighed precisely.
+const BIT_FRACTIONS: usize = 8;
+/// Bounds of bits of a literal fitted to its count. Bytes covered by matches are counted
+/// as literals too, so frequent literals would otherwise look cheaper than they end up.
+const MIN_FITTED_LITERAL_BITS: f64 = 1.0;
+const MAX_FITTED_LITERAL_BITS: f64 = 15.0;
+
+use std::array;
+
+use super::alphabet::LITERALS_NUM;
+use super::implementation::ResultEncoding;
+
+/// How an entropy coder fits the code of literals to the block it codes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LiteralFitting {
+    /// Predefined code, the same for every block.
+    Predefined,
+    /// Code of whole bits fitted to the counts of literals, like Huffman codes.
+    WholeBits,
+    /// Literals coded in fractions of bits given by their probabilities, like range coding.
+    Fractional,
+}
+
+/// Estimated bits of symbols of the entropy coder following LZ77, extra bits excluded.
+/// Literals of coders fitting their code to the block cost `ENTROPY_LITERAL_BITS`
+/// until the costs are fitted to the block with `fitted_to`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SymbolCosts {
+    /// Costs of literals in `1 / BIT_FRACTIONS` of a bit, as the rest of the costs.
+    literals: [u8; LITERALS_NUM as usize],
+    mean_literal: u16,
+    length: u16,
+    distance: u16,
+    fitting: LiteralFitting,
+}
+
+impl SymbolCosts {
+    /// Costs of a coder fitting the code of literals to every block, coding length
+    /// and distance symbols in about `length_bits` and `distance_bits`.
+    pub fn fitted(fitting: LiteralFitting, length_bits: usize, distance_bits: usize) -> Self {
+        SymbolCosts::new(
+            |_| ENTROPY_LITERAL_BITS,
+            length_bits,
+            distance_bits,
+            fitting,
+        )
+    }
+
+    /// Costs of predefined codes: `literal_bits` of every literal and the longest codes
+    /// of length and distance symbols.
+    pub fn predefined(
+        literal_bits: impl Fn(u8) -> usize,
+        length_bits: usize,
+        distance_bits: usize,
+    ) -> Self {
+        SymbolCosts::new(
+            literal_bits,
+            length_bits,
+            distance_bits,
+            LiteralFitting::Predefined,
+        )
+    }
+
+    fn new(
+        literal_bits: impl Fn(u8) -> usize,
+        length_bits: usize,
+        distance_bits: usize,
+        fitting: LiteralFitting,
+    ) -> Self {
+        let literals = array::from_fn(|byte| (literal_bits(byte as u8) * BIT_FRACTIONS) as u8);
+        let mean_literal =
+            literals.iter().map(|cost| *cost as usize).sum::<usize>() / LITERALS_NUM as usize;
+        SymbolCosts {
+            literals,
+            mean_literal: mean_literal as u16,
+            length: (length_bits * BIT_FRACTIONS) as u16,
+            distance: (distance_bits * BIT_FRACTIONS) as u16,
+            fitting,
+        }
+    }
+
+    pub fn fitting(&self) -> LiteralFitting {
+        self.fitting
+    }
+
+    /// Costs with literals priced as the code the coder would fit to `bytes` taken
+    /// as literals, a first pass over the block before its matches are known.
+    /// Predefined costs stay as they are.
+    pub fn fitted_to(&self, bytes: &[u8]) -> Self {
+        if self.fitting == LiteralFitting::Predefined || bytes.is_empty() {
+            return *self;
+        }
+
+        let mut counts = [0usize; LITERALS_NUM as usize];
+        bytes.iter().for_each(|byte| counts[*byte as usize] += 1);
+        let literals = counts.map(|count| {
+            let bits = match count {
+                0 => MAX_FITTED_LITERAL_BITS,
+                _ => -(count as f64 / bytes.len() as f64).log2(),
+            };
+            let bits = match self.fitting {
+                LiteralFitting::WholeBits => bits.round(),
+                _ => bits,
+            };
+            (bits.clamp(MIN_FITTED_LITERAL_BITS, MAX_FITTED_LITERAL_BITS) * BIT_FRACTIONS as f64)
+                .round() as u8
+        });
+        let mean_literal = counts
+            .iter()
+            .zip(literals)
+            .map(|(count, cost)| count * cost as usize)
+            .sum::<usize>()
+            / bytes.len();
+        SymbolCosts {
+            literals,
+            mean_literal: mean_literal as u16,
+            ..*self
+        }
+    }
+}
+
+/// Estimated cost of tokens in the output of the stage following LZ77, deciding whether
+/// a match is worth a reference rather than literals:
+///  - `Entropy`: tokens coded by an unknown entropy coder over `SymbolCode`s, a literal
+///    costs `ENTROPY_LITERAL_BITS`, a reference two symbols of `ENTROPY_SYMBOL_BITS` plus
+///    the extra bits of its length and distance, so far references need longer matches,
+///  - `Coded`: the same for a known entropy coder, with the `SymbolCosts` of its codes,
+///  - `Ascii`: tokens written in their ascii form, a literal costs a byte, a reference
+///    the decimal digits of its distance and length with separators.
+///
+/// Repeat tokens are costed the same way, with the slot in place of the distance.
+/// Models are copied only with their compressor, so `Coded` is kept inline.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[allow(clippy::large_enum_variant)]
+pub enum CostModel {
+    #[default]
+    Entropy,
+    Coded(SymbolCosts),
+    Ascii,
+}
+
+impl CostModel {
+    /// Same model with literals priced by their statistics in `bytes`,
+    /// see `SymbolCosts::fitted_to`.
+    pub fn fitted_to(&self, bytes: &[u8]) -> Self {
+        match self {
+            CostModel::Coded(costs) => CostModel::Coded(costs.fitted_to(bytes)),
+            _ => *self,
+        }
+    }
+
+    pub fn reference_bits(&self, dist: usize, len: usize) -> usize {
+        self.cost(&ResultEncoding::Reference(dist, len))
+            .div_ceil(BIT_FRACTIONS)
+    }
+
+    pub fn repeat_bits(&self, slot: usize, len: usize) -> usize {
+        self.cost(&ResultEncoding::Repeat(slot, len))
+            .div_ceil(BIT_FRACTIONS)
+    }
+
+    /// Whether a reference is cheaper than `len` literals of average cost. Matches shorter
+    /// than `ResultEncoding::len_treshold()` cannot be written as references at all.
+    pub fn accepts(&self, dist: usize, len: usize) -> bool {
+        len >= ResultEncoding::len_treshold()
+            && self.cost(&ResultEncoding::Reference(dist, len)) < len * self.mean_literal_cost()
+    }
+
+    /// Same as `accepts` for a repeat token.
+    pub fn accepts_repeat(&self, slot: usize, len: usize) -> bool {
+        len >= ResultEncoding::len_treshold()
+            && self.cost(&ResultEncoding::Repeat(slot, len)) < len * self.mean_literal_cost()
+    }
+
+    /// Whether the reference or repeat `token` is cheaper than the `literals` it replaces.
+    pub fn replaces(&self, token: &ResultEncoding, literals: &[u8]) -> bool {
+        token.expanded_len() >= ResultEncoding::len_treshold()
+            && self.cost(token)
+                < literals
+                    .iter()
+                    .map(|byte| self.literal_cost(*byte))
+                    .sum::<usize>()
+    }
+
+    fn literal_cost(&self, byte: u8) -> usize {
+        match self {
+            CostModel::Entropy => ENTROPY_LITERAL_BITS * BIT_FRACTIONS,
+            CostModel::Coded(costs) => costs.literals[byte as usize] as usize,
+            CostModel::Ascii => ASCII_LITERAL_BITS * BIT_FRACTIONS,
+        }
+    }
+
+    fn mean_literal_cost(&self) -> usize {
+        match self {
+            CostModel::Coded(costs) => costs.mean_literal as usize,
+            _ => self.literal_cost(0),
+        }
+    }
+
+    /// Cost of the token in `1 / BIT_FRACTIONS` of a bit.
+    fn cost(&self, token: &ResultEncoding) -> usize {
+        let digits_num = |n: usize| n.checked_ilog10().unwrap_or(0) as usize + 1;
+        let extra_bits_num = || {
+            let (len_code, dist_code) = token.to_symbol_codes();
+            let dist_extra_bits_num = dist_code.map_or(0, |code| code.extra_bits_num);
+            (len_code.extra_bits_num + dist_extra_bits_num) as usize * BIT_FRACTIONS
+        };
+        match (self, token) {
+            (_, ResultEncoding::Ascii(byte)) => self.literal_cost(*byte),
+            (CostModel::Entropy, _) => 2 * ENTROPY_SYMBOL_BITS * BIT_FRACTIONS + extra_bits_num(),
+            (CostModel::Coded(costs), _) => {
+                (costs.length + costs.distance) as usize + extra_bits_num()
+            }
+            (CostModel::Ascii, ResultEncoding::Reference(dist, len)) => {
+                (ASCII_SEPARATORS_NUM + digits_num(*dist) + digits_num(*len))
+                    * ASCII_LITERAL_BITS
+                    * BIT_FRACTIONS
+            }
+            (CostModel::Ascii, ResultEncoding::Repeat(slot, len)) => {
+                (ASCII_SEPARATORS_NUM + 1 + digits_num(*slot) + digits_num(*len))
+                    * ASCII_LITERAL_BITS
+                    * BIT_FRACTIONS
+            }
+        }
+    }
+}
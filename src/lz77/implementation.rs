@@ -1,19 +1,31 @@
 use itertools::Itertools;
 
+use super::cost::CostModel;
 use super::hashes::{Hash, HashTable};
+use super::long_range::{LongRangeMatcher, Segment};
+use super::repeat::RepeatDistances;
+use crate::error::DecodeError;
+use crate::limits::{DecompressLimits, OutputBudget};
+use crate::trace::scope;
 use std::{
     cmp::min,
     collections::{HashMap, VecDeque},
 };
 
 const DEFAULT_WINDOW_SIZE: usize = 32768;
-const DEFAULT_LEN_TRESHOLD: usize = 6;
+const DEFAULT_LEN_TRESHOLD: usize = 3;
 const DEFAULT_ASCII_NUM_OF_SEPARATOR: u8 = 126;
+/// Prefix of the slot replacing the distance in the ascii form of `ResultEncoding::Repeat`.
+const ASCII_REPEAT_PREFIX: char = 'r';
 
+/// Token of LZ77: a literal, a reference to `len` bytes `dist` bytes back given as
+/// `Reference(dist, len)`, or `Repeat(slot, len)` reusing the distance in `slot` of
+/// `RepeatDistances`.
 #[derive(Clone, Debug)]
 pub enum ResultEncoding {
     Ascii(u8),
     Reference(usize, usize),
+    Repeat(usize, usize),
 }
 
 impl ResultEncoding {
@@ -21,28 +33,34 @@ impl ResultEncoding {
         DEFAULT_ASCII_NUM_OF_SEPARATOR
     }
 
+    /// Length of the shortest reference, the first value of length symbols. Whether a match
+    /// this long is worth a reference is up to `CostModel`.
     pub fn len_treshold() -> usize {
         DEFAULT_LEN_TRESHOLD
     }
 
+    pub fn expanded_len(&self) -> usize {
+        match self {
+            ResultEncoding::Ascii(_) => 1,
+            ResultEncoding::Reference(_, l) | ResultEncoding::Repeat(_, l) => *l,
+        }
+    }
+
     pub fn to_ascii_bytes(&self) -> Vec<u8> {
-        let s = match self {
-            ResultEncoding::Ascii(c) => vec![*c],
-            ResultEncoding::Reference(d, l) => {
-                let sep = vec![ResultEncoding::get_separator()];
-                [
-                    sep.clone(),
-                    d.to_string().as_bytes().to_vec(),
-                    sep.clone(),
-                    l.to_string().as_bytes().to_vec(),
-                    sep,
-                ]
-                .concat()
-                .to_vec()
-            }
+        let (dist, len) = match self {
+            ResultEncoding::Ascii(c) => return vec![*c],
+            ResultEncoding::Reference(d, l) => (d.to_string(), l),
+            ResultEncoding::Repeat(slot, l) => (format!("{}{}", ASCII_REPEAT_PREFIX, slot), l),
         };
-
-        s
+        let sep = vec![ResultEncoding::get_separator()];
+        [
+            sep.clone(),
+            dist.as_bytes().to_vec(),
+            sep.clone(),
+            len.to_string().as_bytes().to_vec(),
+            sep,
+        ]
+        .concat()
     }
 }
 
@@ -70,13 +88,38 @@ impl ResultEncodingVec {
         self.vec.reverse();
     }
 
+    pub fn len(&self) -> usize {
+        self.vec.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vec.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, ResultEncoding> {
+        self.vec.iter()
+    }
+
+    pub fn as_slice(&self) -> &[ResultEncoding] {
+        &self.vec
+    }
+
+    pub fn to_ascii_bytes(&self) -> Vec<u8> {
+        self.vec.iter().map(|x| x.to_ascii_bytes()).concat()
+    }
+
     pub fn from_ascii_bytes(ascii_bytes: &[u8]) -> Self {
+        ResultEncodingVec::try_from_ascii_bytes(ascii_bytes)
+            .expect("Ascii bytes have to be written by `to_ascii_bytes`.")
+    }
+
+    pub fn try_from_ascii_bytes(ascii_bytes: &[u8]) -> Result<Self, DecodeError> {
         let (mut i, mut res, sep) = (0, ResultEncodingVec::new(), ResultEncoding::get_separator());
         while i < ascii_bytes.len() {
             let c = ascii_bytes[i];
             if c == sep {
                 let (enc_reference, processed_size) =
-                    ResultEncodingVec::parse_reference(ascii_bytes, i);
+                    ResultEncodingVec::parse_reference(ascii_bytes, i)?;
                 res.push(enc_reference);
                 i += processed_size;
             } else {
@@ -85,39 +128,90 @@ impl ResultEncodingVec {
             }
         }
 
-        res
+        Ok(res)
     }
 
     pub fn expand(&self) -> Vec<u8> {
-        let mut s = Vec::new();
-        self.vec
-            .clone()
-            .into_iter()
-            .map(|e| match e {
-                ResultEncoding::Ascii(c) => {
-                    s.push(c);
-                    vec![c]
-                }
+        self.try_expand()
+            .expect("References have to point inside of already expanded data.")
+    }
+
+    /// Same as `expand`, but fails on references pointing before the start of data
+    /// and on data exceeding default `DecompressLimits`.
+    pub fn try_expand(&self) -> Result<Vec<u8>, DecodeError> {
+        self.try_expand_with(&DecompressLimits::default())
+    }
+
+    /// Same as `try_expand`, but fails as soon as data or a single reference exceeds `limits`,
+    /// so corrupted references cannot make it grow without bound.
+    pub fn try_expand_with(&self, limits: &DecompressLimits) -> Result<Vec<u8>, DecodeError> {
+        self.try_expand_after(&[], limits)
+    }
+
+    /// Same as `try_expand_with`, where references may also point into `dictionary`,
+    /// as if it preceded the data. Only the data is returned.
+    pub fn try_expand_after(
+        &self,
+        dictionary: &[u8],
+        limits: &DecompressLimits,
+    ) -> Result<Vec<u8>, DecodeError> {
+        let mut budget = OutputBudget::new(limits);
+        let capacity = (self.vec.len() as u64).min(limits.max_output_len());
+        let mut s = Vec::with_capacity(capacity as usize);
+        let mut repeats = RepeatDistances::new();
+        for e in self.vec.iter() {
+            budget.take(e)?;
+            match *e {
+                ResultEncoding::Ascii(c) => s.push(c),
                 ResultEncoding::Reference(d, l) => {
-                    let i = s.len() - d;
-                    (i..i + l)
-                        .map(|j| {
-                            s.push(s[j]);
-                            s[j]
-                        })
-                        .collect()
+                    repeats.use_distance(d);
+                    ResultEncodingVec::copy_reference(&mut s, dictionary, d, l)?;
                 }
-            })
-            .concat()
+                ResultEncoding::Repeat(slot, l) => {
+                    let d = repeats.repeat(slot)?;
+                    ResultEncodingVec::copy_reference(&mut s, dictionary, d, l)?;
+                }
+            }
+        }
+
+        Ok(s)
     }
 
-    fn parse_reference(ascii_bytes: &[u8], start_index: usize) -> (ResultEncoding, usize) {
+    fn copy_reference(
+        s: &mut Vec<u8>,
+        dictionary: &[u8],
+        d: usize,
+        l: usize,
+    ) -> Result<(), DecodeError> {
+        if d == 0 || d > dictionary.len() + s.len() {
+            return Err(DecodeError::new("reference points outside of the data"));
+        }
+        match d.checked_sub(s.len()) {
+            None => {
+                let i = s.len() - d;
+                (i..i + l).for_each(|j| s.push(s[j]));
+            }
+            Some(back) => {
+                let i = dictionary.len() - back;
+                let in_dictionary = l.min(back);
+                s.extend_from_slice(&dictionary[i..i + in_dictionary]);
+                (0..l - in_dictionary).for_each(|j| s.push(s[j]));
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_reference(
+        ascii_bytes: &[u8],
+        start_index: usize,
+    ) -> Result<(ResultEncoding, usize), DecodeError> {
         let sep = ResultEncoding::get_separator();
         let mut sep_counter = 0;
         let mut i = start_index;
         let (mut dist, mut len) = (String::new(), String::new());
+        let invalid_reference = || DecodeError::new("invalid reference in ascii form");
         loop {
-            let c = ascii_bytes[i];
+            let c = *ascii_bytes.get(i).ok_or_else(invalid_reference)?;
             if c == sep {
                 sep_counter += 1;
                 if sep_counter == 3 {
@@ -134,14 +228,39 @@ impl ResultEncodingVec {
             i += 1;
         }
 
-        let reference = ResultEncoding::Reference(dist.parse().unwrap(), len.parse().unwrap());
-        (reference, i - start_index + 1)
+        let len = len.parse().map_err(|_| invalid_reference())?;
+        let reference = match dist.strip_prefix(ASCII_REPEAT_PREFIX) {
+            Some(slot) => {
+                ResultEncoding::Repeat(slot.parse().map_err(|_| invalid_reference())?, len)
+            }
+            None => ResultEncoding::Reference(dist.parse().map_err(|_| invalid_reference())?, len),
+        };
+        Ok((reference, i - start_index + 1))
+    }
+}
+
+impl FromIterator<ResultEncoding> for ResultEncodingVec {
+    fn from_iter<I: IntoIterator<Item = ResultEncoding>>(iter: I) -> Self {
+        ResultEncodingVec {
+            vec: Vec::from_iter(iter),
+        }
+    }
+}
+
+impl IntoIterator for ResultEncodingVec {
+    type Item = ResultEncoding;
+    type IntoIter = std::vec::IntoIter<ResultEncoding>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.vec.into_iter()
     }
 }
 
 struct SlidingWindow<'a> {
     text: &'a Vec<u8>,
     max_len_to_reduce: usize,
+    min_match_len: usize,
+    cost_model: CostModel,
     window_size: usize,
     hashes: HashTable<'a>,
     subwords: Vec<HashMap<Hash, VecDeque<usize>>>,
@@ -149,7 +268,13 @@ struct SlidingWindow<'a> {
 }
 
 impl<'a> SlidingWindow<'a> {
-    pub fn new(text: &'a Vec<u8>, window_size: usize, max_len_to_reduce: Option<usize>) -> Self {
+    pub fn new(
+        text: &'a Vec<u8>,
+        window_size: usize,
+        max_len_to_reduce: Option<usize>,
+        min_match_len: usize,
+        cost_model: CostModel,
+    ) -> Self {
         let ws = min(window_size, text.len());
         let max_len = min(
             max_len_to_reduce.unwrap_or(SlidingWindow::sqrt_usize(window_size)),
@@ -157,6 +282,8 @@ impl<'a> SlidingWindow<'a> {
         );
         SlidingWindow {
             max_len_to_reduce: max_len,
+            min_match_len,
+            cost_model,
             window_size: ws,
             text,
             hashes: HashTable::new(text, None),
@@ -170,6 +297,7 @@ impl<'a> SlidingWindow<'a> {
     }
 
     pub fn run(&mut self) {
+        let scope = scope!(DEBUG, "sliding_window", input_bytes = self.text.len());
         for i in 0..self.text.len() {
             self.remove_old_subwords(i);
             let mut new_subwords_descending = self.extract_new_subwords(i);
@@ -178,6 +306,7 @@ impl<'a> SlidingWindow<'a> {
             let new_subwords_aescending = new_subwords_descending;
             self.add_new_subwords(new_subwords_aescending);
         }
+        scope.finish(self.partial_result.len());
     }
 
     fn remove_old_subwords(&mut self, curr_index: usize) {
@@ -235,13 +364,22 @@ impl<'a> SlidingWindow<'a> {
         }
     }
 
-    pub fn get_result(&self) -> Vec<u8> {
+    pub fn get_result(&self) -> ResultEncodingVec {
         let mut result = ResultEncodingVec::new();
+        if self.partial_result.is_empty() {
+            return result;
+        }
+
         let mut i = self.partial_result.len() - 1;
         loop {
             let (dist, len) = self.partial_result[i];
-            if len >= ResultEncoding::len_treshold() {
-                result.push(ResultEncoding::Reference(dist, len));
+            let reference = ResultEncoding::Reference(dist, len);
+            if len >= self.min_match_len
+                && self
+                    .cost_model
+                    .replaces(&reference, &self.text[i + 1 - len..=i])
+            {
+                result.push(reference);
                 if len > i {
                     break;
                 }
@@ -256,13 +394,17 @@ impl<'a> SlidingWindow<'a> {
         }
 
         result.reverse();
-        result.vec.into_iter().map(|x| x.to_ascii_bytes()).concat()
+        result
     }
 }
 
+#[derive(Clone, Copy)]
 pub struct LZ77Compressor {
     window_size: usize,
     max_len_to_reduce: Option<usize>,
+    min_match_len: usize,
+    cost_model: CostModel,
+    long_range: Option<LongRangeMatcher>,
 }
 
 impl LZ77Compressor {
@@ -270,17 +412,201 @@ impl LZ77Compressor {
         LZ77Compressor {
             window_size: window_size.unwrap_or(DEFAULT_WINDOW_SIZE),
             max_len_to_reduce,
+            min_match_len: ResultEncoding::len_treshold(),
+            cost_model: CostModel::default(),
+            long_range: None,
         }
     }
 
-    pub fn compress(&self, ascii_bytes: &Vec<u8>) -> Vec<u8> {
-        let mut sw = SlidingWindow::new(ascii_bytes, self.window_size, self.max_len_to_reduce);
+    /// Enables matches beyond the window found by `matcher` in the whole input, so tokens
+    /// may reference anything before them.
+    pub fn with_long_range(mut self, matcher: LongRangeMatcher) -> Self {
+        self.long_range = Some(matcher);
+        self
+    }
+
+    pub fn long_range(&self) -> Option<&LongRangeMatcher> {
+        self.long_range.as_ref()
+    }
+
+    /// Model deciding which matches are cheaper as references, `CostModel::Entropy` unless set.
+    pub fn with_cost_model(mut self, cost_model: CostModel) -> Self {
+        self.cost_model = cost_model;
+        self
+    }
+
+    pub fn cost_model(&self) -> CostModel {
+        self.cost_model
+    }
+
+    /// Same compressor with the cost model fitted to literal statistics of `bytes`,
+    /// see `CostModel::fitted_to`.
+    pub fn fitted_to(&self, bytes: &[u8]) -> Self {
+        self.with_cost_model(self.cost_model.fitted_to(bytes))
+    }
+
+    /// Matches shorter than `min_match_len` are written as literals instead. Values below
+    /// `ResultEncoding::len_treshold()`, the shortest match worth a reference, are raised to it.
+    pub fn with_min_match_len(mut self, min_match_len: usize) -> Self {
+        self.min_match_len = min_match_len.max(ResultEncoding::len_treshold());
+        self
+    }
+
+    pub fn min_match_len(&self) -> usize {
+        self.min_match_len
+    }
+
+    pub fn default_window_size() -> usize {
+        DEFAULT_WINDOW_SIZE
+    }
+
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    /// Tokens in their ascii form, so matches are weighed by `CostModel::Ascii` whatever
+    /// the cost model set.
+    pub fn compress(&self, ascii_bytes: &[u8]) -> Vec<u8> {
+        let compressor = LZ77Compressor {
+            cost_model: CostModel::Ascii,
+            ..*self
+        };
+        compressor.compress_to_tokens(ascii_bytes).to_ascii_bytes()
+    }
+
+    pub fn compress_to_tokens(&self, ascii_bytes: &[u8]) -> ResultEncodingVec {
+        let compressor = self.fitted_to(ascii_bytes);
+        let tokens = compressor
+            .segments(ascii_bytes, ascii_bytes.len())
+            .into_iter()
+            .flat_map(|segment| match segment {
+                Segment::Chunk(range) => compressor.find_matches(&ascii_bytes[range].to_vec()),
+                Segment::Match(token) => ResultEncodingVec::from_iter([token]),
+            })
+            .collect();
+        compressor.use_repeat_distances(ascii_bytes, tokens)
+    }
+
+    /// Chunks of at most `chunk_len` bytes for the sliding window, separated by references
+    /// of long range matches if enabled.
+    pub fn segments(&self, bytes: &[u8], chunk_len: usize) -> Vec<Segment> {
+        match &self.long_range {
+            Some(matcher) => matcher.segments(bytes, chunk_len),
+            None => Segment::chunks(0..bytes.len(), chunk_len).collect(),
+        }
+    }
+
+    /// Literals and references to the longest matches found by the sliding window,
+    /// without repeat tokens.
+    pub fn find_matches(&self, ascii_bytes: &Vec<u8>) -> ResultEncodingVec {
+        let mut sw = SlidingWindow::new(
+            ascii_bytes,
+            self.window_size,
+            self.max_len_to_reduce,
+            self.min_match_len,
+            self.cost_model,
+        );
         sw.run();
         sw.get_result()
     }
 
-    pub fn decompress(&self, ascii_bytes: &[u8]) -> Vec<u8> {
-        let encoded_result = ResultEncodingVec::from_ascii_bytes(ascii_bytes);
-        encoded_result.expand()
+    /// Rewrites `tokens` of `bytes` found by `find_matches`, possibly concatenated from
+    /// chunks of `bytes`, to use `RepeatDistances`:
+    ///  - a reference becomes a repeat token if the same bytes occur at a recent distance
+    ///    and the repeat token costs no more,
+    ///  - literals become a repeat token if they match bytes at a recent distance
+    ///    and the cost model accepts it, which the sliding window misses whenever
+    ///    a nearer occurrence of their prefix exists,
+    ///  - a repeat token already there is resolved to its distance and rewritten as
+    ///    a reference, or left as it is if its slot is outside of recent distances.
+    pub fn use_repeat_distances(
+        &self,
+        bytes: &[u8],
+        tokens: ResultEncodingVec,
+    ) -> ResultEncodingVec {
+        let tokens = tokens.vec;
+        let mut repeats = RepeatDistances::new();
+        let mut result = ResultEncodingVec::new();
+        let (mut i, mut pos, mut literals_end) = (0, 0, 0);
+        while i < tokens.len() {
+            let token = match tokens[i] {
+                ResultEncoding::Repeat(slot, len) => match repeats.get(slot) {
+                    Some(dist) => ResultEncoding::Reference(dist, len),
+                    None => ResultEncoding::Repeat(slot, len),
+                },
+                ref token => token.clone(),
+            };
+            let token = match token {
+                ResultEncoding::Reference(dist, len) => {
+                    i += 1;
+                    let slot = repeats.slot(dist).or_else(|| {
+                        (0..repeats.distances().len()).find(|slot| {
+                            repeat_len(bytes, pos, repeats.distances()[*slot], len) == len
+                        })
+                    });
+                    match slot.filter(|slot| {
+                        self.cost_model.repeat_bits(*slot, len)
+                            <= self.cost_model.reference_bits(dist, len)
+                    }) {
+                        Some(slot) => ResultEncoding::Repeat(slot, len),
+                        None => ResultEncoding::Reference(dist, len),
+                    }
+                }
+                ResultEncoding::Ascii(c) => {
+                    if literals_end <= i {
+                        literals_end = i + tokens[i..]
+                            .iter()
+                            .take_while(|token| matches!(token, ResultEncoding::Ascii(_)))
+                            .count();
+                    }
+                    let max_len = (literals_end - i).min(self.window_size);
+                    let (slot, len) = repeats
+                        .distances()
+                        .iter()
+                        .map(|dist| repeat_len(bytes, pos, *dist, max_len))
+                        .enumerate()
+                        .rev()
+                        .max_by_key(|(_, len)| *len)
+                        .expect("There is always a recent distance.");
+                    let repeat = ResultEncoding::Repeat(slot, len);
+                    match self.cost_model.replaces(&repeat, &bytes[pos..pos + len]) {
+                        true => {
+                            i += len;
+                            repeat
+                        }
+                        false => {
+                            i += 1;
+                            ResultEncoding::Ascii(c)
+                        }
+                    }
+                }
+                invalid @ ResultEncoding::Repeat(..) => {
+                    i += 1;
+                    pos += invalid.expanded_len();
+                    result.push(invalid);
+                    continue;
+                }
+            };
+            pos += token.expanded_len();
+            repeats.push(&token);
+            result.push(token);
+        }
+
+        result
+    }
+
+    pub fn decompress(&self, ascii_bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        ResultEncodingVec::try_from_ascii_bytes(ascii_bytes)?.try_expand()
+    }
+}
+
+/// Length of the match of at most `max_len` bytes starting at `pos` with bytes `dist` back.
+fn repeat_len(bytes: &[u8], pos: usize, dist: usize, max_len: usize) -> usize {
+    if dist == 0 || dist > pos {
+        return 0;
     }
+    (pos..bytes.len())
+        .take(max_len)
+        .take_while(|i| bytes[*i] == bytes[*i - dist])
+        .count()
 }
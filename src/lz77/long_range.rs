@@ -0,0 +1,265 @@
+const DEFAULT_MIN_MATCH_LEN: usize = 64;
+const DEFAULT_ANCHOR_INTERVAL: usize = 256;
+/// Base of the polynomial rolling hash.
+const HASH_BASE: u64 = 0x100_0000_01B3;
+/// Mixes bits of the rolling hash, so anchors do not depend on its lowest bits only.
+const HASH_MIX: u64 = 0x9E37_79B9_7F4A_7C15;
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+use super::implementation::ResultEncoding;
+use crate::config::MAX_WINDOW_SIZE;
+
+/// Farthest distance of a long range match, the largest window a container header records.
+pub const MAX_LONG_DISTANCE: usize = u32::MAX as usize;
+
+/// Match of `len` bytes at `pos`, repeating bytes `dist` back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LongMatch {
+    pub pos: usize,
+    pub dist: usize,
+    pub len: usize,
+}
+
+/// Part of the input, either searched for matches by the sliding window or replaced
+/// by a reference of a long range match.
+#[derive(Clone, Debug)]
+pub enum Segment {
+    Chunk(Range<usize>),
+    Match(ResultEncoding),
+}
+
+impl Segment {
+    /// Chunks of at most `chunk_len` bytes of input of `len` bytes, separated by references
+    /// of `matches`.
+    pub fn around(matches: Vec<LongMatch>, len: usize, chunk_len: usize) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        let mut start = 0;
+        for found in matches {
+            segments.extend(Segment::chunks(start..found.pos, chunk_len));
+            segments.push(Segment::Match(ResultEncoding::Reference(
+                found.dist, found.len,
+            )));
+            start = found.pos + found.len;
+        }
+        segments.extend(Segment::chunks(start..len, chunk_len));
+        segments
+    }
+
+    /// `range` split into chunks of at most `chunk_len` bytes.
+    pub fn chunks(range: Range<usize>, chunk_len: usize) -> impl Iterator<Item = Segment> {
+        let end = range.end;
+        range
+            .step_by(chunk_len.max(1))
+            .map(move |start| Segment::Chunk(start..end.min(start + chunk_len.max(1))))
+    }
+}
+
+/// Finds matches of at least `min_match_len` bytes anywhere in the input, however far
+/// beyond the sliding window. Windows of `min_match_len` bytes are hashed with a rolling
+/// hash, and those whose hash picks them as anchors, on average one in `anchor_interval`,
+/// are indexed. Anchors depend on content only, so a repeated window is an anchor in both
+/// of its occurrences, and the match found at the second one is extended both ways.
+/// The index takes one entry per anchor, about `len / anchor_interval` of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LongRangeMatcher {
+    min_match_len: usize,
+    anchor_interval: usize,
+}
+
+impl Default for LongRangeMatcher {
+    fn default() -> Self {
+        LongRangeMatcher {
+            min_match_len: DEFAULT_MIN_MATCH_LEN,
+            anchor_interval: DEFAULT_ANCHOR_INTERVAL,
+        }
+    }
+}
+
+impl LongRangeMatcher {
+    /// Both values are raised to at least 1, `min_match_len` is bounded by the longest
+    /// reference and `anchor_interval` is rounded up to a power of 2.
+    pub fn new(min_match_len: usize, anchor_interval: usize) -> Self {
+        LongRangeMatcher {
+            min_match_len: min_match_len.clamp(1, MAX_WINDOW_SIZE),
+            anchor_interval: anchor_interval.max(1).next_power_of_two(),
+        }
+    }
+
+    pub fn min_match_len(&self) -> usize {
+        self.min_match_len
+    }
+
+    pub fn anchor_interval(&self) -> usize {
+        self.anchor_interval
+    }
+
+    /// Non overlapping matches in increasing order of positions. Lengths are bounded by
+    /// the longest reference accepted by default `DecompressLimits`.
+    pub fn find_matches(&self, bytes: &[u8]) -> Vec<LongMatch> {
+        self.find_matches_after(&[], &HashMap::new(), bytes)
+    }
+
+    /// Splits `bytes` into chunks of at most `chunk_len` bytes between long range matches,
+    /// and references of the matches.
+    pub fn segments(&self, bytes: &[u8], chunk_len: usize) -> Vec<Segment> {
+        Segment::around(self.find_matches(bytes), bytes.len(), chunk_len)
+    }
+
+    /// Positions of anchors by their hashes, the last one of equal hashes.
+    fn index(&self, bytes: &[u8]) -> HashMap<u64, usize> {
+        self.anchors(bytes).map(|(pos, hash)| (hash, pos)).collect()
+    }
+
+    /// Same as `find_matches`, where matches may also reference `dictionary` preceding `bytes`,
+    /// whose anchors are `dictionary_anchors`. Windows across the end of `dictionary`
+    /// are not indexed.
+    fn find_matches_after(
+        &self,
+        dictionary: &[u8],
+        dictionary_anchors: &HashMap<u64, usize>,
+        bytes: &[u8],
+    ) -> Vec<LongMatch> {
+        let text = Joined { dictionary, bytes };
+        let mut anchors: HashMap<u64, usize> = HashMap::new();
+        let mut matches = Vec::new();
+        let mut covered_end = dictionary.len();
+        for (pos, hash) in self.anchors(bytes) {
+            let pos = dictionary.len() + pos;
+            if pos >= covered_end {
+                let found = anchors
+                    .get(&hash)
+                    .or_else(|| dictionary_anchors.get(&hash))
+                    .and_then(|prev| self.extend(&text, *prev, pos, covered_end));
+                if let Some(found) = found {
+                    covered_end = found.pos + found.len;
+                    matches.push(LongMatch {
+                        pos: found.pos - dictionary.len(),
+                        ..found
+                    });
+                }
+            }
+            anchors.insert(hash, pos);
+        }
+
+        matches
+    }
+
+    /// Positions of windows of `min_match_len` bytes picked as anchors, with their hashes.
+    fn anchors<'a>(&'a self, bytes: &'a [u8]) -> impl Iterator<Item = (usize, u64)> + 'a {
+        let window_len = self.min_match_len;
+        let top_power = (1..window_len).fold(1u64, |power, _| power.wrapping_mul(HASH_BASE));
+        let first_hash = bytes.iter().take(window_len).fold(0u64, |hash, byte| {
+            hash.wrapping_mul(HASH_BASE).wrapping_add(*byte as u64)
+        });
+        (0..(bytes.len() + 1).saturating_sub(window_len))
+            .scan(first_hash, move |hash, pos| {
+                if pos > 0 {
+                    let (removed, added) =
+                        (bytes[pos - 1] as u64, bytes[pos + window_len - 1] as u64);
+                    *hash = hash
+                        .wrapping_sub(removed.wrapping_mul(top_power))
+                        .wrapping_mul(HASH_BASE)
+                        .wrapping_add(added);
+                }
+                Some((pos, *hash))
+            })
+            .filter(|(_, hash)| self.is_anchor(*hash))
+    }
+
+    fn is_anchor(&self, hash: u64) -> bool {
+        (hash.wrapping_mul(HASH_MIX) >> 32) as usize & (self.anchor_interval - 1) == 0
+    }
+
+    /// Match of the window at `pos` with the one at `prev`, extended forward and backward
+    /// to `covered_end`, if the windows are equal and not too far apart.
+    fn extend(
+        &self,
+        text: &Joined,
+        prev: usize,
+        pos: usize,
+        covered_end: usize,
+    ) -> Option<LongMatch> {
+        let dist = pos - prev;
+        let window_len = self.min_match_len;
+        let equal = |i: &usize| text.at(*i) == text.at(*i - dist);
+        if dist > MAX_LONG_DISTANCE || !(pos..pos + window_len).all(|i| equal(&i)) {
+            return None;
+        }
+
+        let forward = (pos + window_len..text.len())
+            .take(MAX_WINDOW_SIZE - window_len)
+            .take_while(equal)
+            .count();
+        let len = window_len + forward;
+        let backward = (covered_end.max(dist)..pos)
+            .rev()
+            .take(MAX_WINDOW_SIZE - len)
+            .take_while(equal)
+            .count();
+        Some(LongMatch {
+            pos: pos - backward,
+            dist,
+            len: len + backward,
+        })
+    }
+}
+
+/// Dictionary followed by the input, indexed as one sequence without copying either.
+struct Joined<'a> {
+    dictionary: &'a [u8],
+    bytes: &'a [u8],
+}
+
+impl<'a> Joined<'a> {
+    fn len(&self) -> usize {
+        self.dictionary.len() + self.bytes.len()
+    }
+
+    fn at(&self, i: usize) -> u8 {
+        match i.checked_sub(self.dictionary.len()) {
+            Some(i) => self.bytes[i],
+            None => self.dictionary[i],
+        }
+    }
+}
+
+/// Data preceding every input, e.g. an earlier version of it, which long range matches
+/// may reference. It is borrowed rather than copied, as it may be as big as the input,
+/// and its anchors are indexed once, when matches are first searched.
+pub struct Dictionary<'a> {
+    matcher: LongRangeMatcher,
+    bytes: &'a [u8],
+    anchors: OnceLock<HashMap<u64, usize>>,
+}
+
+impl<'a> Dictionary<'a> {
+    pub fn new(matcher: LongRangeMatcher, bytes: &'a [u8]) -> Self {
+        Dictionary {
+            matcher,
+            bytes,
+            anchors: OnceLock::new(),
+        }
+    }
+
+    pub fn matcher(&self) -> &LongRangeMatcher {
+        &self.matcher
+    }
+
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Same as `LongRangeMatcher::find_matches`, with distances reaching into the dictionary
+    /// as if it preceded `bytes`.
+    pub fn find_matches(&self, bytes: &[u8]) -> Vec<LongMatch> {
+        let anchors = self.anchors.get_or_init(|| self.matcher.index(self.bytes));
+        self.matcher.find_matches_after(self.bytes, anchors, bytes)
+    }
+
+    pub fn segments(&self, bytes: &[u8], chunk_len: usize) -> Vec<Segment> {
+        Segment::around(self.find_matches(bytes), bytes.len(), chunk_len)
+    }
+}
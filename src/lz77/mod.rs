@@ -1,4 +1,15 @@
+pub mod alphabet;
+pub mod cost;
 pub mod hashes;
 pub mod implementation;
+pub mod long_range;
+pub mod patch;
+pub mod repeat;
+pub mod rle;
 
+pub use cost::{CostModel, LiteralFitting, SymbolCosts};
 pub use implementation::LZ77Compressor;
+pub use long_range::{Dictionary, LongRangeMatcher, Segment};
+pub use patch::PatchCompressor;
+pub use repeat::RepeatDistances;
+pub use rle::RleCompressor;
@@ -0,0 +1,69 @@
+/// Shortest match with the reference, shorter than the long range default, since versions
+/// of a file share also short runs of bytes between their edits.
+const PATCH_MIN_MATCH_LEN: usize = 32;
+/// Anchors are denser than by default, so matches between nearby edits are found too.
+const PATCH_ANCHOR_INTERVAL: usize = 32;
+
+use super::implementation::ResultEncodingVec;
+use super::long_range::{Dictionary, LongRangeMatcher, Segment};
+use super::LZ77Compressor;
+use crate::error::DecodeError;
+use crate::limits::DecompressLimits;
+
+/// Compresses inputs against a reference, e.g. an earlier version of a file, into patches,
+/// in the spirit of `zstd --patch-from` and xdelta. References may point into the reference
+/// as if it preceded the input: matches with it are found by a `LongRangeMatcher` over
+/// the whole reference, the rest of the input goes through the sliding window of `compressor`.
+/// The same reference is needed to apply the patch.
+pub struct PatchCompressor<'a> {
+    compressor: LZ77Compressor,
+    dictionary: Dictionary<'a>,
+}
+
+impl<'a> PatchCompressor<'a> {
+    pub fn new(compressor: LZ77Compressor, reference: &'a [u8]) -> Self {
+        PatchCompressor {
+            compressor,
+            dictionary: Dictionary::new(PatchCompressor::default_matcher(), reference),
+        }
+    }
+
+    pub fn default_matcher() -> LongRangeMatcher {
+        LongRangeMatcher::new(PATCH_MIN_MATCH_LEN, PATCH_ANCHOR_INTERVAL)
+    }
+
+    pub fn compressor(&self) -> &LZ77Compressor {
+        &self.compressor
+    }
+
+    pub fn reference(&self) -> &'a [u8] {
+        self.dictionary.bytes()
+    }
+
+    /// Chunks of at most `chunk_len` bytes for the sliding window, separated by references
+    /// of matches with the reference or earlier input.
+    pub fn segments(&self, bytes: &[u8], chunk_len: usize) -> Vec<Segment> {
+        self.dictionary.segments(bytes, chunk_len)
+    }
+
+    pub fn compress_to_tokens(&self, bytes: &[u8]) -> ResultEncodingVec {
+        let compressor = self.compressor.fitted_to(bytes);
+        let tokens = self
+            .segments(bytes, bytes.len())
+            .into_iter()
+            .flat_map(|segment| match segment {
+                Segment::Chunk(range) => compressor.find_matches(&bytes[range].to_vec()),
+                Segment::Match(token) => ResultEncodingVec::from_iter([token]),
+            })
+            .collect();
+        compressor.use_repeat_distances(bytes, tokens)
+    }
+
+    pub fn decompress_tokens(
+        &self,
+        tokens: &ResultEncodingVec,
+        limits: &DecompressLimits,
+    ) -> Result<Vec<u8>, DecodeError> {
+        tokens.try_expand_after(self.reference(), limits)
+    }
+}
@@ -0,0 +1,90 @@
+const SLOTS_NUM: usize = REPEAT_SLOTS_NUM as usize;
+/// Distances before any reference, typical widths of records in binary data.
+const INITIAL_DISTANCES: [usize; SLOTS_NUM] = [1, 4, 8, 16];
+
+use super::alphabet::REPEAT_SLOTS_NUM;
+use super::implementation::{ResultEncoding, ResultEncodingVec};
+use crate::error::DecodeError;
+
+/// Recently used distances of references, the most recent first, tracked the same way
+/// while encoding and decoding tokens. `ResultEncoding::Repeat(slot, len)` reuses the distance
+/// in `slot`, so structured data repeating the same distances does not pay for them every time.
+/// Every distance used moves to the front, the oldest one is dropped to make room.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RepeatDistances {
+    distances: [usize; SLOTS_NUM],
+}
+
+impl Default for RepeatDistances {
+    fn default() -> Self {
+        RepeatDistances {
+            distances: INITIAL_DISTANCES,
+        }
+    }
+}
+
+impl RepeatDistances {
+    pub fn new() -> Self {
+        RepeatDistances::default()
+    }
+
+    pub fn get(&self, slot: usize) -> Option<usize> {
+        self.distances.get(slot).copied()
+    }
+
+    pub fn slot(&self, dist: usize) -> Option<usize> {
+        self.distances.iter().position(|d| *d == dist)
+    }
+
+    pub fn distances(&self) -> &[usize] {
+        &self.distances
+    }
+
+    /// Makes `dist` the most recent distance.
+    pub fn use_distance(&mut self, dist: usize) {
+        let last = self.slot(dist).unwrap_or(SLOTS_NUM - 1);
+        self.distances[..=last].rotate_right(1);
+        self.distances[0] = dist;
+    }
+
+    /// Distance in `slot`, which becomes the most recent one.
+    pub fn repeat(&mut self, slot: usize) -> Result<usize, DecodeError> {
+        let dist = self
+            .get(slot)
+            .ok_or_else(|| DecodeError::new("repeat of a distance outside of recent ones"))?;
+        self.use_distance(dist);
+        Ok(dist)
+    }
+
+    /// Updates distances with those of a token written by the encoder, which repeats
+    /// only slots it holds.
+    pub fn push(&mut self, token: &ResultEncoding) {
+        match *token {
+            ResultEncoding::Ascii(_) => {}
+            ResultEncoding::Reference(dist, _) => self.use_distance(dist),
+            ResultEncoding::Repeat(slot, _) => {
+                self.repeat(slot)
+                    .expect("Encoder repeats only slots it holds.");
+            }
+        }
+    }
+
+    /// Replaces references to recent distances by repeat tokens.
+    pub fn encode(tokens: ResultEncodingVec) -> ResultEncodingVec {
+        let mut repeats = RepeatDistances::new();
+        tokens
+            .into_iter()
+            .map(|token| {
+                let token = match token {
+                    ResultEncoding::Reference(dist, len) => match repeats.slot(dist) {
+                        Some(slot) => ResultEncoding::Repeat(slot, len),
+                        None => token,
+                    },
+                    _ => token,
+                };
+                repeats.push(&token);
+                token
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,37 @@
+const MAX_RUN_LEN: usize = 258;
+
+use super::implementation::{ResultEncoding, ResultEncodingVec};
+use super::repeat::RepeatDistances;
+
+/// Finds runs of a repeated byte only, each written as the byte followed by a reference
+/// of distance 1, which is a repeat token while 1 is a recent distance. Much faster
+/// than `LZ77Compressor`, and as good on images or sparse data.
+#[derive(Clone, Copy, Default)]
+pub struct RleCompressor;
+
+impl RleCompressor {
+    pub fn compress_to_tokens(bytes: &[u8]) -> ResultEncodingVec {
+        RleCompressor::compress_runs_of(bytes, |_| true)
+    }
+
+    /// Same as `compress_to_tokens`, finding runs only of bytes for which `repeats` holds.
+    pub fn compress_runs_of(bytes: &[u8], repeats: impl Fn(u8) -> bool) -> ResultEncodingVec {
+        let mut tokens = ResultEncodingVec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            tokens.push(ResultEncoding::Ascii(bytes[i]));
+            let run_len = bytes[i + 1..]
+                .iter()
+                .take(MAX_RUN_LEN)
+                .take_while(|byte| **byte == bytes[i])
+                .count();
+            if run_len >= ResultEncoding::len_treshold() && repeats(bytes[i]) {
+                tokens.push(ResultEncoding::Reference(1, run_len));
+                i += run_len;
+            }
+            i += 1;
+        }
+
+        RepeatDistances::encode(tokens)
+    }
+}
@@ -1,10 +1,17 @@
-use compression::utils::TypeOr;
+use bit_vec::BitVec;
+use compression::bwt::Bzip2;
+use compression::ArchiveEntry;
+use compression::ArchiveJob;
+use compression::Bzip2Job;
 use compression::CompressionParams;
 use compression::DeflateCompression;
 use compression::HelpDisplayer;
+use compression::PatchJob;
 use std::env;
+use std::fs;
 use std::io;
 use std::io::prelude::*;
+use std::path::{Component, Path};
 
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
 pub type Result<T> = std::result::Result<T, Error>;
@@ -15,16 +22,44 @@ fn main() {
     if args.len() > 1 {
         for i in (1..args.len() - 1).step_by(2) {
             let alias = &args[i];
-            let value = &args[i + 1]
-                .parse()
-                .unwrap_or_else(|_| panic!("{}", HelpDisplayer::new(&compression_params)));
-            compression_params.update(alias, *value);
+            compression_params
+                .update_from_str(alias, &args[i + 1])
+                .unwrap_or_else(|err| {
+                    panic!("{}\n{}", err, HelpDisplayer::new(&compression_params))
+                });
         }
     } else {
         println!("{}", HelpDisplayer::new(&compression_params));
     }
 
-    let mut deflate_compressor = DeflateCompression::new(&compression_params);
+    let config = compression_params
+        .config()
+        .unwrap_or_else(|err| panic!("{}\n{}", err, HelpDisplayer::new(&compression_params)));
+    let mut deflate_compressor = DeflateCompression::new(&config);
+
+    let patch_job = compression_params
+        .patch_job()
+        .unwrap_or_else(|err| panic!("{}\n{}", err, HelpDisplayer::new(&compression_params)));
+    if let Some(job) = patch_job {
+        run_patch_job(&deflate_compressor, &job).unwrap_or_else(|err| panic!("{}", err));
+        return;
+    }
+
+    let archive_job = compression_params
+        .archive_job()
+        .unwrap_or_else(|err| panic!("{}\n{}", err, HelpDisplayer::new(&compression_params)));
+    if let Some(job) = archive_job {
+        run_archive_job(&deflate_compressor, &job).unwrap_or_else(|err| panic!("{}", err));
+        return;
+    }
+
+    let bzip2_job = compression_params
+        .bzip2_job()
+        .unwrap_or_else(|err| panic!("{}\n{}", err, HelpDisplayer::new(&compression_params)));
+    if let Some(job) = bzip2_job {
+        run_bzip2_job(&deflate_compressor, &job).unwrap_or_else(|err| panic!("{}", err));
+        return;
+    }
 
     let sep = compression::lz77::implementation::ResultEncoding::get_separator();
     println!(
@@ -40,12 +75,20 @@ fn main() {
             break;
         }
 
-        let compreseed = deflate_compressor.deflate_compress(&to_compress);
-        let decompressed = deflate_compressor.deflate_decompress(&compreseed);
-        let compressed = match compreseed {
-            TypeOr::Left(bits) => bits.to_string(),
-            TypeOr::Right(bytes) => String::from_utf8(bytes).unwrap(),
+        let compreseed = match compression_params.print_stats() {
+            true => {
+                let (compressed, stats) =
+                    deflate_compressor.compress_with_stats(to_compress.as_bytes());
+                println!("{}", stats);
+                compressed
+            }
+            false => deflate_compressor.deflate_compress(&to_compress),
         };
+        let decompressed = deflate_compressor
+            .deflate_decompress(&compreseed)
+            .unwrap_or_else(|err| err.to_string());
+        let compressed = String::from_utf8(compreseed)
+            .unwrap_or_else(|err| BitVec::from_bytes(err.as_bytes()).to_string());
 
         println!("---------------------");
         println!("To compress: {}", to_compress);
@@ -54,3 +97,109 @@ fn main() {
         println!("\n");
     }
 }
+
+/// Creates a patch of the input against the reference, or applies the input patch to it.
+fn run_patch_job(compressor: &DeflateCompression, job: &PatchJob) -> Result<()> {
+    let reference = fs::read(&job.reference)?;
+    let input = fs::read(&job.input)?;
+    let output = match job.decompress {
+        true => compressor.apply_patch(&reference, &input)?,
+        false => compressor.compress_patch(&reference, &input),
+    };
+    fs::write(&job.output, &output)?;
+    println!(
+        "{} ({} bytes) written from {} ({} bytes) against {}",
+        job.output,
+        output.len(),
+        job.input,
+        input.len(),
+        job.reference
+    );
+    Ok(())
+}
+
+/// Compresses the input into a `.bz2` file, or decompresses the input `.bz2` file.
+fn run_bzip2_job(compressor: &DeflateCompression, job: &Bzip2Job) -> Result<()> {
+    let input = fs::read(&job.input)?;
+    let output = match job.decompress {
+        true => Bzip2::decompress(&input, compressor.limits())?,
+        false => Bzip2::compress(&input),
+    };
+    fs::write(&job.output, &output)?;
+    println!(
+        "{} ({} bytes) written from {} ({} bytes)",
+        job.output,
+        output.len(),
+        job.input,
+        input.len()
+    );
+    Ok(())
+}
+
+/// Archives files of a directory, extracts an archive into one or lists its entries.
+fn run_archive_job(compressor: &DeflateCompression, job: &ArchiveJob) -> Result<()> {
+    match job {
+        ArchiveJob::Create { directory, archive } => {
+            let mut entries = Vec::new();
+            collect_files(Path::new(directory), "", &mut entries)?;
+            let compressed = compressor.compress_archive(&entries)?;
+            fs::write(archive, &compressed)?;
+            let len: usize = entries.iter().map(|entry| entry.data.len()).sum();
+            println!(
+                "{} ({} bytes) written from {} files ({} bytes) of {}",
+                archive,
+                compressed.len(),
+                entries.len(),
+                len,
+                directory
+            );
+        }
+        ArchiveJob::Extract { archive, directory } => {
+            let entries = compressor.decompress_archive(&fs::read(archive)?)?;
+            for entry in &entries {
+                let relative = Path::new(&entry.name);
+                if !relative
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_)))
+                {
+                    return Err(format!("Entry {} escapes the output directory", entry.name).into());
+                }
+                let path = Path::new(directory).join(relative);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, &entry.data)?;
+            }
+            println!(
+                "{} files extracted from {} into {}",
+                entries.len(),
+                archive,
+                directory
+            );
+        }
+        ArchiveJob::List { archive } => {
+            let index = compressor.list_archive(&fs::read(archive)?)?;
+            for entry in &index.entries {
+                println!("{:>12} {}", entry.len, entry.name);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads files under `directory` recursively, named by their paths relative to it.
+fn collect_files(directory: &Path, prefix: &str, entries: &mut Vec<ArchiveEntry>) -> Result<()> {
+    let mut children: Vec<_> = fs::read_dir(directory)?.collect::<io::Result<_>>()?;
+    children.sort_by_key(|child| child.file_name());
+    for child in children {
+        let name = format!("{}{}", prefix, child.file_name().to_string_lossy());
+        match child.file_type()?.is_dir() {
+            true => collect_files(&child.path(), &format!("{}/", name), entries)?,
+            false => entries.push(ArchiveEntry {
+                name,
+                data: fs::read(child.path())?,
+            }),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use crate::error::{CancelledError, DecodeError};
+use crate::limits::DecompressLimits;
+use crate::lz77::implementation::{ResultEncoding, ResultEncodingVec};
+use crate::lz77::CostModel;
+use crate::progress::FrameControl;
+use crate::stats::CompressionStats;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DataKind {
+    Bytes,
+    Tokens,
+}
+
+/// Data passed between stages of a pipeline.
+pub enum StageData {
+    Bytes(Vec<u8>),
+    Tokens(ResultEncodingVec),
+}
+
+impl StageData {
+    pub fn kind(&self) -> DataKind {
+        match self {
+            StageData::Bytes(_) => DataKind::Bytes,
+            StageData::Tokens(_) => DataKind::Tokens,
+        }
+    }
+
+    /// Number of bytes or tokens.
+    pub fn len(&self) -> usize {
+        match self {
+            StageData::Bytes(bytes) => bytes.len(),
+            StageData::Tokens(tokens) => tokens.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Converts data before handing it to a stage consuming `kind`.
+    /// Bytes become literal tokens, tokens are written in their ascii form.
+    pub fn encode_as(self, kind: DataKind) -> StageData {
+        match (self, kind) {
+            (StageData::Bytes(bytes), DataKind::Tokens) => {
+                StageData::Tokens(bytes.into_iter().map(ResultEncoding::Ascii).collect())
+            }
+            (StageData::Tokens(tokens), DataKind::Bytes) => {
+                StageData::Bytes(tokens.to_ascii_bytes())
+            }
+            (data, _) => data,
+        }
+    }
+
+    /// Reverts `encode_as` which turned data of `kind` into the current one.
+    /// Expanded tokens cannot exceed `limits`.
+    pub fn decode_as(
+        self,
+        kind: DataKind,
+        limits: &DecompressLimits,
+    ) -> Result<StageData, DecodeError> {
+        let data = match (self, kind) {
+            (StageData::Tokens(tokens), DataKind::Bytes) => {
+                StageData::Bytes(tokens.try_expand_with(limits)?)
+            }
+            (StageData::Bytes(bytes), DataKind::Tokens) => {
+                StageData::Tokens(ResultEncodingVec::try_from_ascii_bytes(&bytes)?)
+            }
+            (data, _) => data,
+        };
+
+        Ok(data)
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        match self.encode_as(DataKind::Bytes) {
+            StageData::Bytes(bytes) => bytes,
+            StageData::Tokens(_) => unreachable!(),
+        }
+    }
+
+    pub fn into_tokens(self) -> ResultEncodingVec {
+        match self.encode_as(DataKind::Tokens) {
+            StageData::Tokens(tokens) => tokens,
+            StageData::Bytes(_) => unreachable!(),
+        }
+    }
+}
+
+/// Reversible transformation being a single stage of a pipeline.
+///
+/// `encode` gets data of `input_kind` and returns data of `output_kind`,
+/// `decode` goes the opposite way. The pipeline converts data between stages.
+/// Data returned by `decode` must not expand beyond `limits`.
+pub trait Codec: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn input_kind(&self) -> DataKind;
+
+    fn output_kind(&self) -> DataKind;
+
+    fn encode(&self, data: StageData) -> StageData;
+
+    /// Same as `encode`, additionally recording what the stage knows about its output.
+    fn encode_with_stats(&self, data: StageData, _stats: &mut CompressionStats) -> StageData {
+        self.encode(data)
+    }
+
+    /// Same as `encode`, but stops early once the cancellation token of `control` is cancelled.
+    /// Stages taking long check it while running and report progress in chunks of their
+    /// input, the rest only check it before starting.
+    fn encode_with_control(
+        &self,
+        data: StageData,
+        control: &FrameControl,
+    ) -> Result<StageData, CancelledError> {
+        control.check()?;
+        Ok(self.encode(data))
+    }
+
+    fn decode(&self, data: StageData, limits: &DecompressLimits) -> Result<StageData, DecodeError>;
+
+    /// Whether decoded data is exactly as long as the expanded tokens the stage gets,
+    /// so the limits of its output apply to its input as well.
+    fn preserves_expanded_len(&self) -> bool {
+        false
+    }
+
+    /// Window of the stage emitting back references.
+    fn window_size(&self) -> Option<usize> {
+        None
+    }
+
+    /// Cost of tokens coded by the stage, for a stage consuming tokens, by which
+    /// the LZ77 stage before it decides which matches to write as references.
+    fn cost_model(&self) -> Option<CostModel> {
+        None
+    }
+}
@@ -0,0 +1,287 @@
+const STAGES_SEPARATOR: char = ',';
+
+use std::{error::Error, fmt, time::Instant};
+
+use super::codec::{Codec, DataKind, StageData};
+use crate::ans::AnsCompressor;
+use crate::bwt::{BwtCompressor, MoveToFront, ZeroRunCompressor};
+use crate::config::DeflateConfig;
+use crate::error::{CancelledError, DecodeError};
+use crate::huffman::{AdaptiveHuffmanCompressor, HuffmanCompressor};
+use crate::limits::DecompressLimits;
+use crate::lz77::{CostModel, LZ77Compressor, LongRangeMatcher, PatchCompressor, RleCompressor};
+use crate::progress::FrameControl;
+use crate::range_coder::RangeCompressor;
+use crate::stats::CompressionStats;
+use crate::trace::scope;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnknownStageError(pub String);
+
+impl fmt::Display for UnknownStageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Unknown pipeline stage: '{}'", self.0)
+    }
+}
+
+impl Error for UnknownStageError {}
+
+/// Ordered list of stages. Encoding runs them first to last, decoding last to first.
+/// Stages may borrow data for `'a`, e.g. the reference of a patch.
+#[derive(Default)]
+pub struct Pipeline<'a> {
+    stages: Vec<Box<dyn Codec + 'a>>,
+}
+
+impl<'a> Pipeline<'a> {
+    pub fn new() -> Self {
+        Pipeline { stages: Vec::new() }
+    }
+
+    pub fn with_stage(mut self, stage: impl Codec + 'a) -> Self {
+        self.push(Box::new(stage));
+        self
+    }
+
+    pub fn push(&mut self, stage: Box<dyn Codec + 'a>) {
+        self.stages.push(stage);
+    }
+
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Builds pipeline from stage names separated by commas, e.g. `lz77,huffman`.
+    /// Stages take their settings from `config`, except that LZ77 weighs matches by
+    /// the cost model of the stage coding its tokens, or by the cost of their ascii form
+    /// if none follows, and `lz77_long` always finds long range matches.
+    pub fn from_spec(spec: &str, config: &DeflateConfig) -> Result<Self, UnknownStageError> {
+        Pipeline::from_spec_with_reference(spec, config, None)
+    }
+
+    /// Same as `from_spec`, where the `patch` stage compresses against `reference`,
+    /// see `PatchCompressor`. Without a reference the `patch` stage is unknown.
+    pub fn from_patch_spec(
+        spec: &str,
+        config: &DeflateConfig,
+        reference: &'a [u8],
+    ) -> Result<Self, UnknownStageError> {
+        Pipeline::from_spec_with_reference(spec, config, Some(reference))
+    }
+
+    /// Spec of the same stages compressing patches: the first stage finding matches
+    /// is replaced by `patch`, which is prepended if there is none.
+    pub fn patch_spec(spec: &str) -> String {
+        let mut names: Vec<&str> = spec.split(STAGES_SEPARATOR).map(str::trim).collect();
+        match names.first() {
+            Some(&("lz77" | "lz77_long" | "rle" | "patch")) => names[0] = "patch",
+            _ => names.insert(0, "patch"),
+        }
+        names.retain(|name| !name.is_empty());
+        names.join(&STAGES_SEPARATOR.to_string())
+    }
+
+    fn from_spec_with_reference(
+        spec: &str,
+        config: &DeflateConfig,
+        mut reference: Option<&'a [u8]>,
+    ) -> Result<Self, UnknownStageError> {
+        let names: Vec<&str> = spec
+            .split(STAGES_SEPARATOR)
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .collect();
+        let mut stages = names
+            .iter()
+            .map(|name| match *name {
+                "patch" | "lz77" | "lz77_long" => Ok(None),
+                _ => Pipeline::new_stage(name, config).map(Some),
+            })
+            .collect::<Result<Vec<Option<Box<dyn Codec + 'a>>>, UnknownStageError>>()?;
+        for i in 0..stages.len() {
+            let next_kind = stages.get(i + 1).map(|stage| {
+                stage
+                    .as_ref()
+                    .map_or(DataKind::Bytes, |stage| stage.input_kind())
+            });
+            let compressor = || {
+                let cost_model = match next_kind {
+                    Some(DataKind::Tokens) => stages[i + 1]
+                        .as_ref()
+                        .and_then(|stage| stage.cost_model())
+                        .unwrap_or_default(),
+                    _ => CostModel::Ascii,
+                };
+                Pipeline::lz77_stage(names[i], config).with_cost_model(cost_model)
+            };
+            match names[i] {
+                "patch" => {
+                    let reference = reference
+                        .take()
+                        .ok_or_else(|| UnknownStageError(names[i].to_string()))?;
+                    stages[i] = Some(Box::new(PatchCompressor::new(compressor(), reference)));
+                }
+                "lz77" | "lz77_long" => stages[i] = Some(Box::new(compressor())),
+                _ => {}
+            }
+        }
+
+        Ok(Pipeline {
+            stages: stages.into_iter().flatten().collect(),
+        })
+    }
+
+    fn new_stage(name: &str, config: &DeflateConfig) -> Result<Box<dyn Codec>, UnknownStageError> {
+        let stage: Box<dyn Codec> = match name {
+            "rle" => Box::new(RleCompressor),
+            "bwt" => Box::new(BwtCompressor),
+            "mtf" => Box::new(MoveToFront),
+            "zrle" => Box::new(ZeroRunCompressor),
+            "huffman" => Box::new(HuffmanCompressor::new(config.predefined_codes())),
+            "adaptive_huffman" => Box::new(AdaptiveHuffmanCompressor),
+            "range" => Box::new(RangeCompressor),
+            "rans" => Box::new(AnsCompressor),
+            _ => return Err(UnknownStageError(name.to_string())),
+        };
+
+        Ok(stage)
+    }
+
+    fn lz77_stage(name: &str, config: &DeflateConfig) -> LZ77Compressor {
+        let compressor = config.lz77_compressor();
+        match (name, compressor.long_range()) {
+            ("lz77_long", None) => compressor.with_long_range(LongRangeMatcher::default()),
+            _ => compressor,
+        }
+    }
+
+    /// Largest distance a reference may reach in the output of any stage, 0 if none emits references.
+    pub fn window_size(&self) -> usize {
+        self.stages
+            .iter()
+            .filter_map(|stage| stage.window_size())
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn spec(&self) -> String {
+        self.stages
+            .iter()
+            .map(|stage| stage.name())
+            .collect::<Vec<&str>>()
+            .join(&STAGES_SEPARATOR.to_string())
+    }
+
+    pub fn encode(&self, bytes: Vec<u8>) -> Vec<u8> {
+        let mut data = StageData::Bytes(bytes);
+        for stage in self.stages.iter() {
+            let scope = scope!(DEBUG, "encode_stage", stage = stage.name());
+            data = stage.encode(data.encode_as(stage.input_kind()));
+            scope.finish(data.len());
+        }
+
+        data.into_bytes()
+    }
+
+    /// Same as `encode`, but stops early once the cancellation token of `control` is cancelled.
+    /// Progress is reported by the first stage only, the one consuming the input.
+    pub fn encode_with_control(
+        &self,
+        bytes: Vec<u8>,
+        control: &FrameControl,
+    ) -> Result<Vec<u8>, CancelledError> {
+        let silent = FrameControl::new(control.cancellation_token());
+        let mut data = StageData::Bytes(bytes);
+        for (i, stage) in self.stages.iter().enumerate() {
+            let scope = scope!(DEBUG, "encode_stage", stage = stage.name());
+            let stage_control = match i {
+                0 => control,
+                _ => &silent,
+            };
+            data = stage.encode_with_control(data.encode_as(stage.input_kind()), stage_control)?;
+            scope.finish(data.len());
+        }
+
+        Ok(data.into_bytes())
+    }
+
+    /// Same as `encode`, recording time of every stage and tokens emitted by them into `stats`.
+    /// If no stage emits tokens, all bytes are counted as literals.
+    pub fn encode_with_stats(&self, bytes: Vec<u8>, stats: &mut CompressionStats) -> Vec<u8> {
+        let literals_num = bytes.len();
+        let mut tokens_emitted = false;
+        let mut data = StageData::Bytes(bytes);
+        for stage in self.stages.iter() {
+            let scope = scope!(DEBUG, "encode_stage", stage = stage.name());
+            let start = Instant::now();
+            data = stage.encode_with_stats(data.encode_as(stage.input_kind()), stats);
+            stats.record_stage_time(stage.name(), start.elapsed());
+            scope.finish(data.len());
+            if let StageData::Tokens(tokens) = &data {
+                stats.record_tokens(tokens);
+                tokens_emitted = true;
+            }
+        }
+        if !tokens_emitted {
+            stats.literals_num += literals_num;
+        }
+
+        data.into_bytes()
+    }
+
+    pub fn decode(&self, bytes: Vec<u8>) -> Result<Vec<u8>, DecodeError> {
+        self.decode_with_limits(bytes, &DecompressLimits::default())
+    }
+
+    /// Same as `decode`, but fails once decoded data exceeds `limits`. The output limit
+    /// applies to a stage only if the stages decoding after it preserve expanded length,
+    /// since lengths of other data between stages are not known.
+    pub fn decode_with_limits(
+        &self,
+        bytes: Vec<u8>,
+        limits: &DecompressLimits,
+    ) -> Result<Vec<u8>, DecodeError> {
+        self.decode_checked(bytes, limits, || Ok(()))
+    }
+
+    /// Same as `decode_with_limits`, but calls `check` before every stage, stopping
+    /// with its error, e.g. once a job is cancelled.
+    pub fn decode_checked<E: From<DecodeError>>(
+        &self,
+        bytes: Vec<u8>,
+        limits: &DecompressLimits,
+        check: impl Fn() -> Result<(), E>,
+    ) -> Result<Vec<u8>, E> {
+        let inner_limits = limits.with_max_output_len(u64::MAX);
+        let stage_limits = |i: usize| match self.stages[..i]
+            .iter()
+            .all(|stage| stage.preserves_expanded_len())
+        {
+            true => limits,
+            false => &inner_limits,
+        };
+        let stages_num = self.stages.len();
+        let mut data = StageData::Bytes(bytes)
+            .decode_as(self.output_kind(stages_num), stage_limits(stages_num))?;
+        for (i, stage) in self.stages.iter().enumerate().rev() {
+            check()?;
+            data = stage
+                .decode(data, stage_limits(i))?
+                .decode_as(self.output_kind(i), stage_limits(i))?;
+        }
+
+        Ok(data.into_bytes())
+    }
+
+    /// Kind of data produced by first `stages_num` stages.
+    fn output_kind(&self, stages_num: usize) -> DataKind {
+        match stages_num {
+            0 => DataKind::Bytes,
+            n => self.stages[n - 1].output_kind(),
+        }
+    }
+}
@@ -0,0 +1,6 @@
+pub mod codec;
+pub mod implementation;
+mod stages;
+
+pub use codec::{Codec, DataKind, StageData};
+pub use implementation::{Pipeline, UnknownStageError};
@@ -0,0 +1,407 @@
+const SIZE_FOR_ONE_WORKER: usize = 5000;
+
+use rayon::prelude::*;
+
+use super::codec::{Codec, DataKind, StageData};
+use crate::ans::AnsCompressor;
+use crate::bwt::{BwtCompressor, MoveToFront, ZeroRunCompressor};
+use crate::error::{CancelledError, DecodeError};
+use crate::huffman::{AdaptiveHuffmanCompressor, HuffmanCompressor};
+use crate::limits::DecompressLimits;
+use crate::lz77::{
+    implementation::ResultEncodingVec, long_range::MAX_LONG_DISTANCE, CostModel, LZ77Compressor,
+    PatchCompressor, RleCompressor, Segment,
+};
+use crate::progress::{CancellationToken, FrameControl};
+use crate::range_coder::RangeCompressor;
+use crate::stats::CompressionStats;
+use crate::trace::{scope, Parent};
+
+impl Codec for LZ77Compressor {
+    fn name(&self) -> &'static str {
+        match self.long_range() {
+            Some(_) => "lz77_long",
+            None => "lz77",
+        }
+    }
+
+    fn input_kind(&self) -> DataKind {
+        DataKind::Bytes
+    }
+
+    fn output_kind(&self) -> DataKind {
+        DataKind::Tokens
+    }
+
+    /// Matches are searched in chunks of the input in parallel and never cross their borders,
+    /// except long range ones found in the whole input beforehand, then tokens of all chunks
+    /// reuse recent distances in one pass.
+    fn encode(&self, data: StageData) -> StageData {
+        self.encode_with_control(data, &FrameControl::new(&CancellationToken::new()))
+            .expect("Encoding without cancellation cannot be cancelled.")
+    }
+
+    fn encode_with_control(
+        &self,
+        data: StageData,
+        control: &FrameControl,
+    ) -> Result<StageData, CancelledError> {
+        let bytes = data.into_bytes();
+        let segments = self.segments(&bytes, SIZE_FOR_ONE_WORKER);
+        let tokens = encode_segments(self, &bytes, segments, control)?;
+        Ok(StageData::Tokens(tokens))
+    }
+
+    fn decode(&self, data: StageData, limits: &DecompressLimits) -> Result<StageData, DecodeError> {
+        Ok(StageData::Bytes(
+            data.into_tokens().try_expand_with(limits)?,
+        ))
+    }
+
+    fn preserves_expanded_len(&self) -> bool {
+        true
+    }
+
+    fn window_size(&self) -> Option<usize> {
+        match self.long_range() {
+            Some(_) => Some(MAX_LONG_DISTANCE),
+            None => Some(LZ77Compressor::window_size(self)),
+        }
+    }
+}
+
+/// Tokens of `bytes` split into `segments`, chunks of which are searched for matches
+/// in parallel, reporting every segment done, with recent distances reused over all of them.
+fn encode_segments(
+    compressor: &LZ77Compressor,
+    bytes: &[u8],
+    segments: Vec<Segment>,
+    control: &FrameControl,
+) -> Result<ResultEncodingVec, CancelledError> {
+    let compressor = &compressor.fitted_to(bytes);
+    let parent = Parent::current();
+    let chunks = segments
+        .into_par_iter()
+        .map(|segment| {
+            control.check()?;
+            let len = match &segment {
+                Segment::Chunk(range) => range.len(),
+                Segment::Match(token) => token.expanded_len(),
+            };
+            let tokens = compress_segment(compressor, bytes, segment, &parent);
+            control.advance(len);
+            Ok(tokens)
+        })
+        .collect::<Result<Vec<ResultEncodingVec>, CancelledError>>()?;
+    let tokens = chunks.into_iter().flatten().collect();
+    Ok(compressor.use_repeat_distances(bytes, tokens))
+}
+
+fn compress_segment(
+    compressor: &LZ77Compressor,
+    bytes: &[u8],
+    segment: Segment,
+    parent: &Parent,
+) -> ResultEncodingVec {
+    let chunk = match segment {
+        Segment::Chunk(range) => &bytes[range],
+        Segment::Match(token) => return ResultEncodingVec::from_iter([token]),
+    };
+    let scope = scope!(parent: parent, DEBUG, "lz77_chunk", input_bytes = chunk.len());
+    let tokens = compressor.find_matches(&chunk.to_vec());
+    scope.finish(tokens.len());
+    tokens
+}
+
+impl Codec for PatchCompressor<'_> {
+    fn name(&self) -> &'static str {
+        "patch"
+    }
+
+    fn input_kind(&self) -> DataKind {
+        DataKind::Bytes
+    }
+
+    fn output_kind(&self) -> DataKind {
+        DataKind::Tokens
+    }
+
+    /// Same as the encoding of `LZ77Compressor`, with matches in the reference.
+    fn encode(&self, data: StageData) -> StageData {
+        self.encode_with_control(data, &FrameControl::new(&CancellationToken::new()))
+            .expect("Encoding without cancellation cannot be cancelled.")
+    }
+
+    fn encode_with_control(
+        &self,
+        data: StageData,
+        control: &FrameControl,
+    ) -> Result<StageData, CancelledError> {
+        let bytes = data.into_bytes();
+        let segments = self.segments(&bytes, SIZE_FOR_ONE_WORKER);
+        let tokens = encode_segments(self.compressor(), &bytes, segments, control)?;
+        Ok(StageData::Tokens(tokens))
+    }
+
+    fn decode(&self, data: StageData, limits: &DecompressLimits) -> Result<StageData, DecodeError> {
+        Ok(StageData::Bytes(
+            self.decompress_tokens(&data.into_tokens(), limits)?,
+        ))
+    }
+
+    fn preserves_expanded_len(&self) -> bool {
+        true
+    }
+
+    fn window_size(&self) -> Option<usize> {
+        Some(MAX_LONG_DISTANCE)
+    }
+}
+
+impl Codec for RleCompressor {
+    fn name(&self) -> &'static str {
+        "rle"
+    }
+
+    fn input_kind(&self) -> DataKind {
+        DataKind::Bytes
+    }
+
+    fn output_kind(&self) -> DataKind {
+        DataKind::Tokens
+    }
+
+    fn encode(&self, data: StageData) -> StageData {
+        StageData::Tokens(RleCompressor::compress_to_tokens(&data.into_bytes()))
+    }
+
+    fn decode(&self, data: StageData, limits: &DecompressLimits) -> Result<StageData, DecodeError> {
+        Ok(StageData::Bytes(
+            data.into_tokens().try_expand_with(limits)?,
+        ))
+    }
+
+    fn preserves_expanded_len(&self) -> bool {
+        true
+    }
+
+    fn window_size(&self) -> Option<usize> {
+        Some(1)
+    }
+}
+
+impl Codec for BwtCompressor {
+    fn name(&self) -> &'static str {
+        "bwt"
+    }
+
+    fn input_kind(&self) -> DataKind {
+        DataKind::Bytes
+    }
+
+    fn output_kind(&self) -> DataKind {
+        DataKind::Bytes
+    }
+
+    fn encode(&self, data: StageData) -> StageData {
+        StageData::Bytes(BwtCompressor::compress(&data.into_bytes()))
+    }
+
+    fn decode(&self, data: StageData, limits: &DecompressLimits) -> Result<StageData, DecodeError> {
+        Ok(StageData::Bytes(BwtCompressor::decompress(
+            &data.into_bytes(),
+            limits,
+        )?))
+    }
+}
+
+impl Codec for MoveToFront {
+    fn name(&self) -> &'static str {
+        "mtf"
+    }
+
+    fn input_kind(&self) -> DataKind {
+        DataKind::Bytes
+    }
+
+    fn output_kind(&self) -> DataKind {
+        DataKind::Bytes
+    }
+
+    fn encode(&self, data: StageData) -> StageData {
+        StageData::Bytes(MoveToFront::encode(&data.into_bytes()))
+    }
+
+    fn decode(
+        &self,
+        data: StageData,
+        _limits: &DecompressLimits,
+    ) -> Result<StageData, DecodeError> {
+        Ok(StageData::Bytes(MoveToFront::decode(&data.into_bytes())))
+    }
+
+    fn preserves_expanded_len(&self) -> bool {
+        true
+    }
+}
+
+impl Codec for ZeroRunCompressor {
+    fn name(&self) -> &'static str {
+        "zrle"
+    }
+
+    fn input_kind(&self) -> DataKind {
+        DataKind::Bytes
+    }
+
+    fn output_kind(&self) -> DataKind {
+        DataKind::Tokens
+    }
+
+    fn encode(&self, data: StageData) -> StageData {
+        StageData::Tokens(ZeroRunCompressor::compress_to_tokens(&data.into_bytes()))
+    }
+
+    fn decode(&self, data: StageData, limits: &DecompressLimits) -> Result<StageData, DecodeError> {
+        Ok(StageData::Bytes(
+            data.into_tokens().try_expand_with(limits)?,
+        ))
+    }
+
+    fn preserves_expanded_len(&self) -> bool {
+        true
+    }
+
+    fn window_size(&self) -> Option<usize> {
+        Some(1)
+    }
+}
+
+impl Codec for HuffmanCompressor {
+    fn name(&self) -> &'static str {
+        "huffman"
+    }
+
+    fn input_kind(&self) -> DataKind {
+        DataKind::Tokens
+    }
+
+    fn output_kind(&self) -> DataKind {
+        DataKind::Bytes
+    }
+
+    fn encode(&self, data: StageData) -> StageData {
+        let tokens = data.into_tokens();
+        StageData::Bytes(HuffmanCompressor::compress_tokens_to_bytes(
+            &tokens,
+            self.predefined_codes(),
+        ))
+    }
+
+    fn encode_with_stats(&self, data: StageData, stats: &mut CompressionStats) -> StageData {
+        let tokens = data.into_tokens();
+        StageData::Bytes(
+            HuffmanCompressor::compress_tokens_with_stats(&tokens, self.predefined_codes(), stats)
+                .to_bytes(),
+        )
+    }
+
+    fn decode(&self, data: StageData, limits: &DecompressLimits) -> Result<StageData, DecodeError> {
+        let bytes = data.into_bytes();
+        Ok(StageData::Tokens(
+            HuffmanCompressor::decompress_tokens_with_limits(&bytes, limits)?,
+        ))
+    }
+
+    fn cost_model(&self) -> Option<CostModel> {
+        Some(CostModel::Coded(self.symbol_costs()))
+    }
+}
+
+impl Codec for AdaptiveHuffmanCompressor {
+    fn name(&self) -> &'static str {
+        "adaptive_huffman"
+    }
+
+    fn input_kind(&self) -> DataKind {
+        DataKind::Tokens
+    }
+
+    fn output_kind(&self) -> DataKind {
+        DataKind::Bytes
+    }
+
+    fn encode(&self, data: StageData) -> StageData {
+        let tokens = data.into_tokens();
+        StageData::Bytes(AdaptiveHuffmanCompressor::compress_tokens_to_bytes(&tokens))
+    }
+
+    fn decode(&self, data: StageData, limits: &DecompressLimits) -> Result<StageData, DecodeError> {
+        let bytes = data.into_bytes();
+        Ok(StageData::Tokens(
+            AdaptiveHuffmanCompressor::decompress_tokens_with_limits(&bytes, limits)?,
+        ))
+    }
+
+    fn cost_model(&self) -> Option<CostModel> {
+        Some(CostModel::Coded(AdaptiveHuffmanCompressor::symbol_costs()))
+    }
+}
+
+impl Codec for RangeCompressor {
+    fn name(&self) -> &'static str {
+        "range"
+    }
+
+    fn input_kind(&self) -> DataKind {
+        DataKind::Tokens
+    }
+
+    fn output_kind(&self) -> DataKind {
+        DataKind::Bytes
+    }
+
+    fn encode(&self, data: StageData) -> StageData {
+        StageData::Bytes(RangeCompressor::compress_tokens(&data.into_tokens()))
+    }
+
+    fn decode(&self, data: StageData, limits: &DecompressLimits) -> Result<StageData, DecodeError> {
+        let bytes = data.into_bytes();
+        Ok(StageData::Tokens(
+            RangeCompressor::decompress_tokens_with_limits(&bytes, limits)?,
+        ))
+    }
+
+    fn cost_model(&self) -> Option<CostModel> {
+        Some(CostModel::Coded(RangeCompressor::symbol_costs()))
+    }
+}
+
+impl Codec for AnsCompressor {
+    fn name(&self) -> &'static str {
+        "rans"
+    }
+
+    fn input_kind(&self) -> DataKind {
+        DataKind::Tokens
+    }
+
+    fn output_kind(&self) -> DataKind {
+        DataKind::Bytes
+    }
+
+    fn encode(&self, data: StageData) -> StageData {
+        StageData::Bytes(AnsCompressor::compress_tokens(&data.into_tokens()))
+    }
+
+    fn decode(&self, data: StageData, limits: &DecompressLimits) -> Result<StageData, DecodeError> {
+        let bytes = data.into_bytes();
+        Ok(StageData::Tokens(
+            AnsCompressor::decompress_tokens_with_limits(&bytes, limits)?,
+        ))
+    }
+
+    fn cost_model(&self) -> Option<CostModel> {
+        Some(CostModel::Coded(AnsCompressor::symbol_costs()))
+    }
+}
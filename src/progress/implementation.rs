@@ -0,0 +1,158 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc,
+};
+
+use crate::error::CancelledError;
+
+/// Bytes of input processed so far, out of all bytes of input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub consumed: u64,
+    pub total: u64,
+}
+
+/// Flag shared between the caller and a running job, which stops the job once set.
+/// Clones refer to the same flag.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn check(&self) -> Result<(), CancelledError> {
+        match self.is_cancelled() {
+            true => Err(CancelledError),
+            false => Ok(()),
+        }
+    }
+}
+
+type ProgressCallback = Box<dyn Fn(Progress) + Send + Sync>;
+
+/// Progress callback and cancellation token of a single compression or decompression.
+///
+/// Compression reports progress after every chunk searched for matches, and after
+/// every frame for the rest of it, decompression after every frame. Frames and chunks
+/// are processed in parallel, so the callback is called from many threads and reports
+/// may come out of order. Cancellation is checked before every frame and every stage
+/// of a pipeline, and while compressing also before every chunk searched for matches.
+#[derive(Default)]
+pub struct JobControl {
+    on_progress: Option<ProgressCallback>,
+    cancellation: CancellationToken,
+}
+
+impl JobControl {
+    pub fn new() -> Self {
+        JobControl::default()
+    }
+
+    pub fn on_progress(mut self, callback: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.on_progress = Some(Box::new(callback));
+        self
+    }
+
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    pub(crate) fn tracker(&self, total: u64) -> ProgressTracker<'_> {
+        ProgressTracker {
+            control: self,
+            consumed: AtomicU64::new(0),
+            total,
+        }
+    }
+}
+
+/// Sums bytes consumed by frames finished on any thread.
+pub(crate) struct ProgressTracker<'a> {
+    control: &'a JobControl,
+    consumed: AtomicU64,
+    total: u64,
+}
+
+impl<'a> ProgressTracker<'a> {
+    pub(crate) fn advance(&self, bytes_num: usize) {
+        let consumed =
+            self.consumed.fetch_add(bytes_num as u64, Ordering::Relaxed) + bytes_num as u64;
+        if let Some(callback) = &self.control.on_progress {
+            callback(Progress {
+                consumed,
+                total: self.total,
+            });
+        }
+    }
+}
+
+/// Cancellation token and progress of a single frame, handed to the stages encoding it.
+/// Stages consuming the frame in chunks report them with `advance`, and whatever they
+/// did not report is reported once the frame is done.
+pub struct FrameControl<'a> {
+    cancellation: &'a CancellationToken,
+    tracker: Option<&'a ProgressTracker<'a>>,
+    reported: AtomicU64,
+}
+
+impl<'a> FrameControl<'a> {
+    /// Control reporting no progress.
+    pub fn new(cancellation: &'a CancellationToken) -> Self {
+        FrameControl {
+            cancellation,
+            tracker: None,
+            reported: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn reporting(
+        cancellation: &'a CancellationToken,
+        tracker: &'a ProgressTracker<'a>,
+    ) -> Self {
+        FrameControl {
+            tracker: Some(tracker),
+            ..FrameControl::new(cancellation)
+        }
+    }
+
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        self.cancellation
+    }
+
+    pub fn check(&self) -> Result<(), CancelledError> {
+        self.cancellation.check()
+    }
+
+    /// Reports `bytes_num` bytes of the frame consumed.
+    pub fn advance(&self, bytes_num: usize) {
+        if let Some(tracker) = self.tracker {
+            self.reported.fetch_add(bytes_num as u64, Ordering::Relaxed);
+            tracker.advance(bytes_num);
+        }
+    }
+
+    /// Reports the bytes of the frame of `frame_len` bytes not reported yet.
+    pub(crate) fn finish(&self, frame_len: usize) {
+        let reported = self.reported.load(Ordering::Relaxed) as usize;
+        if let Some(tracker) = self.tracker.filter(|_| reported < frame_len) {
+            tracker.advance(frame_len - reported);
+        }
+    }
+}
@@ -0,0 +1,245 @@
+const TOP_VALUE: u32 = 1 << 24;
+const INITIAL_BYTES_NUM: usize = 5;
+/// Estimated bits of a length or distance symbol, extra bits excluded.
+const ESTIMATED_SYMBOL_BITS: usize = 5;
+
+use super::model::FrequencyModel;
+use crate::error::DecodeError;
+use crate::limits::{DecompressLimits, OutputBudget};
+use crate::lz77::{
+    alphabet::{SymbolCode, DISTANCE_SYMBOLS_NUM, END_OF_BLOCK, LIT_LEN_SYMBOLS_NUM},
+    cost::{LiteralFitting, SymbolCosts},
+    implementation::{ResultEncoding, ResultEncodingVec},
+};
+
+/// Multi-symbol range encoder with carry propagation, in the style of the LZMA one.
+pub struct RangeEncoder {
+    low: u64,
+    range: u32,
+    cache: u8,
+    cache_size: u64,
+    bytes: Vec<u8>,
+}
+
+impl Default for RangeEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RangeEncoder {
+    pub fn new() -> Self {
+        RangeEncoder {
+            low: 0,
+            range: u32::MAX,
+            cache: 0,
+            cache_size: 1,
+            bytes: Vec::new(),
+        }
+    }
+
+    pub fn encode(&mut self, model: &mut FrequencyModel, symbol: usize) {
+        let (low, frequency) = model.interval(symbol);
+        self.range /= model.total();
+        self.low += low as u64 * self.range as u64;
+        self.range *= frequency;
+        self.normalize();
+        model.update(symbol);
+    }
+
+    /// Writes `n` lowest bits of `value` with probability 1/2 each.
+    pub fn encode_direct_bits(&mut self, value: u64, n: u32) {
+        for i in (0..n).rev() {
+            self.range >>= 1;
+            if (value >> i) & 1 == 1 {
+                self.low += self.range as u64;
+            }
+            self.normalize();
+        }
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        (0..INITIAL_BYTES_NUM).for_each(|_| self.shift_low());
+        self.bytes
+    }
+
+    fn normalize(&mut self) {
+        while self.range < TOP_VALUE {
+            self.range <<= 8;
+            self.shift_low();
+        }
+    }
+
+    fn shift_low(&mut self) {
+        if self.low < 0xFF00_0000 || self.low >= 1 << 32 {
+            let carry = (self.low >> 32) as u8;
+            let mut byte = self.cache;
+            loop {
+                self.bytes.push(byte.wrapping_add(carry));
+                byte = 0xFF;
+                self.cache_size -= 1;
+                if self.cache_size == 0 {
+                    break;
+                }
+            }
+            self.cache = (self.low >> 24) as u8;
+        }
+        self.cache_size += 1;
+        self.low = (self.low & 0x00FF_FFFF) << 8;
+    }
+}
+
+pub struct RangeDecoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    code: u32,
+    range: u32,
+}
+
+impl<'a> RangeDecoder<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        let mut decoder = RangeDecoder {
+            bytes,
+            position: 0,
+            code: 0,
+            range: u32::MAX,
+        };
+        (0..INITIAL_BYTES_NUM)
+            .for_each(|_| decoder.code = (decoder.code << 8) | decoder.next_byte() as u32);
+        decoder
+    }
+
+    /// Returns `false` once the decoder read past the bytes flushed by the encoder.
+    pub fn is_within_input(&self) -> bool {
+        self.position <= self.bytes.len()
+    }
+
+    pub fn decode(&mut self, model: &mut FrequencyModel) -> usize {
+        self.range /= model.total();
+        let target = (self.code / self.range).min(model.total() - 1);
+        let (symbol, low, frequency) = model.find(target);
+        self.code -= low * self.range;
+        self.range *= frequency;
+        self.normalize();
+        model.update(symbol);
+        symbol
+    }
+
+    pub fn decode_direct_bits(&mut self, n: u32) -> u64 {
+        let mut value = 0;
+        for _ in 0..n {
+            self.range >>= 1;
+            let bit = self.code >= self.range;
+            if bit {
+                self.code -= self.range;
+            }
+            value = (value << 1) | bit as u64;
+            self.normalize();
+        }
+
+        value
+    }
+
+    fn normalize(&mut self) {
+        while self.range < TOP_VALUE {
+            self.range <<= 8;
+            self.code = (self.code << 8) | self.next_byte() as u32;
+        }
+    }
+
+    fn next_byte(&mut self) -> u8 {
+        let byte = self.bytes.get(self.position).copied().unwrap_or(0);
+        self.position += 1;
+        byte
+    }
+}
+
+/// Range coder over LZ77 tokens, with adaptive models for literals/lengths and distances.
+pub struct RangeCompressor;
+
+impl RangeCompressor {
+    /// Costs of tokens coded by the stage, with fractions of bits adapting to the probabilities of literals.
+    pub fn symbol_costs() -> SymbolCosts {
+        SymbolCosts::fitted(
+            LiteralFitting::Fractional,
+            ESTIMATED_SYMBOL_BITS,
+            ESTIMATED_SYMBOL_BITS,
+        )
+    }
+
+    pub fn compress(ascii_bytes: &[u8]) -> Vec<u8> {
+        let tokens: ResultEncodingVec = ascii_bytes
+            .iter()
+            .map(|c| ResultEncoding::Ascii(*c))
+            .collect();
+        RangeCompressor::compress_tokens(&tokens)
+    }
+
+    pub fn decompress(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        RangeCompressor::decompress_tokens(bytes)?.try_expand()
+    }
+
+    pub fn compress_tokens(tokens: &ResultEncodingVec) -> Vec<u8> {
+        let mut lit_len = FrequencyModel::new(LIT_LEN_SYMBOLS_NUM as usize);
+        let mut distance = FrequencyModel::new(DISTANCE_SYMBOLS_NUM as usize);
+        let mut encoder = RangeEncoder::new();
+
+        for token in tokens.iter() {
+            let (lit_len_code, distance_code) = token.to_symbol_codes();
+            encoder.encode(&mut lit_len, lit_len_code.symbol as usize);
+            encoder.encode_direct_bits(lit_len_code.extra_bits, lit_len_code.extra_bits_num);
+            if let Some(code) = distance_code {
+                encoder.encode(&mut distance, code.symbol as usize);
+                encoder.encode_direct_bits(code.extra_bits, code.extra_bits_num);
+            }
+        }
+        encoder.encode(&mut lit_len, END_OF_BLOCK as usize);
+
+        encoder.finish()
+    }
+
+    pub fn decompress_tokens(bytes: &[u8]) -> Result<ResultEncodingVec, DecodeError> {
+        RangeCompressor::decompress_tokens_with_limits(bytes, &DecompressLimits::default())
+    }
+
+    /// Same as `decompress_tokens`, but fails as soon as expanded tokens
+    /// exceed `limits`, the ratio limit included.
+    pub fn decompress_tokens_with_limits(
+        bytes: &[u8],
+        limits: &DecompressLimits,
+    ) -> Result<ResultEncodingVec, DecodeError> {
+        let mut budget =
+            OutputBudget::new(&limits.with_max_output_len(limits.output_limit(bytes.len())));
+        let mut lit_len = FrequencyModel::new(LIT_LEN_SYMBOLS_NUM as usize);
+        let mut distance = FrequencyModel::new(DISTANCE_SYMBOLS_NUM as usize);
+        let mut decoder = RangeDecoder::new(bytes);
+
+        let mut tokens = ResultEncodingVec::new();
+        loop {
+            if !decoder.is_within_input() {
+                return Err(DecodeError::new(
+                    "sequence ended before the end of block symbol",
+                ));
+            }
+            let symbol = decoder.decode(&mut lit_len) as u16;
+            if symbol == END_OF_BLOCK {
+                return Ok(tokens);
+            }
+            if !SymbolCode::is_length_symbol(symbol) {
+                budget.take_bytes(1)?;
+                tokens.push(ResultEncoding::Ascii(symbol as u8));
+                continue;
+            }
+
+            let length_extra =
+                decoder.decode_direct_bits(SymbolCode::length_extra_bits_num(symbol));
+            let len = SymbolCode::length_value(symbol, length_extra);
+            let distance_symbol = decoder.decode(&mut distance) as u16;
+            let distance_extra =
+                decoder.decode_direct_bits(SymbolCode::distance_extra_bits_num(distance_symbol));
+            let token = SymbolCode::reference(len, distance_symbol, distance_extra);
+            budget.take(&token)?;
+            tokens.push(token);
+        }
+    }
+}
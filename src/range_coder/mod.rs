@@ -0,0 +1,5 @@
+pub mod implementation;
+mod model;
+
+pub use implementation::{RangeCompressor, RangeDecoder, RangeEncoder};
+pub use model::FrequencyModel;
@@ -0,0 +1,86 @@
+const MAX_TOTAL_FREQUENCY: u32 = 1 << 16;
+const FREQUENCY_INCREMENT: u32 = 32;
+
+/// Adaptive frequencies of symbols `0..symbols_num`, every one of them starting at 1.
+/// Cumulative frequencies are kept in a Fenwick tree, so both coding directions
+/// take logarithmic time in the size of the alphabet.
+pub struct FrequencyModel {
+    frequencies: Vec<u32>,
+    fenwick_tree: Vec<u32>,
+    total: u32,
+}
+
+impl FrequencyModel {
+    pub fn new(symbols_num: usize) -> Self {
+        let mut model = FrequencyModel {
+            frequencies: vec![1; symbols_num],
+            fenwick_tree: Vec::new(),
+            total: 0,
+        };
+        model.rebuild_tree();
+        model
+    }
+
+    pub fn total(&self) -> u32 {
+        self.total
+    }
+
+    /// Returns sum of frequencies of symbols lower than `symbol`, and its own frequency.
+    pub fn interval(&self, symbol: usize) -> (u32, u32) {
+        let mut low = 0;
+        let mut i = symbol;
+        while i > 0 {
+            low += self.fenwick_tree[i];
+            i &= i - 1;
+        }
+
+        (low, self.frequencies[symbol])
+    }
+
+    /// Finds symbol whose interval contains `target`, returns it with its interval.
+    pub fn find(&self, target: u32) -> (usize, u32, u32) {
+        let mut position = 0;
+        let mut low = 0;
+        let mut step = self.fenwick_tree.len().next_power_of_two();
+        while step > 0 {
+            let next = position + step;
+            if next < self.fenwick_tree.len() && low + self.fenwick_tree[next] <= target {
+                position = next;
+                low += self.fenwick_tree[next];
+            }
+            step /= 2;
+        }
+
+        (position, low, self.frequencies[position])
+    }
+
+    pub fn update(&mut self, symbol: usize) {
+        self.frequencies[symbol] += FREQUENCY_INCREMENT;
+        self.total += FREQUENCY_INCREMENT;
+        if self.total > MAX_TOTAL_FREQUENCY {
+            self.frequencies
+                .iter_mut()
+                .for_each(|frequency| *frequency = (*frequency / 2).max(1));
+            self.rebuild_tree();
+            return;
+        }
+
+        let mut i = symbol + 1;
+        while i < self.fenwick_tree.len() {
+            self.fenwick_tree[i] += FREQUENCY_INCREMENT;
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    fn rebuild_tree(&mut self) {
+        self.fenwick_tree = vec![0; self.frequencies.len() + 1];
+        for (symbol, frequency) in self.frequencies.iter().enumerate() {
+            let mut i = symbol + 1;
+            while i < self.fenwick_tree.len() {
+                self.fenwick_tree[i] += frequency;
+                i += i & i.wrapping_neg();
+            }
+        }
+        self.total = self.frequencies.iter().sum();
+    }
+}
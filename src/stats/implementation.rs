@@ -0,0 +1,134 @@
+use std::{collections::BTreeMap, fmt, time::Duration};
+
+use crate::lz77::{alphabet::SymbolCode, implementation::ResultEncodingVec};
+
+/// Report on a single compression, returned by `DeflateCompression::compress_with_stats`.
+///
+/// Histograms of match lengths and distances group values coded with the same symbol,
+/// keyed by the smallest value of the group. Frames are compressed in parallel, so
+/// times of stages are summed over all frames and may exceed the total wall time.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompressionStats {
+    pub input_len: u64,
+    pub compressed_len: u64,
+    pub literals_num: usize,
+    pub matches_num: usize,
+    /// Matches repeating one of recent distances, left out of `match_distances`.
+    pub repeat_matches_num: usize,
+    pub match_lengths: BTreeMap<usize, usize>,
+    pub match_distances: BTreeMap<usize, usize>,
+    /// Number of Huffman coded symbols written with a code of given length.
+    pub code_lengths: BTreeMap<usize, usize>,
+    /// Bits of container header, frame lengths and checksums, block headers and code tables.
+    pub header_bits: u64,
+    /// Frames written uncompressed, since compression would not make them shorter.
+    pub stored_frames_num: usize,
+    pub stage_times: Vec<(&'static str, Duration)>,
+}
+
+impl CompressionStats {
+    pub fn new() -> Self {
+        CompressionStats::default()
+    }
+
+    pub fn bits_per_byte(&self) -> f64 {
+        match self.input_len {
+            0 => 0.0,
+            len => (self.compressed_len * 8) as f64 / len as f64,
+        }
+    }
+
+    pub fn record_tokens(&mut self, tokens: &ResultEncodingVec) {
+        for token in tokens.iter() {
+            let (lit_len_code, distance_code) = token.to_symbol_codes();
+            let Some(distance_code) = distance_code else {
+                self.literals_num += 1;
+                continue;
+            };
+
+            self.matches_num += 1;
+            *self
+                .match_lengths
+                .entry(SymbolCode::length_value(lit_len_code.symbol, 0))
+                .or_default() += 1;
+            if SymbolCode::is_repeat_symbol(distance_code.symbol) {
+                self.repeat_matches_num += 1;
+                continue;
+            }
+            *self
+                .match_distances
+                .entry(SymbolCode::distance_value(distance_code.symbol, 0))
+                .or_default() += 1;
+        }
+    }
+
+    pub fn record_code_len(&mut self, code_len: usize) {
+        *self.code_lengths.entry(code_len).or_default() += 1;
+    }
+
+    pub fn record_stage_time(&mut self, stage: &'static str, time: Duration) {
+        match self.stage_times.iter_mut().find(|(name, _)| *name == stage) {
+            Some((_, total)) => *total += time,
+            None => self.stage_times.push((stage, time)),
+        }
+    }
+
+    /// Adds counts and times of `other`, e.g. of another frame.
+    pub fn merge(&mut self, other: &CompressionStats) {
+        self.input_len += other.input_len;
+        self.compressed_len += other.compressed_len;
+        self.literals_num += other.literals_num;
+        self.matches_num += other.matches_num;
+        self.repeat_matches_num += other.repeat_matches_num;
+        for (merged, added) in [
+            (&mut self.match_lengths, &other.match_lengths),
+            (&mut self.match_distances, &other.match_distances),
+            (&mut self.code_lengths, &other.code_lengths),
+        ] {
+            added
+                .iter()
+                .for_each(|(key, count)| *merged.entry(*key).or_default() += count);
+        }
+        self.header_bits += other.header_bits;
+        self.stored_frames_num += other.stored_frames_num;
+        other
+            .stage_times
+            .iter()
+            .for_each(|(stage, time)| self.record_stage_time(stage, *time));
+    }
+}
+
+impl fmt::Display for CompressionStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let histogram = |values: &BTreeMap<usize, usize>| {
+            values
+                .iter()
+                .map(|(key, count)| format!("{}: {}", key, count))
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+
+        writeln!(
+            f,
+            "Bytes: {} -> {} ({:.3} bits per byte)",
+            self.input_len,
+            self.compressed_len,
+            self.bits_per_byte()
+        )?;
+        writeln!(
+            f,
+            "Literals: {}, matches: {} ({} of repeated distances)",
+            self.literals_num, self.matches_num, self.repeat_matches_num
+        )?;
+        writeln!(f, "Match lengths: {}", histogram(&self.match_lengths))?;
+        writeln!(f, "Match distances: {}", histogram(&self.match_distances))?;
+        writeln!(f, "Code lengths: {}", histogram(&self.code_lengths))?;
+        writeln!(f, "Header bits: {}", self.header_bits)?;
+        writeln!(f, "Stored frames: {}", self.stored_frames_num)?;
+        for (stage, time) in self.stage_times.iter() {
+            writeln!(f, "Stage {}: {:?}", stage, time)?;
+        }
+
+        Ok(())
+    }
+}
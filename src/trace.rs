@@ -0,0 +1,91 @@
+//! Spans and events emitted with the `tracing` feature. Without the feature `Scope` and
+//! `Parent` are empty and `scope!` evaluates none of its fields, so it costs nothing.
+
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+
+/// Span entered for the time of a single step of compression, created by `scope!`.
+pub(crate) struct Scope {
+    #[cfg(feature = "tracing")]
+    span: tracing::span::EnteredSpan,
+    #[cfg(feature = "tracing")]
+    start: Instant,
+}
+
+impl Scope {
+    #[cfg(feature = "tracing")]
+    pub(crate) fn new(span: tracing::Span) -> Self {
+        Scope {
+            span: span.entered(),
+            start: Instant::now(),
+        }
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    pub(crate) fn new() -> Self {
+        Scope {}
+    }
+
+    /// Emits event with the length of the output of the step (bytes, or tokens and
+    /// positions for LZ77 steps) and the time elapsed since the span was entered.
+    pub(crate) fn finish(self, output_len: usize) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            parent: self.span.id(),
+            output_len,
+            elapsed_us = self.start.elapsed().as_micros() as u64,
+            "finished"
+        );
+        #[cfg(not(feature = "tracing"))]
+        let _ = output_len;
+    }
+}
+
+/// Span of the calling thread, passed to rayon workers which do not inherit it.
+#[derive(Clone)]
+pub(crate) struct Parent {
+    #[cfg(feature = "tracing")]
+    pub(crate) span: tracing::Span,
+}
+
+impl Parent {
+    pub(crate) fn current() -> Self {
+        Parent {
+            #[cfg(feature = "tracing")]
+            span: tracing::Span::current(),
+        }
+    }
+}
+
+/// Enters span of given level and name, e.g. `scope!(DEBUG, "frame", input_bytes = n)`,
+/// optionally under a `parent: &Parent` taken from another thread.
+macro_rules! scope {
+    (parent: $parent:expr, $level:ident, $name:literal $(, $($fields:tt)*)?) => {{
+        #[cfg(feature = "tracing")]
+        let scope = $crate::trace::Scope::new(tracing::span!(
+            parent: &$parent.span,
+            tracing::Level::$level,
+            $name
+            $(, $($fields)*)?
+        ));
+        #[cfg(not(feature = "tracing"))]
+        let scope = {
+            let _ = $parent;
+            $crate::trace::Scope::new()
+        };
+        scope
+    }};
+    ($level:ident, $name:literal $(, $($fields:tt)*)?) => {{
+        #[cfg(feature = "tracing")]
+        let scope = $crate::trace::Scope::new(tracing::span!(
+            tracing::Level::$level,
+            $name
+            $(, $($fields)*)?
+        ));
+        #[cfg(not(feature = "tracing"))]
+        let scope = $crate::trace::Scope::new();
+        scope
+    }};
+}
+
+pub(crate) use scope;
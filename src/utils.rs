@@ -1,4 +0,0 @@
-pub enum TypeOr<S, T> {
-    Left(S),
-    Right(T),
-}
@@ -0,0 +1,101 @@
+use compression::analysis::{CompressedFormat, InputAnalysis};
+use compression::{DeflateCompression, DeflateConfig, Strategy};
+
+#[macro_use]
+mod utils;
+
+use utils::random_bytes;
+
+fn runs() -> Vec<u8> {
+    (0..400u32)
+        .flat_map(|i| vec![(i % 7) as u8; 20 + (i % 13) as usize])
+        .collect()
+}
+
+fn text() -> Vec<u8> {
+    "The quick brown fox jumps over the lazy dog, then naps.\n"
+        .repeat(300)
+        .into_bytes()
+}
+
+/// Bytes of skewed frequencies, but without repeated 4 byte sequences.
+fn skewed_bytes() -> Vec<u8> {
+    random_bytes(20000, 1)
+        .chunks(2)
+        .map(|pair| match pair[0] % 4 {
+            0 => pair[1],
+            _ => pair[1] % 16,
+        })
+        .collect()
+}
+
+test!(analysis_detects_compressed_formats, {
+    let mut gzip = vec![0x1F, 0x8B, 0x08];
+    gzip.extend(text());
+    assert_eq!(
+        InputAnalysis::of(&gzip).compressed_format,
+        Some(CompressedFormat::Gzip)
+    );
+    assert_eq!(InputAnalysis::of(&gzip).strategy(), Strategy::Stored);
+
+    let formats = [
+        (b"\xFF\xD8\xFF\xE0".to_vec(), CompressedFormat::Jpeg),
+        (b"PK\x03\x04".to_vec(), CompressedFormat::Zip),
+        (b"\x89PNG\r\n".to_vec(), CompressedFormat::Png),
+    ];
+    for (magic, format) in formats {
+        assert_eq!(CompressedFormat::detect(&magic), Some(format));
+    }
+    assert_eq!(CompressedFormat::detect(&text()), None);
+});
+
+test!(analysis_picks_strategy_by_content, {
+    let random = InputAnalysis::of(&random_bytes(100000, 1));
+    assert!(random.entropy > 7.9);
+    assert_eq!(random.strategy(), Strategy::Stored);
+
+    let runs = InputAnalysis::of(&runs());
+    assert!(runs.run_fraction > 0.9);
+    assert_eq!(runs.strategy(), Strategy::Rle);
+
+    let text = InputAnalysis::of(&text());
+    assert_eq!(text.text_fraction, 1.0);
+    assert_eq!(text.strategy(), Strategy::Default);
+
+    let skewed = InputAnalysis::of(&skewed_bytes());
+    assert!(skewed.repeat_fraction < 0.1);
+    assert_eq!(skewed.strategy(), Strategy::HuffmanOnly);
+
+    assert_eq!(InputAnalysis::of(&[]).strategy(), Strategy::Stored);
+});
+
+test!(auto_strategy_compresses_with_picked_pipeline, {
+    let config = DeflateConfig::builder()
+        .strategy(Strategy::Auto)
+        .build()
+        .unwrap();
+    let compressor = DeflateCompression::new(&config);
+    let inputs = [
+        random_bytes(20000, 1),
+        runs(),
+        text(),
+        skewed_bytes(),
+        Vec::new(),
+    ];
+    for input in inputs.iter() {
+        let compressed = compressor.compress(input);
+        assert_eq!(&compressor.decompress(&compressed).unwrap(), input);
+        assert!(compressed.len() as u64 <= compressor.max_compressed_len(input.len()));
+
+        let strategy = InputAnalysis::of(input).strategy();
+        let picked = DeflateCompression::new(&config.with_strategy(strategy));
+        assert_eq!(compressed, picked.compress(input));
+        let decompressor = DeflateCompression::new(&DeflateConfig::default());
+        assert_eq!(&decompressor.decompress(&compressed).unwrap(), input);
+    }
+
+    let runs = runs();
+    let rle = compressor.compress(&runs);
+    let huffman_only = DeflateCompression::new(&config.with_strategy(Strategy::HuffmanOnly));
+    assert!(rle.len() < huffman_only.compress(&runs).len());
+});
@@ -0,0 +1,95 @@
+use compression::ans::{AnsCompressor, NormalizedFrequencies, RansDecoder, RansEncoder};
+use compression::huffman::HuffmanCompressor;
+use compression::lz77::LZ77Compressor;
+use compression::{Checksum, DeflateCompression, DeflateConfig};
+
+#[macro_use]
+mod utils;
+
+test!(test_normalized_frequencies, {
+    let counts = [1000000, 1, 0, 3, 77, 0, 1];
+    let frequencies = NormalizedFrequencies::new(&counts);
+    assert_eq!(frequencies.frequencies().iter().sum::<u32>(), 1 << 12);
+    for (symbol, count) in counts.iter().enumerate() {
+        assert_eq!(frequencies.frequencies()[symbol] > 0, *count > 0);
+        let (start, frequency) = frequencies.interval(symbol);
+        (start..start + frequency)
+            .for_each(|slot| assert_eq!(frequencies.symbol_at(slot), Some(symbol)));
+    }
+});
+
+test!(test_rans_symbols_and_raw_bits, {
+    let symbols: Vec<usize> = (0..5000).map(|i| (i * i + 7) % 13).collect();
+    let mut counts = vec![0; 13];
+    symbols.iter().for_each(|s| counts[*s] += 1);
+    let frequencies = NormalizedFrequencies::new(&counts);
+
+    let mut encoder = RansEncoder::new();
+    for (i, symbol) in symbols.iter().enumerate() {
+        encoder.push_symbol(&frequencies, *symbol);
+        encoder.push_raw_bits(i as u64 * 12345, 40);
+    }
+    let bytes = encoder.finish();
+
+    let mut decoder = RansDecoder::new(&bytes);
+    for (i, symbol) in symbols.iter().enumerate() {
+        assert_eq!(decoder.decode_symbol(&frequencies), Some(*symbol));
+        assert_eq!(decoder.decode_raw_bits(40), i as u64 * 12345);
+    }
+});
+
+test!(test_ans_bytes, {
+    let s: Vec<u8> =
+        Vec::from("abcdefghijklmonprstuwvxyzABCDEFGHIJKLMNOPRSTUWVXYZ1234567890!@#$%^&*((_+");
+    assert_eq!(
+        AnsCompressor::decompress(&AnsCompressor::compress(&s)).unwrap(),
+        s
+    );
+
+    let s: Vec<u8> = Vec::new();
+    assert_eq!(
+        AnsCompressor::decompress(&AnsCompressor::compress(&s)).unwrap(),
+        s
+    );
+});
+
+test!(test_ans_beats_huffman_on_skewed_input, {
+    let s: Vec<u8> = "a".repeat(10000).into_bytes();
+    let ans_compressed = AnsCompressor::compress(&s);
+    let huffman_compressed = HuffmanCompressor::compress(&s, false);
+    assert!(ans_compressed.len() * 8 < huffman_compressed.len() / 10);
+    assert_eq!(AnsCompressor::decompress(&ans_compressed).unwrap(), s);
+});
+
+test!(test_ans_tokens, {
+    let s: Vec<u8> = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. "
+        .repeat(50)
+        .into_bytes();
+    let tokens = LZ77Compressor::new(None, None).compress_to_tokens(&s);
+    let compressed = AnsCompressor::compress_tokens(&tokens);
+    assert_eq!(
+        AnsCompressor::decompress_tokens(&compressed)
+            .unwrap()
+            .expand(),
+        s
+    );
+});
+
+test!(test_ans_rejects_table_without_end_of_block, {
+    // Literal 'a' takes all 4096 slots, so decoding it neither changes the state nor
+    // reads input, and no end of block symbol could ever be decoded.
+    let payload = [0, 1, 0, b'a', 0x10, 0, 0, 0, 0, 0x80, 0, 0];
+    assert!(AnsCompressor::decompress_tokens(&payload).is_err());
+
+    let config = DeflateConfig::builder()
+        .pipeline("lz77,rans")
+        .checksum(Checksum::None)
+        .build()
+        .unwrap();
+    let compressor = DeflateCompression::new(&config);
+    let mut stream = compressor.compress(b"a")[..32].to_vec();
+    stream.extend((payload.len() as u32).to_be_bytes());
+    stream.extend(payload);
+    assert_eq!(stream.len(), 48);
+    assert!(compressor.decompress(&stream).is_err());
+});
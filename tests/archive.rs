@@ -0,0 +1,185 @@
+use compression::{
+    ArchiveEntry, ArchiveError, ArchiveIndex, ArchiveJob, CompressionParams, ConfigError,
+    ContainerError, DeflateCompression, DeflateConfig,
+};
+
+#[macro_use]
+mod utils;
+
+fn config_files(num: usize) -> Vec<ArchiveEntry> {
+    (0..num)
+        .map(|i| ArchiveEntry {
+            name: format!("configs/server{}.conf", i),
+            data: format!(
+                "host = server{}.example.com\nport = {}\ntimeout = 30\nretries = 5\n\
+                 log_level = info\nfeature_flags = alpha,beta,gamma\n",
+                i,
+                8000 + i
+            )
+            .into_bytes(),
+        })
+        .collect()
+}
+
+fn compressor() -> DeflateCompression {
+    DeflateCompression::new(&DeflateConfig::builder().window_size(4096).build().unwrap())
+}
+
+test!(archive_round_trip, {
+    let mut entries = config_files(50);
+    entries.insert(
+        10,
+        ArchiveEntry {
+            name: "notes.txt".to_string(),
+            data: b"Some notes".to_vec(),
+        },
+    );
+    entries.push(ArchiveEntry {
+        name: "empty".to_string(),
+        data: Vec::new(),
+    });
+    let compressor = compressor();
+
+    let archive = compressor.compress_archive(&entries).unwrap();
+    assert_eq!(compressor.decompress_archive(&archive).unwrap(), entries);
+    let decompressor = DeflateCompression::new(&DeflateConfig::builder().build().unwrap());
+    assert_eq!(decompressor.decompress_archive(&archive).unwrap(), entries);
+
+    let index = compressor.list_archive(&archive).unwrap();
+    let names: Vec<&str> = index.entries.iter().map(|e| e.name.as_str()).collect();
+    let expected: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, expected);
+    assert_eq!(index.entries[10].len, 10);
+
+    let empty = compressor.compress_archive(&[]).unwrap();
+    assert_eq!(compressor.decompress_archive(&empty).unwrap(), Vec::new());
+});
+
+test!(archive_matches_span_files, {
+    let entries = config_files(40);
+    let compressor = compressor();
+    let archive = compressor.compress_archive(&entries).unwrap();
+    let separately: usize = entries
+        .iter()
+        .map(|entry| compressor.compress(&entry.data).len())
+        .sum();
+    assert!(archive.len() * 5 < separately);
+});
+
+test!(archive_orders_and_deduplicates, {
+    let entries: Vec<ArchiveEntry> = [
+        ("b.txt", "text one"),
+        ("a.json", "{\"json\": 1}"),
+        ("c.txt", "text two"),
+        ("copy.txt", "text one"),
+        ("d.json", "{\"json\": 2}"),
+    ]
+    .into_iter()
+    .map(|(name, data)| ArchiveEntry {
+        name: name.to_string(),
+        data: data.as_bytes().to_vec(),
+    })
+    .collect();
+    let (index, solid) = ArchiveIndex::build(&entries);
+    let total: usize = entries.iter().map(|entry| entry.data.len()).sum();
+    assert_eq!(solid.len(), total - "text one".len());
+    assert_eq!(index.entries[0].offset, index.entries[3].offset);
+
+    let json_offsets = [index.entries[1].offset, index.entries[4].offset];
+    let text_offsets = [index.entries[0].offset, index.entries[2].offset];
+    assert!(json_offsets
+        .iter()
+        .all(|json| text_offsets.iter().all(|text| json < text)));
+    assert_eq!(index.extract(&solid).unwrap(), entries);
+    assert_eq!(
+        ArchiveIndex::from_bytes(&index.to_bytes().unwrap()).unwrap(),
+        index
+    );
+});
+
+test!(archive_rejects_invalid_input, {
+    let compressor = compressor();
+    let archive = compressor.compress_archive(&config_files(3)).unwrap();
+
+    assert_eq!(
+        compressor.list_archive(b"DFLR"),
+        Err(ArchiveError::BadMagic)
+    );
+    assert_eq!(
+        compressor.decompress_archive(&archive[..8]),
+        Err(ArchiveError::Truncated)
+    );
+    let mut wrong_version = archive.clone();
+    wrong_version[4] = ArchiveIndex::version() + 1;
+    assert_eq!(
+        compressor.list_archive(&wrong_version),
+        Err(ArchiveError::UnsupportedVersion(
+            ArchiveIndex::version() + 1
+        ))
+    );
+    assert_eq!(
+        compressor.decompress_archive(&archive[..archive.len() - 1]),
+        Err(ArchiveError::Container(ContainerError::Truncated))
+    );
+
+    let (mut index, solid) = ArchiveIndex::build(&config_files(3));
+    index.entries[2].offset = solid.len() as u64;
+    assert_eq!(
+        index.extract(&solid),
+        Err(ArchiveError::EntryOutOfBounds { entry: 2 })
+    );
+});
+
+test!(archive_rejects_too_long_name, {
+    let mut entries = config_files(3);
+    entries[1].name = "n".repeat(u16::MAX as usize + 1);
+    assert_eq!(
+        compressor().compress_archive(&entries),
+        Err(ArchiveError::NameTooLong { entry: 1 })
+    );
+
+    entries[1].name = "n".repeat(u16::MAX as usize);
+    let archive = compressor().compress_archive(&entries).unwrap();
+    assert_eq!(compressor().decompress_archive(&archive).unwrap(), entries);
+});
+
+test!(archive_cli_job, {
+    let mut params = CompressionParams::new();
+    assert_eq!(params.archive_job(), Ok(None));
+    params.update(&"-archive".to_string(), 1).unwrap();
+    params
+        .update_from_str(&"-input".to_string(), "configs")
+        .unwrap();
+    assert_eq!(params.archive_job(), Err(ConfigError::ArchiveFiles));
+    params
+        .update_from_str(&"-output".to_string(), "configs.dfs")
+        .unwrap();
+    assert_eq!(
+        params.archive_job(),
+        Ok(Some(ArchiveJob::Create {
+            directory: "configs".to_string(),
+            archive: "configs.dfs".to_string(),
+        }))
+    );
+    params.update(&"-decompress".to_string(), 1).unwrap();
+    assert_eq!(
+        params.archive_job(),
+        Ok(Some(ArchiveJob::Extract {
+            archive: "configs".to_string(),
+            directory: "configs.dfs".to_string(),
+        }))
+    );
+
+    let mut params = CompressionParams::new();
+    params.update(&"-list".to_string(), 1).unwrap();
+    assert_eq!(params.archive_job(), Err(ConfigError::ArchiveFiles));
+    params
+        .update_from_str(&"-input".to_string(), "configs.dfs")
+        .unwrap();
+    assert_eq!(
+        params.archive_job(),
+        Ok(Some(ArchiveJob::List {
+            archive: "configs.dfs".to_string(),
+        }))
+    );
+});
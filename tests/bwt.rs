@@ -0,0 +1,177 @@
+use compression::bwt::{BwtCompressor, Bzip2, MoveToFront, ZeroRunCompressor};
+use compression::lz77::implementation::ResultEncoding;
+use compression::pipeline::Pipeline;
+use compression::{
+    Bzip2Job, CompressionParams, ConfigError, DecompressLimits, DeflateCompression, DeflateConfig,
+};
+
+#[macro_use]
+mod utils;
+
+use utils::random_bytes;
+
+/// Output of `printf 'hello world\nhello bzip2\n' | bzip2 -9`.
+const HELLO_BZ2: [u8; 61] = [
+    0x42, 0x5a, 0x68, 0x39, 0x31, 0x41, 0x59, 0x26, 0x53, 0x59, 0x00, 0xfe, 0x01, 0x9c, 0x00, 0x00,
+    0x05, 0x59, 0x80, 0x00, 0x10, 0x40, 0x00, 0x10, 0x00, 0x16, 0x64, 0xd0, 0x90, 0x20, 0x00, 0x31,
+    0x00, 0x00, 0x04, 0xa8, 0x1a, 0x1b, 0x53, 0x6a, 0x6b, 0x32, 0x28, 0x52, 0x70, 0x75, 0xe2, 0x01,
+    0xb5, 0x2f, 0xe2, 0xee, 0x48, 0xa7, 0x0a, 0x12, 0x00, 0x1f, 0xc0, 0x33, 0x80,
+];
+
+/// Sentences of words drawn from a small vocabulary, like text of a natural language.
+fn text(words_num: usize) -> Vec<u8> {
+    let words = [
+        "the",
+        "of",
+        "and",
+        "compression",
+        "window",
+        "block",
+        "stream",
+        "is",
+        "a",
+        "data",
+        "with",
+        "every",
+        "match",
+        "bytes",
+        "sorted",
+        "rotation",
+        "in",
+        "to",
+        "which",
+    ];
+    random_bytes(words_num, 3)
+        .into_iter()
+        .enumerate()
+        .map(|(i, random)| match i % 12 {
+            11 => format!("{}.\n", words[random as usize % words.len()]),
+            _ => format!("{} ", words[random as usize % words.len()]),
+        })
+        .collect::<String>()
+        .into_bytes()
+}
+
+test!(bwt_forward_and_inverse, {
+    assert_eq!(BwtCompressor::forward(b"banana"), (b"nnbaaa".to_vec(), 3));
+    assert_eq!(BwtCompressor::inverse(b"nnbaaa", 3).unwrap(), b"banana");
+    assert!(BwtCompressor::inverse(b"nnbaaa", 6).is_err());
+
+    let inputs = [
+        Vec::new(),
+        b"a".to_vec(),
+        b"abab".repeat(1000),
+        vec![0; 5000],
+        random_bytes(10000, 1),
+        text(2000),
+    ];
+    let limits = DecompressLimits::unlimited();
+    for bytes in inputs {
+        let compressed = BwtCompressor::compress(&bytes);
+        assert_eq!(compressed.len(), bytes.len() + 4);
+        assert_eq!(
+            BwtCompressor::decompress(&compressed, &limits).unwrap(),
+            bytes
+        );
+    }
+    let limits = DecompressLimits::unlimited().with_max_output_len(99);
+    assert!(BwtCompressor::decompress(&BwtCompressor::compress(&[7; 100]), &limits).is_err());
+    assert!(BwtCompressor::decompress(&[0, 0], &limits).is_err());
+});
+
+test!(mtf_and_zero_runs, {
+    assert_eq!(MoveToFront::encode(b"aaabbba"), vec![97, 0, 0, 98, 0, 0, 1]);
+    let bytes = random_bytes(1000, 2);
+    assert_eq!(MoveToFront::decode(&MoveToFront::encode(&bytes)), bytes);
+
+    let tokens: Vec<ResultEncoding> =
+        ZeroRunCompressor::compress_to_tokens(&[5, 0, 0, 0, 0, 0, 7, 7, 7, 7, 7])
+            .into_iter()
+            .collect();
+    assert_eq!(tokens.len(), 8);
+    assert!(matches!(tokens[2], ResultEncoding::Repeat(0, 4)));
+    assert!(tokens[3..]
+        .iter()
+        .all(|token| matches!(token, ResultEncoding::Ascii(7))));
+});
+
+test!(pipeline_bwt, {
+    let bytes = text(1500);
+    let config = DeflateConfig::builder()
+        .pipeline("bwt,mtf,zrle,huffman")
+        .build()
+        .unwrap();
+    let compressor = DeflateCompression::new(&config);
+    assert_eq!(compressor.pipeline().spec(), "bwt,mtf,zrle,huffman");
+
+    let compressed = compressor.compress(&bytes);
+    assert_eq!(compressor.decompress(&compressed).unwrap(), bytes);
+    let decompressor = DeflateCompression::new(&DeflateConfig::default());
+    assert_eq!(decompressor.decompress(&compressed).unwrap(), bytes);
+    assert!(compressed.len() < decompressor.compress(&bytes).len());
+
+    let pipeline = Pipeline::from_spec("bwt,mtf,zrle,range", &config).unwrap();
+    let encoded = pipeline.encode(bytes.clone());
+    assert_eq!(pipeline.decode(encoded).unwrap(), bytes);
+});
+
+test!(bzip2_reads_bzip2_output, {
+    let limits = DecompressLimits::default();
+    assert_eq!(
+        Bzip2::decompress(&HELLO_BZ2, &limits).unwrap(),
+        b"hello world\nhello bzip2\n"
+    );
+    let concatenated = [HELLO_BZ2, HELLO_BZ2].concat();
+    assert_eq!(
+        Bzip2::decompress(&concatenated, &limits).unwrap(),
+        b"hello world\nhello bzip2\n".repeat(2)
+    );
+
+    let mut corrupted = HELLO_BZ2;
+    corrupted[40] ^= 0x10;
+    assert!(Bzip2::decompress(&corrupted, &limits).is_err());
+    assert!(Bzip2::decompress(&HELLO_BZ2[..50], &limits).is_err());
+    let limits = DecompressLimits::default().with_max_output_len(10);
+    assert!(Bzip2::decompress(&HELLO_BZ2, &limits).is_err());
+});
+
+test!(bzip2_round_trip, {
+    let limits = DecompressLimits::default();
+    let runs: Vec<u8> = (0..3000u32)
+        .flat_map(|i| vec![(i % 3) as u8; (i % 300) as usize + 1])
+        .collect();
+    let inputs = [
+        Vec::new(),
+        b"x".to_vec(),
+        runs,
+        random_bytes(5000, 4),
+        text(20000),
+    ];
+    for bytes in inputs {
+        let compressed = Bzip2::compress(&bytes);
+        assert_eq!(&compressed[..4], b"BZh9");
+        assert_eq!(Bzip2::decompress(&compressed, &limits).unwrap(), bytes);
+    }
+    assert_eq!(Bzip2::compress(&[]).len(), 14);
+});
+
+test!(bzip2_cli_job, {
+    let mut params = CompressionParams::new();
+    assert_eq!(params.bzip2_job(), Ok(None));
+    params.update(&"-bz2".to_string(), 1).unwrap();
+    params
+        .update_from_str(&"-input".to_string(), "text.txt")
+        .unwrap();
+    assert_eq!(params.bzip2_job(), Err(ConfigError::Bzip2Files));
+    params
+        .update_from_str(&"-output".to_string(), "text.txt.bz2")
+        .unwrap();
+    assert_eq!(
+        params.bzip2_job(),
+        Ok(Some(Bzip2Job {
+            input: "text.txt".to_string(),
+            output: "text.txt.bz2".to_string(),
+            decompress: false,
+        }))
+    );
+});
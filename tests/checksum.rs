@@ -0,0 +1,81 @@
+use compression::checksum::{xxh32, Crc32};
+
+#[macro_use]
+mod utils;
+
+use utils::random_bytes;
+
+test!(crc32_reference_values, {
+    assert_eq!(Crc32::checksum(b""), 0);
+    assert_eq!(Crc32::checksum(b"123456789"), 0xCBF4_3926);
+    assert_eq!(
+        Crc32::checksum(b"The quick brown fox jumps over the lazy dog"),
+        0x414F_A339
+    );
+
+    let mut crc = Crc32::new();
+    crc.update(b"12345");
+    crc.update(b"6789");
+    assert_eq!(crc.finalize(), 0xCBF4_3926);
+});
+
+test!(xxh32_reference_values, {
+    assert_eq!(xxh32(b"", 0), 0x02CC_5D05);
+    assert_eq!(xxh32(b"a", 0), 0x550D_7456);
+    assert_eq!(xxh32(b"abc", 0), 0x32D1_53FF);
+    assert_eq!(
+        xxh32(b"Nobody inspects the spammish repetition", 0),
+        0xE229_3B2F
+    );
+});
+
+fn bitwise_crc32(bytes: &[u8]) -> u32 {
+    let mut crc = u32::MAX;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xEDB8_8320,
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
+
+test!(crc32_matches_bitwise_computation, {
+    let bytes = random_bytes(3000, 1);
+    for offset in 0..16 {
+        for len in (0..700).chain([1024, 2047, 2900]) {
+            let slice = &bytes[offset..offset + len];
+            assert_eq!(Crc32::checksum(slice), bitwise_crc32(slice));
+        }
+    }
+
+    let mut crc = Crc32::new();
+    bytes.chunks(333).for_each(|chunk| crc.update(chunk));
+    assert_eq!(crc.finalize(), bitwise_crc32(&bytes));
+});
+
+test!(crc32_combine, {
+    let bytes = random_bytes(5000, 1);
+    for split in [0, 1, 7, 128, 1000, 4999, 5000] {
+        let (first, second) = bytes.split_at(split);
+        assert_eq!(
+            Crc32::combine(
+                Crc32::checksum(first),
+                Crc32::checksum(second),
+                second.len() as u64
+            ),
+            Crc32::checksum(&bytes)
+        );
+    }
+
+    let combined = bytes
+        .chunks(700)
+        .map(|chunk| (Crc32::checksum(chunk), chunk.len() as u64))
+        .fold(Crc32::checksum(b""), |crc, (chunk_crc, len)| {
+            Crc32::combine(crc, chunk_crc, len)
+        });
+    assert_eq!(combined, Crc32::checksum(&bytes));
+});
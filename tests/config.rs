@@ -0,0 +1,162 @@
+use compression::pipeline::UnknownStageError;
+use compression::{
+    CompressionParams, ConfigError, DeflateCompression, DeflateConfig, EntropyCoder, Strategy,
+};
+
+#[macro_use]
+mod utils;
+
+test!(config_builder, {
+    let config = DeflateConfig::builder()
+        .window_size(1000)
+        .max_match_len(20)
+        .strategy(Strategy::HuffmanOnly)
+        .entropy_coder(EntropyCoder::Rans)
+        .build()
+        .unwrap();
+    assert_eq!(config.window_size(), 1000);
+    assert_eq!(config.max_match_len(), Some(20));
+    assert_eq!(config.build_pipeline().spec(), "rans");
+    assert_eq!(
+        DeflateConfig::default().build_pipeline().spec(),
+        "lz77,huffman"
+    );
+
+    let text = "abcabcbabcbbhjklijhga789! ".repeat(100);
+    let mut compressor = DeflateCompression::new(&config);
+    let compressed = compressor.deflate_compress(&text);
+    assert_eq!(compressor.deflate_decompress(&compressed).unwrap(), text);
+});
+
+test!(config_validation, {
+    assert_eq!(
+        DeflateConfig::builder().window_size(0).build(),
+        Err(ConfigError::WindowSize(0))
+    );
+    assert_eq!(
+        DeflateConfig::builder()
+            .window_size(100)
+            .max_match_len(101)
+            .build(),
+        Err(ConfigError::MaxMatchLen {
+            max_match_len: 101,
+            window_size: 100
+        })
+    );
+    assert_eq!(
+        DeflateConfig::builder().pipeline("lz77,zip").build(),
+        Err(ConfigError::UnknownStage(UnknownStageError(
+            "zip".to_string()
+        )))
+    );
+
+    let spec = vec!["mtf"; 70].join(",") + ",lz77,huffman";
+    assert_eq!(
+        DeflateConfig::builder().pipeline(&spec).build(),
+        Err(ConfigError::SpecTooLong(spec.len()))
+    );
+    let spec = vec!["mtf"; 60].join(",") + ",lz77,huffman";
+    let config = DeflateConfig::builder().pipeline(&spec).build().unwrap();
+    let compressor = DeflateCompression::new(&config);
+    let text = b"spec of many stages".to_vec();
+    assert_eq!(
+        compressor.decompress(&compressor.compress(&text)).unwrap(),
+        text
+    );
+
+    let mut params = CompressionParams::new();
+    assert_eq!(
+        params.update(&"-entropy".to_string(), 4),
+        Err(ConfigError::EntropyCoder(4))
+    );
+    assert_eq!(
+        params.update(&"-windowsize".to_string(), 4096),
+        Err(ConfigError::UnknownOption("-windowsize".to_string()))
+    );
+    assert_eq!(
+        params.update_from_str(&"-window_size".to_string(), "4k"),
+        Err(ConfigError::InvalidValue {
+            option: "-window_size".to_string(),
+            value: "4k".to_string(),
+        })
+    );
+    for option in ["-pipeline", "-input"] {
+        assert_eq!(
+            params.update(&option.to_string(), 5),
+            Err(ConfigError::InvalidValue {
+                option: option.to_string(),
+                value: "5".to_string(),
+            })
+        );
+    }
+    params.update(&"-window_size".to_string(), 0).unwrap();
+    assert!(params.config().is_err());
+});
+
+test!(config_serde, {
+    let config = DeflateConfig::builder()
+        .window_size(4096)
+        .strategy(Strategy::Lz77Only)
+        .entropy_coder(EntropyCoder::AdaptiveHuffman)
+        .build()
+        .unwrap();
+    let json = serde_json::to_string(&config).unwrap();
+    assert_eq!(
+        serde_json::from_str::<DeflateConfig>(&json).unwrap(),
+        config
+    );
+
+    let partial: DeflateConfig =
+        serde_json::from_str(r#"{"entropy_coder": "range_coder", "max_match_len": 16}"#).unwrap();
+    assert_eq!(partial.entropy_coder(), EntropyCoder::RangeCoder);
+    assert_eq!(
+        partial.window_size(),
+        DeflateConfig::default().window_size()
+    );
+
+    assert!(serde_json::from_str::<DeflateConfig>(r#"{"window_size": 0}"#).is_err());
+    assert!(serde_json::from_str::<DeflateConfig>(r#"{"window": 10}"#).is_err());
+});
+
+test!(config_zlib_strategies, {
+    let config = |strategy| {
+        DeflateConfig::builder()
+            .strategy(strategy)
+            .entropy_coder(EntropyCoder::Rans)
+            .build()
+            .unwrap()
+    };
+    assert_eq!(config(Strategy::Rle).build_pipeline().spec(), "rle,rans");
+    assert_eq!(
+        config(Strategy::Filtered).build_pipeline().spec(),
+        "lz77,rans"
+    );
+    assert_eq!(
+        config(Strategy::Fixed).build_pipeline().spec(),
+        "lz77,huffman"
+    );
+    assert_eq!(
+        config(Strategy::Filtered).lz77_compressor().min_match_len(),
+        12
+    );
+    assert_eq!(
+        config(Strategy::Default).lz77_compressor().min_match_len(),
+        3
+    );
+
+    let text = "abcabcbabcbbhjklijhga789! 0.125 0.126 0.127 ".repeat(100);
+    for strategy in [Strategy::Rle, Strategy::Filtered, Strategy::Fixed] {
+        let mut compressor = DeflateCompression::new(&config(strategy));
+        let compressed = compressor.deflate_compress(&text);
+        assert_eq!(compressor.deflate_decompress(&compressed).unwrap(), text);
+    }
+
+    let mut params = CompressionParams::new();
+    params.update(&"-lz77".to_string(), 0).unwrap();
+    params.update(&"-strategy".to_string(), 5).unwrap();
+    assert_eq!(params.config().unwrap().strategy(), Strategy::Filtered);
+    assert_eq!(
+        params.update(&"-strategy".to_string(), 8),
+        Err(ConfigError::Strategy(8))
+    );
+});
@@ -0,0 +1,188 @@
+use compression::container::{ContainerError, ContainerHeader};
+use compression::{Checksum, DeflateCompression, DeflateConfig, EntropyCoder, Strategy};
+
+#[macro_use]
+mod utils;
+
+use utils::random_bytes;
+
+fn compressors() -> Vec<DeflateCompression> {
+    let strategies = [
+        Strategy::Default,
+        Strategy::HuffmanOnly,
+        Strategy::Lz77Only,
+        Strategy::Stored,
+        Strategy::Rle,
+        Strategy::Filtered,
+        Strategy::Fixed,
+        Strategy::Auto,
+    ];
+    let coders = [
+        EntropyCoder::Huffman,
+        EntropyCoder::AdaptiveHuffman,
+        EntropyCoder::RangeCoder,
+        EntropyCoder::Rans,
+    ];
+    strategies
+        .into_iter()
+        .flat_map(|strategy| {
+            coders.into_iter().map(move |coder| {
+                let config = DeflateConfig::builder()
+                    .window_size(2000)
+                    .strategy(strategy)
+                    .entropy_coder(coder)
+                    .build()
+                    .unwrap();
+                DeflateCompression::new(&config)
+            })
+        })
+        .collect()
+}
+
+test!(container_decoded_by_any_instance, {
+    let text = "abcabcbabcbbhjklijhga789! abcabcbabcbbhjklijhga789! ".repeat(50);
+    let compressors = compressors();
+    for compressor in compressors.iter() {
+        let compressed = compressor.compress(text.as_bytes());
+        for decompressor in compressors.iter() {
+            assert_eq!(
+                decompressor.decompress(&compressed).unwrap(),
+                text.as_bytes()
+            );
+        }
+    }
+});
+
+test!(container_header, {
+    let config = DeflateConfig::builder()
+        .window_size(1234)
+        .frame_size(5)
+        .build()
+        .unwrap();
+    let compressed = DeflateCompression::new(&config).compress(b"abcabcabcabc");
+    let (header, _) = ContainerHeader::read(&compressed).unwrap();
+    assert_eq!(header.pipeline_spec, "lz77,huffman");
+    assert_eq!(header.window_size, 1234);
+    assert_eq!(header.original_len, 12);
+    assert_eq!(header.frame_size, 5);
+    assert_eq!(header.checksum, Checksum::Crc32);
+    assert_eq!(header.frames_num(), 3);
+    assert_eq!(header.frame_len(2), 2);
+});
+
+test!(container_frames, {
+    let text = "abcabcbabcbbhjklijhga789! abcabcbabcbbhjklijhga789! ".repeat(50);
+    for checksum in [Checksum::None, Checksum::Crc32, Checksum::XxHash32] {
+        for frame_size in [7, 100, 1 << 20] {
+            let config = DeflateConfig::builder()
+                .frame_size(frame_size)
+                .checksum(checksum)
+                .build()
+                .unwrap();
+            let compressor = DeflateCompression::new(&config);
+            let compressed = compressor.compress(text.as_bytes());
+            assert_eq!(compressor.decompress(&compressed).unwrap(), text.as_bytes());
+        }
+    }
+});
+
+test!(container_rejects_invalid_streams, {
+    let compressor = DeflateCompression::new(&DeflateConfig::default());
+    let compressed = compressor.compress(b"abcabcabcabc");
+
+    let mut wrong_version = compressed.clone();
+    wrong_version[4] = ContainerHeader::version() + 1;
+    assert_eq!(
+        compressor.decompress(&wrong_version),
+        Err(ContainerError::UnsupportedVersion(
+            ContainerHeader::version() + 1
+        ))
+    );
+    assert_eq!(
+        compressor.decompress(b"abcabcabcabc"),
+        Err(ContainerError::BadMagic)
+    );
+    assert_eq!(
+        compressor.decompress(&compressed[..10]),
+        Err(ContainerError::Truncated)
+    );
+    assert_eq!(
+        compressor.decompress(&compressed[..compressed.len() - 1]),
+        Err(ContainerError::Truncated)
+    );
+    assert_eq!(
+        compressor.decompress(&[compressed.clone(), vec![0]].concat()),
+        Err(ContainerError::TrailingData)
+    );
+
+    let mut wrong_len = compressed.clone();
+    let len_position = 4 + 1 + 1 + 1 + "lz77,huffman".len() + 4 + 7;
+    wrong_len[len_position] += 1;
+    assert_eq!(
+        compressor.decompress(&wrong_len),
+        Err(ContainerError::HeaderChecksumMismatch)
+    );
+});
+
+test!(container_detects_flipped_bits, {
+    let text = "abcabcbabcbbhjklijhga789! abcabcbabcbbhjklijhga789! ".repeat(10);
+    for coder in [
+        EntropyCoder::Huffman,
+        EntropyCoder::AdaptiveHuffman,
+        EntropyCoder::RangeCoder,
+        EntropyCoder::Rans,
+    ] {
+        for checksum in [Checksum::Crc32, Checksum::XxHash32] {
+            let config = DeflateConfig::builder()
+                .entropy_coder(coder)
+                .checksum(checksum)
+                .build()
+                .unwrap();
+            let compressor = DeflateCompression::new(&config);
+            let compressed = compressor.compress(text.as_bytes());
+            let (_, header_len) = ContainerHeader::read(&compressed).unwrap();
+            for position in (header_len + 4..compressed.len()).step_by(7) {
+                for bit in [0, 3, 7] {
+                    let mut corrupted = compressed.clone();
+                    corrupted[position] ^= 1 << bit;
+                    match compressor.decompress(&corrupted) {
+                        Ok(decompressed) => assert_eq!(decompressed, text.as_bytes()),
+                        Err(_) => continue,
+                    }
+                }
+            }
+        }
+    }
+});
+
+test!(container_stores_incompressible_frames, {
+    let inputs = [
+        Vec::new(),
+        b"a".to_vec(),
+        b"xyz".to_vec(),
+        random_bytes(3000, 1),
+    ];
+    for compressor in compressors() {
+        for input in inputs.iter() {
+            let compressed = compressor.compress(input);
+            assert!(compressed.len() as u64 <= compressor.max_compressed_len(input.len()));
+            assert_eq!(&compressor.decompress(&compressed).unwrap(), input);
+        }
+    }
+
+    let config = DeflateConfig::builder().frame_size(1000).build().unwrap();
+    let compressor = DeflateCompression::new(&config);
+    let input = random_bytes(3000, 1);
+    let (compressed, stats) = compressor.compress_with_stats(&input);
+    assert_eq!(stats.stored_frames_num, 3);
+    assert_eq!(
+        compressed.len() as u64,
+        compressor.max_compressed_len(input.len())
+    );
+    let (header, header_len) = ContainerHeader::read(&compressed).unwrap();
+    assert_eq!(header.encoded_len(), header_len);
+    let frames = header.split_frames(&compressed[header_len..]).unwrap();
+    assert!(frames
+        .iter()
+        .all(|frame| frame.stored && frame.compressed.len() == 1000));
+});
@@ -1,5 +1,7 @@
 use bit_vec::BitVec;
 use compression::huffman::{self, HuffmanTreeCreator};
+use compression::lz77::implementation::{ResultEncoding, ResultEncodingVec};
+use compression::lz77::LZ77Compressor;
 use std::collections::HashMap;
 
 #[macro_use]
@@ -74,7 +76,7 @@ test!(test_building_tree_from_weights_2, {
 test!(test_huffman_1, {
     let s: Vec<u8> = Vec::from("abcabcbabcbb");
     let compressed = huffman::HuffmanCompressor::compress(&s, false);
-    let decompressed = huffman::HuffmanCompressor::decompress(&compressed);
+    let decompressed = huffman::HuffmanCompressor::decompress(&compressed).unwrap();
     assert_eq!(s, decompressed);
 });
 
@@ -83,7 +85,7 @@ test!(test_huffman_2, {
         Vec::from("abcdefghijklmonprstuwvxyzABCDEFGHIJKLMNOPRSTUWVXYZ1234567890!@#$%^&*((_+");
     let compressed = huffman::HuffmanCompressor::compress(&s, false);
     println!("{}", compressed);
-    let decompressed = huffman::HuffmanCompressor::decompress(&compressed);
+    let decompressed = huffman::HuffmanCompressor::decompress(&compressed).unwrap();
     assert_eq!(s, decompressed);
 });
 
@@ -92,7 +94,7 @@ test!(test_huffman_3, {
     let s: Vec<u8> = Vec::from(big_word);
     let compressed = huffman::HuffmanCompressor::compress(&s, false);
     println!("{}", compressed);
-    let decompressed = huffman::HuffmanCompressor::decompress(&compressed);
+    let decompressed = huffman::HuffmanCompressor::decompress(&compressed).unwrap();
     assert_eq!(s, decompressed);
 });
 
@@ -101,6 +103,203 @@ test!(test_huffman_4, {
         Vec::from("abcdefghijklmonprstuwvxyzABCDEFGHIJKLMNOPRSTUWVXYZ1234567890!@#$%^&*((_+");
     let compressed = huffman::HuffmanCompressor::compress(&s, true);
     println!("{}", compressed);
-    let decompressed = huffman::HuffmanCompressor::decompress(&compressed);
+    let decompressed = huffman::HuffmanCompressor::decompress(&compressed).unwrap();
     assert_eq!(s, decompressed);
 });
+
+test!(test_huffman_long_codes, {
+    // Fibonacci-like frequencies give codes longer than the primary decoding table.
+    let mut s: Vec<u8> = Vec::new();
+    let (mut a, mut b) = (1, 1);
+    for c in b'a'..b'p' {
+        s.extend(std::iter::repeat_n(c, a));
+        (a, b) = (b, a + b);
+    }
+    let compressed = huffman::HuffmanCompressor::compress(&s, false);
+    let decompressed = huffman::HuffmanCompressor::decompress(&compressed).unwrap();
+    assert_eq!(s, decompressed);
+});
+
+test!(test_huffman_decoder_matches_codes, {
+    let mut weights = HashMap::new();
+    (0..40_u8).for_each(|i| {
+        weights.insert(i, i / 3 + 1);
+    });
+
+    let (on_bits, _) = HuffmanTreeCreator::get_mappings(&weights);
+    let decoder = huffman::HuffmanDecoder::new(&on_bits);
+    let mut bits = BitVec::new();
+    (0..40_u8)
+        .rev()
+        .for_each(|i| bits.extend(on_bits[&i].iter()));
+    let bytes = bits.to_bytes();
+    let mut reader = huffman::BitReader::new(&bytes, bits.len());
+
+    let decoded: Vec<u8> = (0..40)
+        .map(|_| decoder.decode(&mut reader).unwrap())
+        .collect();
+    assert_eq!(decoded, (0..40_u8).rev().collect::<Vec<u8>>());
+    assert!(reader.is_empty());
+});
+
+test!(test_huffman_tokens, {
+    let s: Vec<u8> = Vec::from("aaaaaabbbbbabbababbbbbbbbbbaaaaaaaaabbbbbbabbbbbababababab");
+    let compressor = LZ77Compressor::new(None, Some(7));
+    let tokens = compressor.compress_to_tokens(&s);
+
+    for predefined in [false, true] {
+        let compressed = huffman::HuffmanCompressor::compress_tokens(&tokens, predefined);
+        let decompressed = huffman::HuffmanCompressor::decompress_tokens(&compressed).unwrap();
+        assert_eq!(decompressed.to_ascii_bytes(), tokens.to_ascii_bytes());
+        assert_eq!(decompressed.expand(), s);
+    }
+});
+
+test!(test_huffman_tokens_smaller_than_ascii_references, {
+    let s: Vec<u8> = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. "
+        .repeat(200)
+        .into_bytes();
+    let tokens = LZ77Compressor::new(None, None).compress_to_tokens(&s);
+
+    let on_tokens = huffman::HuffmanCompressor::compress_tokens(&tokens, false);
+    let on_ascii = huffman::HuffmanCompressor::compress(&tokens.to_ascii_bytes(), false);
+    assert!(on_tokens.len() < on_ascii.len());
+    assert_eq!(
+        huffman::HuffmanCompressor::decompress_tokens(&on_tokens)
+            .unwrap()
+            .expand(),
+        s
+    );
+});
+
+test!(test_huffman_blocks, {
+    let mut s: Vec<u8> = "the quick brown fox jumps over the lazy dog "
+        .repeat(100)
+        .into_bytes();
+    s.extend((0..5000_u32).map(|i| (i * 7919 % 251) as u8));
+    s.extend("0123456789".repeat(300).into_bytes());
+
+    for predefined in [false, true] {
+        let compressed = huffman::HuffmanCompressor::compress(&s, predefined);
+        let decompressed = huffman::HuffmanCompressor::decompress(&compressed).unwrap();
+        assert_eq!(s, decompressed);
+    }
+});
+
+test!(test_huffman_predefined_codes_cover_binary_data, {
+    let s: Vec<u8> = (0..=255).rev().cycle().take(2000).collect();
+    let compressed = huffman::HuffmanCompressor::compress(&s, true);
+    let block_type: Vec<bool> = compressed.iter().skip(1).take(2).collect();
+    assert_eq!(block_type, [false, true]);
+    assert_eq!(
+        huffman::HuffmanCompressor::decompress(&compressed).unwrap(),
+        s
+    );
+});
+
+test!(test_huffman_empty, {
+    let s: Vec<u8> = Vec::new();
+    let compressed = huffman::HuffmanCompressor::compress(&s, false);
+    assert_eq!(
+        huffman::HuffmanCompressor::decompress(&compressed).unwrap(),
+        s
+    );
+});
+
+test!(test_huffman_incompressible_block_is_stored, {
+    let s: Vec<u8> = (0..=255).collect();
+    let compressed = huffman::HuffmanCompressor::compress(&s, false);
+    assert!(compressed.len() <= s.len() * 8 + 64);
+    assert_eq!(
+        huffman::HuffmanCompressor::decompress(&compressed).unwrap(),
+        s
+    );
+});
+
+test!(test_huffman_stored_block_keeps_repeat_distances, {
+    let mut tokens: Vec<ResultEncoding> = (0..255).map(ResultEncoding::Ascii).collect();
+    tokens.push(ResultEncoding::Reference(100, 3));
+    tokens.extend((0..300).map(|_| ResultEncoding::Repeat(0, 3)));
+    let tokens = ResultEncodingVec::from_iter(tokens);
+
+    let compressed = huffman::HuffmanCompressor::compress_tokens(&tokens, false);
+    assert_eq!(
+        huffman::HuffmanCompressor::decompress_tokens(&compressed)
+            .unwrap()
+            .expand(),
+        tokens.expand()
+    );
+});
+
+test!(test_adaptive_huffman, {
+    let mut s: Vec<u8> = Vec::new();
+    let (mut a, mut b) = (1, 1);
+    for c in b'a'..b'p' {
+        s.extend(std::iter::repeat_n(c, a));
+        (a, b) = (b, a + b);
+    }
+    s.extend((0..=255).rev());
+
+    let compressed = huffman::AdaptiveHuffmanCompressor::compress(&s);
+    let decompressed = huffman::AdaptiveHuffmanCompressor::decompress(&compressed).unwrap();
+    assert_eq!(s, decompressed);
+    assert!(compressed.len() < s.len() * 8);
+});
+
+test!(test_adaptive_huffman_tokens, {
+    let s: Vec<u8> = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. "
+        .repeat(50)
+        .into_bytes();
+    let tokens = LZ77Compressor::new(None, None).compress_to_tokens(&s);
+
+    let mut encoder = huffman::AdaptiveTokenEncoder::new();
+    tokens.iter().for_each(|token| encoder.push(token));
+    let compressed = encoder.finish();
+    assert_eq!(
+        compressed,
+        huffman::AdaptiveHuffmanCompressor::compress_tokens(&tokens)
+    );
+    let decompressed = huffman::AdaptiveHuffmanCompressor::decompress_tokens(&compressed).unwrap();
+    assert_eq!(decompressed.expand(), s);
+});
+
+test!(test_adaptive_huffman_far_distances, {
+    let tokens = ResultEncodingVec::from_iter([
+        ResultEncoding::Ascii(1),
+        ResultEncoding::Reference(usize::MAX / 2, 3),
+        ResultEncoding::Repeat(0, 4),
+    ]);
+    let compressed = huffman::AdaptiveHuffmanCompressor::compress_tokens(&tokens);
+    assert_eq!(
+        huffman::AdaptiveHuffmanCompressor::decompress_tokens(&compressed)
+            .unwrap()
+            .to_ascii_bytes(),
+        tokens.to_ascii_bytes()
+    );
+});
+
+test!(test_huffman_bytes_round_trip, {
+    let text = b"abracadabra, abracadabra! 0123456789".to_vec();
+    for len in 0..text.len() {
+        let s = &text[..len];
+        for predefined in [false, true] {
+            let compressed = huffman::HuffmanCompressor::compress_to_bytes(s, predefined);
+            assert_eq!(
+                huffman::HuffmanCompressor::decompress_from_bytes(&compressed).unwrap(),
+                s
+            );
+        }
+        let compressed = huffman::AdaptiveHuffmanCompressor::compress_to_bytes(s);
+        assert_eq!(
+            huffman::AdaptiveHuffmanCompressor::decompress_from_bytes(&compressed).unwrap(),
+            s
+        );
+    }
+});
+
+test!(test_huffman_truncated_bytes, {
+    let s = b"abracadabra, abracadabra! 0123456789".repeat(10);
+    let compressed = huffman::HuffmanCompressor::compress_to_bytes(&s, false);
+    let truncated = &compressed[..compressed.len() / 2];
+    assert!(huffman::HuffmanCompressor::decompress_from_bytes(truncated).is_err());
+});
@@ -1,13 +1,34 @@
 use compression::CompressionParams;
 use compression::DeflateCompression;
+use compression::DeflateConfig;
 
 #[macro_use]
 mod utils;
 
 test!(test_deflate_1, {
     let s = String::from("abcabcbabcbbhjklijhga789!");
-    let mut compressor = DeflateCompression::new(&CompressionParams::new());
+    let mut compressor = DeflateCompression::new(&DeflateConfig::default());
     let compressed = compressor.deflate_compress(&s);
-    let decompressed = compressor.deflate_decompress(&compressed);
+    let decompressed = compressor.deflate_decompress(&compressed).unwrap();
     assert_eq!(s, decompressed);
 });
+
+test!(test_deflate_all_stages_combinations, {
+    let s = "abcabcbabcbbhjklijhga789! abcabcbabcbbhjklijhga789! ".repeat(100);
+    let options = [
+        ("-lz77", vec![0, 1]),
+        ("-huff", vec![0, 1]),
+        ("-codes_predef", vec![0, 1]),
+        ("-entropy", vec![0, 1, 2, 3]),
+    ];
+    for (alias, values) in options {
+        for value in values {
+            let mut params = CompressionParams::new();
+            params.update(&alias.to_string(), value).unwrap();
+            let mut compressor = DeflateCompression::new(&params.config().unwrap());
+            let compressed = compressor.deflate_compress(&s);
+            let decompressed = compressor.deflate_decompress(&compressed).unwrap();
+            assert_eq!(s, decompressed);
+        }
+    }
+});
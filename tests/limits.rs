@@ -0,0 +1,140 @@
+use compression::ans::AnsCompressor;
+use compression::huffman::{AdaptiveHuffmanCompressor, HuffmanCompressor};
+use compression::lz77::implementation::{ResultEncoding, ResultEncodingVec};
+use compression::pipeline::Pipeline;
+use compression::range_coder::RangeCompressor;
+use compression::DecodeError;
+use compression::{ContainerError, DecompressLimits, DeflateCompression, DeflateConfig, Strategy};
+
+#[macro_use]
+mod utils;
+
+test!(limits_output_limit, {
+    let limits = DecompressLimits::new();
+    assert_eq!(limits.output_limit(10), 1 << 32);
+    assert_eq!(limits.with_max_ratio(100).output_limit(10), 1000);
+    assert_eq!(
+        limits
+            .with_max_ratio(100)
+            .with_max_output_len(500)
+            .output_limit(10),
+        500
+    );
+    assert_eq!(
+        DecompressLimits::unlimited().output_limit(usize::MAX),
+        u64::MAX
+    );
+});
+
+test!(limits_crafted_ascii_reference, {
+    let tokens = ResultEncodingVec::from_ascii_bytes(b"a~1~999999999999~");
+    assert!(tokens.try_expand().is_err());
+    assert!(tokens
+        .try_expand_with(&DecompressLimits::unlimited().with_max_output_len(1000))
+        .is_err());
+
+    let tokens = ResultEncodingVec::from_ascii_bytes(b"a~1~9~");
+    assert_eq!(tokens.try_expand().unwrap(), b"aaaaaaaaaa");
+    assert!(tokens
+        .try_expand_with(&DecompressLimits::new().with_max_reference_len(8))
+        .is_err());
+    assert!(tokens
+        .try_expand_with(&DecompressLimits::new().with_max_output_len(9))
+        .is_err());
+
+    let pipeline = Pipeline::from_spec("lz77", &DeflateConfig::default()).unwrap();
+    assert!(pipeline.decode(b"a~1~999999999999~".to_vec()).is_err());
+});
+
+test!(limits_huffman_decoder, {
+    let tokens: ResultEncodingVec = [
+        ResultEncoding::Ascii(b'a'),
+        ResultEncoding::Reference(1, 5000),
+    ]
+    .into_iter()
+    .collect();
+    let bytes = HuffmanCompressor::compress_tokens_to_bytes(&tokens, false);
+    let decompress = |limits: DecompressLimits| {
+        HuffmanCompressor::decompress_tokens_with_limits(&bytes, &limits)
+    };
+
+    assert_eq!(decompress(DecompressLimits::new()).unwrap().len(), 2);
+    assert_eq!(
+        decompress(DecompressLimits::new().with_max_output_len(5001))
+            .unwrap()
+            .len(),
+        2
+    );
+    assert!(decompress(DecompressLimits::new().with_max_output_len(5000)).is_err());
+    assert!(decompress(DecompressLimits::new().with_max_reference_len(4999)).is_err());
+    assert!(decompress(DecompressLimits::new().with_max_ratio(2)).is_err());
+});
+
+test!(limits_adaptive_coders, {
+    type Decoder = fn(&[u8], &DecompressLimits) -> Result<ResultEncodingVec, DecodeError>;
+    let tokens: ResultEncodingVec = [
+        ResultEncoding::Ascii(b'a'),
+        ResultEncoding::Reference(1, 5000),
+    ]
+    .into_iter()
+    .collect();
+    let coders: [(Vec<u8>, Decoder); 3] = [
+        (
+            AdaptiveHuffmanCompressor::compress_tokens_to_bytes(&tokens),
+            AdaptiveHuffmanCompressor::decompress_tokens_with_limits,
+        ),
+        (
+            RangeCompressor::compress_tokens(&tokens),
+            RangeCompressor::decompress_tokens_with_limits,
+        ),
+        (
+            AnsCompressor::compress_tokens(&tokens),
+            AnsCompressor::decompress_tokens_with_limits,
+        ),
+    ];
+
+    for (bytes, decompress) in coders {
+        let decompress = |limits: DecompressLimits| decompress(&bytes, &limits);
+        assert_eq!(decompress(DecompressLimits::new()).unwrap().len(), 2);
+        assert_eq!(
+            decompress(DecompressLimits::new().with_max_output_len(5001))
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(decompress(DecompressLimits::new().with_max_output_len(5000)).is_err());
+        assert!(decompress(DecompressLimits::new().with_max_reference_len(4999)).is_err());
+        assert!(decompress(DecompressLimits::new().with_max_ratio(2)).is_err());
+    }
+});
+
+test!(limits_of_container, {
+    let text = "a".repeat(8000).into_bytes();
+    for strategy in [Strategy::Default, Strategy::Lz77Only, Strategy::Stored] {
+        let config = DeflateConfig::builder().strategy(strategy).build().unwrap();
+        let compressor = DeflateCompression::new(&config);
+        let compressed = compressor.compress(&text);
+        assert_eq!(compressor.decompress(&compressed).unwrap(), text);
+
+        let limited = DeflateCompression::new(&config)
+            .with_limits(DecompressLimits::new().with_max_output_len(7999));
+        assert_eq!(
+            limited.decompress(&compressed),
+            Err(ContainerError::OutputLimit {
+                declared: 8000,
+                limit: 7999
+            })
+        );
+
+        let ratio = 8000_u64.div_ceil(compressed.len() as u64);
+        let limited = DeflateCompression::new(&config)
+            .with_limits(DecompressLimits::new().with_max_ratio(ratio));
+        assert_eq!(limited.decompress(&compressed).unwrap(), text);
+        let limited = DeflateCompression::new(&config)
+            .with_limits(DecompressLimits::new().with_max_ratio(ratio - 1));
+        assert!(matches!(
+            limited.decompress(&compressed),
+            Err(ContainerError::OutputLimit { .. })
+        ));
+    }
+});
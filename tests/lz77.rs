@@ -1,14 +1,25 @@
+use compression::huffman::{AdaptiveHuffmanCompressor, HuffmanCompressor};
+use compression::lz77::alphabet::SymbolCode;
 use compression::lz77::hashes::HashTable;
-use compression::lz77::LZ77Compressor;
+use compression::lz77::implementation::{ResultEncoding, ResultEncodingVec};
+use compression::lz77::long_range::LongMatch;
+use compression::lz77::{
+    CostModel, LZ77Compressor, LiteralFitting, LongRangeMatcher, RepeatDistances, RleCompressor,
+    Segment,
+};
+use compression::range_coder::RangeCompressor;
+use compression::{DeflateConfig, EntropyCoder, Strategy};
 
 #[macro_use]
 mod utils;
 
+use utils::random_bytes;
+
 fn run_compression_test_case(s_org: String, expected_compr: String, compressor: LZ77Compressor) {
     let s_org = Vec::from(s_org.as_bytes());
     let expected_compr = Vec::from(expected_compr.as_bytes());
     let s_compr = compressor.compress(&s_org);
-    let s_decompr = compressor.decompress(&s_compr);
+    let s_decompr = compressor.decompress(&s_compr).unwrap();
     assert_eq!(s_compr, expected_compr);
     assert_eq!(s_org, s_decompr);
 }
@@ -49,14 +60,14 @@ test!(compression3, {
 
 test!(compression4, {
     let s_org = String::from("aaaaaabbbbbbccccccaaabbbccc");
-    let s_compr_expected = String::from("aaaaaabbbbbbccccccaaa~12~6~");
+    let s_compr_expected = String::from("aaaaaabbbbbbccccccaaabbbccc");
     let compressor_instance = LZ77Compressor::new(None, Some(7));
     run_compression_test_case(s_org, s_compr_expected, compressor_instance);
 });
 
 test!(compression5, {
     let s_org = String::from("aaaaaabbbbbabbababbbbbbbbbbaaaaaaaaabbbbbbabbbbbababababab");
-    let s_compr_expected = String::from("aaaaaabbbbbabbab~11~6~~16~6~a~1~7~~15~7~~6~7~a~2~7~");
+    let s_compr_expected = String::from("aaaaaabbbbbabbababbb~1~7~aa~1~7~~15~7~~6~7~a~2~7~");
     let compressor_instance = LZ77Compressor::new(None, Some(7));
     run_compression_test_case(s_org, s_compr_expected, compressor_instance);
 });
@@ -75,3 +86,254 @@ test!(compression7, {
     let compressor_instance = LZ77Compressor::new(Some(5), Some(5));
     run_compression_test_case(s_org.clone(), s_org, compressor_instance);
 });
+
+test!(symbol_codes, {
+    for value in (6..5000).chain([usize::MAX / 2]) {
+        let code = SymbolCode::length(value);
+        assert!(SymbolCode::is_length_symbol(code.symbol));
+        assert_eq!(
+            SymbolCode::length_extra_bits_num(code.symbol),
+            code.extra_bits_num
+        );
+        assert_eq!(
+            SymbolCode::length_value(code.symbol, code.extra_bits),
+            value
+        );
+
+        let code = SymbolCode::distance(value);
+        assert_eq!(
+            SymbolCode::distance_extra_bits_num(code.symbol),
+            code.extra_bits_num
+        );
+        assert_eq!(
+            SymbolCode::distance_value(code.symbol, code.extra_bits),
+            value
+        );
+    }
+
+    assert_eq!(SymbolCode::distance(1).symbol, 4);
+    assert_eq!(SymbolCode::distance(5).symbol, 8);
+    assert_eq!(SymbolCode::distance(32768).symbol, 33);
+    assert_eq!(SymbolCode::repeat(2).symbol, 2);
+    assert!(SymbolCode::is_repeat_symbol(3) && !SymbolCode::is_repeat_symbol(4));
+    assert!(matches!(
+        SymbolCode::reference(7, 2, 0),
+        ResultEncoding::Repeat(2, 7)
+    ));
+    let code = SymbolCode::distance(100);
+    assert!(matches!(
+        SymbolCode::reference(7, code.symbol, code.extra_bits),
+        ResultEncoding::Reference(100, 7)
+    ));
+});
+
+test!(min_match_len, {
+    let s = Vec::from(
+        "fox jumps, dog naps; fox jumps. 0123456789abcdefghij 0123456789abcdefghij".as_bytes(),
+    );
+    let lengths = |compressor: LZ77Compressor| -> Vec<usize> {
+        let tokens = compressor.compress_to_tokens(&s);
+        assert_eq!(tokens.expand(), s);
+        tokens
+            .iter()
+            .filter_map(|token| match token {
+                ResultEncoding::Reference(_, len) | ResultEncoding::Repeat(_, len) => Some(*len),
+                ResultEncoding::Ascii(_) => None,
+            })
+            .collect()
+    };
+
+    assert!(lengths(LZ77Compressor::new(None, None))
+        .iter()
+        .any(|len| *len < 12));
+    let filtered = lengths(LZ77Compressor::new(None, None).with_min_match_len(12));
+    assert!(!filtered.is_empty() && filtered.iter().all(|len| *len >= 12));
+    assert_eq!(
+        LZ77Compressor::new(None, None)
+            .with_min_match_len(1)
+            .min_match_len(),
+        ResultEncoding::len_treshold()
+    );
+});
+
+test!(rle_references_distance_1, {
+    let s: Vec<u8> = [vec![7; 300], b"abcabcabcabc".to_vec(), vec![0; 5]].concat();
+    let tokens = RleCompressor::compress_to_tokens(&s);
+    assert_eq!(tokens.expand(), s);
+    assert!(tokens
+        .iter()
+        .all(|token| !matches!(token, ResultEncoding::Reference(dist, _) if *dist != 1)));
+    assert!(tokens.len() < 30);
+});
+
+test!(cost_model, {
+    assert!(CostModel::Entropy.accepts(1, 3));
+    assert!(!CostModel::Entropy.accepts(32768, 3));
+    assert!(CostModel::Entropy.accepts(32768, 6));
+    assert!(!CostModel::Entropy.accepts(1, 2));
+    assert!(CostModel::Ascii.accepts(1, 7));
+    assert!(!CostModel::Ascii.accepts(12, 6));
+    assert_eq!(CostModel::Ascii.reference_bits(123, 45), 8 * 8);
+
+    let s = Vec::from("abcXabcYabcdefghZabcdefgh".as_bytes());
+    let references = |compressor: LZ77Compressor| -> Vec<(usize, usize)> {
+        let tokens = compressor.find_matches(&s);
+        assert_eq!(tokens.expand(), s);
+        tokens
+            .iter()
+            .filter_map(|token| match token {
+                ResultEncoding::Reference(dist, len) => Some((*dist, *len)),
+                ResultEncoding::Repeat(..) => {
+                    panic!("Repeat tokens are not found by the sliding window")
+                }
+                ResultEncoding::Ascii(_) => None,
+            })
+            .collect()
+    };
+    let compressor = LZ77Compressor::new(None, None);
+    assert_eq!(compressor.cost_model(), CostModel::Entropy);
+    assert_eq!(references(compressor), vec![(4, 3), (4, 3), (9, 8)]);
+    assert_eq!(
+        references(LZ77Compressor::new(None, None).with_cost_model(CostModel::Ascii)),
+        vec![(9, 8)]
+    );
+});
+
+test!(cost_model_of_coder, {
+    let dynamic = HuffmanCompressor::new(false).symbol_costs();
+    let adaptive = AdaptiveHuffmanCompressor::symbol_costs();
+    assert!(CostModel::Coded(dynamic).accepts(300, 3));
+    assert!(!CostModel::Coded(adaptive).accepts(300, 3));
+
+    let skewed = "aab".repeat(1000).into_bytes();
+    let binary = random_bytes(3000, 5);
+    let on_skewed = CostModel::Coded(dynamic).fitted_to(&skewed);
+    assert!(!on_skewed.accepts(1000, 8));
+    assert!(CostModel::Coded(dynamic)
+        .fitted_to(&binary)
+        .accepts(1000, 8));
+    let reference = ResultEncoding::Reference(1000, 8);
+    assert!(!on_skewed.replaces(&reference, b"aaaaaaaa"));
+    assert!(on_skewed.replaces(&reference, b"zzzzzzzz"));
+
+    let fixed = HuffmanCompressor::new(true).symbol_costs();
+    assert_eq!(fixed.fitting(), LiteralFitting::Predefined);
+    assert_eq!(fixed.fitted_to(&skewed), fixed);
+    assert_eq!(
+        RangeCompressor::symbol_costs().fitting(),
+        LiteralFitting::Fractional
+    );
+
+    let config = DeflateConfig::builder()
+        .entropy_coder(EntropyCoder::AdaptiveHuffman)
+        .build()
+        .unwrap();
+    assert_eq!(
+        config.lz77_compressor().cost_model(),
+        CostModel::Coded(adaptive)
+    );
+    let config = DeflateConfig::builder()
+        .strategy(Strategy::Fixed)
+        .build()
+        .unwrap();
+    assert_eq!(
+        config.lz77_compressor().cost_model(),
+        CostModel::Coded(fixed)
+    );
+});
+
+test!(repeat_distances, {
+    let mut repeats = RepeatDistances::new();
+    assert_eq!(repeats.distances(), &[1, 4, 8, 16]);
+    repeats.use_distance(100);
+    assert_eq!(repeats.distances(), &[100, 1, 4, 8]);
+    assert_eq!(repeats.repeat(2), Ok(4));
+    assert_eq!(repeats.distances(), &[4, 100, 1, 8]);
+    repeats.use_distance(1);
+    assert_eq!(repeats.distances(), &[1, 4, 100, 8]);
+    assert!(repeats.repeat(4).is_err());
+
+    let ascii = "ab~r0~4~".as_bytes();
+    let tokens = ResultEncodingVec::from_ascii_bytes(ascii);
+    assert!(matches!(tokens.as_slice()[2], ResultEncoding::Repeat(0, 4)));
+    assert_eq!(tokens.to_ascii_bytes(), ascii);
+    assert_eq!(tokens.expand(), b"abbbbb");
+    assert!(ResultEncodingVec::from_ascii_bytes("ab~r7~4~".as_bytes())
+        .try_expand()
+        .is_err());
+});
+
+test!(repeat_tokens, {
+    let records: Vec<u8> = (0..200u32)
+        .flat_map(|i| [(i * 7 % 100) as u8, 0, 0, 1, (i % 3) as u8, 9, 9, 9, 9, 9])
+        .collect();
+    let compressor = LZ77Compressor::new(None, Some(16));
+    let tokens = compressor.compress_to_tokens(&records);
+    assert_eq!(tokens.expand(), records);
+    let repeats_num = tokens
+        .iter()
+        .filter(|token| matches!(token, ResultEncoding::Repeat(..)))
+        .count();
+    assert!(repeats_num > 100);
+
+    let plain = compressor.find_matches(&records);
+    assert_eq!(
+        compressor
+            .use_repeat_distances(&records, plain)
+            .to_ascii_bytes(),
+        tokens.to_ascii_bytes()
+    );
+    let rewritten = compressor
+        .use_repeat_distances(&records, tokens.iter().cloned().collect())
+        .to_ascii_bytes();
+    assert_eq!(rewritten, tokens.to_ascii_bytes());
+    let invalid =
+        ResultEncodingVec::from_iter([ResultEncoding::Ascii(1), ResultEncoding::Repeat(7, 3)]);
+    assert_eq!(
+        compressor
+            .use_repeat_distances(&[1, 1, 1, 1], invalid)
+            .to_ascii_bytes(),
+        "\u{1}~r7~3~".as_bytes()
+    );
+    let ascii = compressor.compress(&records);
+    assert_eq!(compressor.decompress(&ascii).unwrap(), records);
+
+    let rle = RleCompressor::compress_to_tokens(&[5; 100]);
+    assert!(matches!(rle.as_slice()[1], ResultEncoding::Repeat(0, 99)));
+});
+
+test!(long_range_matches, {
+    let block = random_bytes(2000, 1);
+    let mut bytes = block.clone();
+    bytes.extend(random_bytes(40000, 2));
+    bytes.extend(&block);
+    bytes.extend(random_bytes(100, 3));
+
+    let matcher = LongRangeMatcher::new(64, 16);
+    assert_eq!(
+        matcher.find_matches(&bytes),
+        vec![LongMatch {
+            pos: 42000,
+            dist: 42000,
+            len: 2000
+        }]
+    );
+    assert!(matches!(
+        matcher.segments(&bytes, 5000).as_slice(),
+        [
+            Segment::Chunk(..),
+            ..,
+            Segment::Match(ResultEncoding::Reference(42000, 2000)),
+            Segment::Chunk(..)
+        ]
+    ));
+    assert!(matcher.find_matches(&random_bytes(50000, 4)).is_empty());
+    assert_eq!(LongRangeMatcher::new(0, 100).anchor_interval(), 128);
+
+    let compressor = LZ77Compressor::new(Some(100), Some(16)).with_long_range(matcher);
+    let tokens = compressor.compress_to_tokens(&bytes);
+    assert_eq!(tokens.expand(), bytes);
+    assert!(tokens
+        .iter()
+        .any(|token| matches!(token, ResultEncoding::Reference(42000, 2000))));
+});
@@ -0,0 +1,121 @@
+use compression::container::ContainerError;
+use compression::lz77::implementation::{ResultEncoding, ResultEncodingVec};
+use compression::lz77::{LZ77Compressor, PatchCompressor};
+use compression::pipeline::{Pipeline, UnknownStageError};
+use compression::{
+    CompressionParams, ConfigError, DecompressLimits, DeflateCompression, DeflateConfig, PatchJob,
+};
+
+#[macro_use]
+mod utils;
+
+use utils::random_bytes;
+
+fn edited(reference: &[u8]) -> Vec<u8> {
+    let mut target = reference.to_vec();
+    target[1000..1010].copy_from_slice(b"0123456789");
+    target.splice(20000..20000, b"inserted bytes".iter().cloned());
+    target.drain(30000..30500);
+    target
+}
+
+test!(patch_expand_after_dictionary, {
+    let tokens = ResultEncodingVec::from_iter([
+        ResultEncoding::Reference(3, 3),
+        ResultEncoding::Ascii(b'!'),
+        ResultEncoding::Reference(6, 4),
+    ]);
+    let limits = DecompressLimits::unlimited();
+    assert_eq!(
+        tokens.try_expand_after(b"xabc", &limits).unwrap(),
+        b"abc!bcab"
+    );
+    assert!(tokens.try_expand_after(b"ab", &limits).is_err());
+    assert!(tokens.try_expand_with(&limits).is_err());
+});
+
+test!(patch_compressor_round_trip, {
+    let reference = random_bytes(50000, 7);
+    let target = edited(&reference);
+    let compressor = PatchCompressor::new(LZ77Compressor::new(Some(1024), Some(16)), &reference);
+    let tokens = compressor.compress_to_tokens(&target);
+    assert!(tokens.len() < 100);
+    let limits = DecompressLimits::unlimited();
+    assert_eq!(
+        compressor.decompress_tokens(&tokens, &limits).unwrap(),
+        target
+    );
+});
+
+test!(patch_spec, {
+    assert_eq!(Pipeline::patch_spec("lz77,huffman"), "patch,huffman");
+    assert_eq!(Pipeline::patch_spec("lz77_long, range"), "patch,range");
+    assert_eq!(Pipeline::patch_spec("rle,huffman"), "patch,huffman");
+    assert_eq!(Pipeline::patch_spec("huffman"), "patch,huffman");
+    assert_eq!(Pipeline::patch_spec(""), "patch");
+
+    let config = DeflateConfig::default();
+    assert_eq!(
+        Pipeline::from_spec("patch,huffman", &config).err(),
+        Some(UnknownStageError("patch".to_string()))
+    );
+    let pipeline = Pipeline::from_patch_spec("patch,huffman", &config, b"abc").unwrap();
+    assert_eq!(pipeline.spec(), "patch,huffman");
+});
+
+test!(patch_round_trip, {
+    let reference = random_bytes(50000, 11);
+    let target = edited(&reference);
+    let config = DeflateConfig::builder()
+        .window_size(1024)
+        .max_match_len(16)
+        .frame_size(16384)
+        .build()
+        .unwrap();
+    let compressor = DeflateCompression::new(&config);
+
+    let patch = compressor.compress_patch(&reference, &target);
+    assert!(patch.len() < 1000);
+    assert!(patch.len() * 20 < compressor.compress(&target).len());
+    assert_eq!(compressor.apply_patch(&reference, &patch).unwrap(), target);
+    let decompressor = DeflateCompression::new(&DeflateConfig::default());
+    assert_eq!(
+        decompressor.apply_patch(&reference, &patch).unwrap(),
+        target
+    );
+
+    assert_eq!(
+        compressor.decompress(&patch),
+        Err(ContainerError::ReferenceRequired)
+    );
+    assert!(compressor
+        .apply_patch(&random_bytes(50000, 12), &patch)
+        .is_err());
+});
+
+test!(patch_cli_job, {
+    let mut params = CompressionParams::new();
+    assert_eq!(params.patch_job(), Ok(None));
+    params
+        .update_from_str(&"-patch_from".to_string(), "old.bin")
+        .unwrap();
+    params
+        .update_from_str(&"-input".to_string(), "new.bin")
+        .unwrap();
+    assert_eq!(params.patch_job(), Err(ConfigError::PatchFiles));
+    params
+        .update_from_str(&"-output".to_string(), "patch.bin")
+        .unwrap();
+    params
+        .update_from_str(&"-decompress".to_string(), "1")
+        .unwrap();
+    assert_eq!(
+        params.patch_job(),
+        Ok(Some(PatchJob {
+            reference: "old.bin".to_string(),
+            input: "new.bin".to_string(),
+            output: "patch.bin".to_string(),
+            decompress: true,
+        }))
+    );
+});
@@ -0,0 +1,88 @@
+use compression::ans::AnsCompressor;
+use compression::huffman::HuffmanCompressor;
+use compression::lz77::LZ77Compressor;
+use compression::pipeline::{Pipeline, UnknownStageError};
+use compression::range_coder::RangeCompressor;
+use compression::{CompressionParams, DeflateCompression, DeflateConfig};
+
+#[macro_use]
+mod utils;
+
+test!(pipeline_from_spec, {
+    let config = DeflateConfig::default();
+    let pipeline = Pipeline::from_spec("lz77, huffman", &config).unwrap();
+    assert_eq!(pipeline.spec(), "lz77,huffman");
+    assert_eq!(
+        Pipeline::from_spec("lz77,zstd", &config).err(),
+        Some(UnknownStageError("zstd".to_string()))
+    );
+    assert!(Pipeline::from_spec("", &config).unwrap().is_empty());
+});
+
+test!(pipeline_round_trip, {
+    let text = "abcabcbabcbbhjklijhga789! abcabcbabcbbhjklijhga789! ".repeat(100);
+    let pipelines = [
+        Pipeline::new(),
+        Pipeline::new().with_stage(LZ77Compressor::new(None, None)),
+        Pipeline::new().with_stage(HuffmanCompressor::new(false)),
+        Pipeline::new()
+            .with_stage(LZ77Compressor::new(None, None))
+            .with_stage(RangeCompressor),
+        Pipeline::new()
+            .with_stage(LZ77Compressor::new(None, None))
+            .with_stage(AnsCompressor)
+            .with_stage(HuffmanCompressor::new(true)),
+    ];
+    for pipeline in pipelines {
+        let encoded = pipeline.encode(text.as_bytes().to_vec());
+        assert_eq!(pipeline.decode(encoded).unwrap(), text.as_bytes());
+    }
+});
+
+test!(pipeline_cli_spec, {
+    let text = "abcabcbabcbbhjklijhga789! abcabcbabcbbhjklijhga789! ".repeat(100);
+    let mut params = CompressionParams::new();
+    params
+        .update_from_str(&"-pipeline".to_string(), "lz77,adaptive_huffman")
+        .unwrap();
+    params
+        .update_from_str(&"-window_size".to_string(), "1000")
+        .unwrap();
+    let mut compressor = DeflateCompression::new(&params.config().unwrap());
+    assert_eq!(compressor.pipeline().spec(), "lz77,adaptive_huffman");
+
+    let compressed = compressor.deflate_compress(&text);
+    assert!(compressed.len() < text.len());
+    assert_eq!(compressor.deflate_decompress(&compressed).unwrap(), text);
+});
+
+test!(pipeline_long_range, {
+    let block: Vec<u8> = (0..20000u32)
+        .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+        .collect();
+    let bytes = [block.clone(), block].concat();
+    let builder = DeflateConfig::builder().window_size(1024).max_match_len(16);
+    let config = builder.clone().long_range(true).build().unwrap();
+    let compressor = DeflateCompression::new(&config);
+    assert_eq!(compressor.pipeline().spec(), "lz77_long,huffman");
+    assert_eq!(compressor.pipeline().window_size(), u32::MAX as usize);
+
+    let compressed = compressor.compress(&bytes);
+    assert_eq!(compressor.decompress(&compressed).unwrap(), bytes);
+    let decompressor = DeflateCompression::new(&DeflateConfig::default());
+    assert_eq!(decompressor.decompress(&compressed).unwrap(), bytes);
+    let windowed_config = builder.build().unwrap();
+    let windowed = DeflateCompression::new(&windowed_config).compress(&bytes);
+    assert!(compressed.len() < windowed.len() * 3 / 4);
+
+    let pipeline = Pipeline::from_spec("lz77_long,range", &windowed_config).unwrap();
+    assert_eq!(pipeline.spec(), "lz77_long,range");
+    let encoded = pipeline.encode(bytes.clone());
+    assert_eq!(pipeline.decode(encoded).unwrap(), bytes);
+
+    let mut params = CompressionParams::new();
+    params
+        .update_from_str(&"-long_range".to_string(), "1")
+        .unwrap();
+    assert!(params.config().unwrap().long_range());
+});
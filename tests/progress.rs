@@ -0,0 +1,129 @@
+use std::sync::{Arc, Mutex};
+
+use compression::{
+    CancellationToken, CancelledError, ContainerError, DeflateCompression, DeflateConfig,
+    JobControl, Progress,
+};
+
+#[macro_use]
+mod utils;
+
+fn compressor(frame_size: usize) -> DeflateCompression {
+    let config = DeflateConfig::builder()
+        .frame_size(frame_size)
+        .build()
+        .unwrap();
+    DeflateCompression::new(&config)
+}
+
+fn recording_control(reports: &Arc<Mutex<Vec<Progress>>>) -> JobControl {
+    let reports = Arc::clone(reports);
+    JobControl::new().on_progress(move |progress| reports.lock().unwrap().push(progress))
+}
+
+test!(progress_reported_per_frame, {
+    let compressor = compressor(1000);
+    let text = "progress of a long job, ".repeat(400).into_bytes();
+    let frames_num = text.len().div_ceil(1000);
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let compressed = compressor
+        .compress_with_control(&text, &recording_control(&reports))
+        .unwrap();
+    assert_eq!(compressed, compressor.compress(&text));
+    let mut consumed: Vec<u64> = reports.lock().unwrap().iter().map(|p| p.consumed).collect();
+    consumed.sort();
+    assert_eq!(consumed.len(), frames_num);
+    assert_eq!(consumed.last(), Some(&(text.len() as u64)));
+    assert!(reports
+        .lock()
+        .unwrap()
+        .iter()
+        .all(|p| p.total == text.len() as u64));
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let decompressed = compressor
+        .decompress_with_control(&compressed, &recording_control(&reports))
+        .unwrap();
+    assert_eq!(decompressed, text);
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), frames_num + 1);
+    assert_eq!(
+        reports.iter().map(|p| p.consumed).max(),
+        Some(compressed.len() as u64)
+    );
+});
+
+test!(progress_reported_per_chunk, {
+    let compressor = compressor(1 << 20);
+    let text = "progress of a single frame, ".repeat(2000).into_bytes();
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let compressed = compressor
+        .compress_with_control(&text, &recording_control(&reports))
+        .unwrap();
+    assert_eq!(compressed, compressor.compress(&text));
+    let mut consumed: Vec<u64> = reports.lock().unwrap().iter().map(|p| p.consumed).collect();
+    consumed.sort();
+    assert!(consumed.len() >= text.len() / 5000);
+    assert_eq!(consumed.last(), Some(&(text.len() as u64)));
+
+    let token = CancellationToken::new();
+    let control = {
+        let token = token.clone();
+        JobControl::new()
+            .cancellation(token.clone())
+            .on_progress(move |_| token.cancel())
+    };
+    assert_eq!(
+        compressor.decompress_with_control(&compressed, &control),
+        Err(ContainerError::Cancelled(CancelledError))
+    );
+});
+
+test!(cancelled_before_start, {
+    let compressor = compressor(1000);
+    let text = "cancelled job ".repeat(200).into_bytes();
+    let compressed = compressor.compress(&text);
+
+    let token = CancellationToken::new();
+    token.cancel();
+    let control = JobControl::new().cancellation(token.clone());
+    assert_eq!(
+        compressor.compress_with_control(&text, &control),
+        Err(CancelledError)
+    );
+    assert_eq!(
+        compressor.decompress_with_control(&compressed, &control),
+        Err(ContainerError::Cancelled(CancelledError))
+    );
+});
+
+test!(cancelled_while_running, {
+    let compressor = compressor(50);
+    let text = "cancelled while running, ".repeat(1000).into_bytes();
+    let compressed = compressor.compress(&text);
+
+    for decompress in [false, true] {
+        let token = CancellationToken::new();
+        let reports = Arc::new(Mutex::new(0));
+        let control = {
+            let (token, reports) = (token.clone(), Arc::clone(&reports));
+            JobControl::new()
+                .cancellation(token.clone())
+                .on_progress(move |_| {
+                    *reports.lock().unwrap() += 1;
+                    token.cancel();
+                })
+        };
+
+        let cancelled = match decompress {
+            false => compressor.compress_with_control(&text, &control).is_err(),
+            true => compressor
+                .decompress_with_control(&compressed, &control)
+                .is_err(),
+        };
+        assert!(cancelled);
+        assert!(*reports.lock().unwrap() < text.len() / 50);
+    }
+});
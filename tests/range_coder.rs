@@ -0,0 +1,74 @@
+use compression::huffman::HuffmanCompressor;
+use compression::lz77::LZ77Compressor;
+use compression::range_coder::{FrequencyModel, RangeCompressor, RangeDecoder, RangeEncoder};
+
+#[macro_use]
+mod utils;
+
+test!(test_frequency_model, {
+    let mut model = FrequencyModel::new(10);
+    (0..1000).for_each(|i| model.update(i % 3));
+
+    let mut low = 0;
+    for symbol in 0..10 {
+        let (symbol_low, frequency) = model.interval(symbol);
+        assert_eq!(symbol_low, low);
+        assert_eq!(model.find(low), (symbol, low, frequency));
+        assert_eq!(model.find(low + frequency - 1), (symbol, low, frequency));
+        low += frequency;
+    }
+    assert_eq!(low, model.total());
+});
+
+test!(test_range_coder_symbols_and_bits, {
+    let symbols: Vec<usize> = (0..5000).map(|i| (i * i + 7) % 13).collect();
+    let mut model = FrequencyModel::new(13);
+    let mut encoder = RangeEncoder::new();
+    for (i, symbol) in symbols.iter().enumerate() {
+        encoder.encode(&mut model, *symbol);
+        encoder.encode_direct_bits(i as u64, 12);
+    }
+    let bytes = encoder.finish();
+
+    let mut model = FrequencyModel::new(13);
+    let mut decoder = RangeDecoder::new(&bytes);
+    for (i, symbol) in symbols.iter().enumerate() {
+        assert_eq!(decoder.decode(&mut model), *symbol);
+        assert_eq!(decoder.decode_direct_bits(12), i as u64 % (1 << 12));
+    }
+});
+
+test!(test_range_coder_bytes, {
+    let s: Vec<u8> =
+        Vec::from("abcdefghijklmonprstuwvxyzABCDEFGHIJKLMNOPRSTUWVXYZ1234567890!@#$%^&*((_+");
+    let compressed = RangeCompressor::compress(&s);
+    assert_eq!(RangeCompressor::decompress(&compressed).unwrap(), s);
+
+    let s: Vec<u8> = Vec::new();
+    assert_eq!(
+        RangeCompressor::decompress(&RangeCompressor::compress(&s)).unwrap(),
+        s
+    );
+});
+
+test!(test_range_coder_beats_huffman_on_skewed_input, {
+    let s: Vec<u8> = "a".repeat(10000).into_bytes();
+    let range_compressed = RangeCompressor::compress(&s);
+    let huffman_compressed = HuffmanCompressor::compress(&s, false);
+    assert!(range_compressed.len() * 8 < huffman_compressed.len() / 10);
+    assert_eq!(RangeCompressor::decompress(&range_compressed).unwrap(), s);
+});
+
+test!(test_range_coder_tokens, {
+    let s: Vec<u8> = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. "
+        .repeat(50)
+        .into_bytes();
+    let tokens = LZ77Compressor::new(None, None).compress_to_tokens(&s);
+    let compressed = RangeCompressor::compress_tokens(&tokens);
+    assert_eq!(
+        RangeCompressor::decompress_tokens(&compressed)
+            .unwrap()
+            .expand(),
+        s
+    );
+});
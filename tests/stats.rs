@@ -0,0 +1,54 @@
+use compression::{DeflateCompression, DeflateConfig, Strategy};
+
+#[macro_use]
+mod utils;
+
+fn text() -> Vec<u8> {
+    "abracadabra, abracadabra! ".repeat(200).into_bytes()
+}
+
+test!(stats_match_compressed_output, {
+    let config = DeflateConfig::builder().frame_size(1000).build().unwrap();
+    let compressor = DeflateCompression::new(&config);
+    let text = text();
+
+    let (compressed, stats) = compressor.compress_with_stats(&text);
+    assert_eq!(compressed, compressor.compress(&text));
+    assert_eq!(compressor.decompress(&compressed).unwrap(), text);
+    assert_eq!(stats.input_len, text.len() as u64);
+    assert_eq!(stats.compressed_len, compressed.len() as u64);
+    assert!(stats.bits_per_byte() < 8.0);
+    assert!(stats.header_bits > 0 && stats.header_bits < compressed.len() as u64 * 8);
+
+    assert!(stats.matches_num > 0);
+    assert_eq!(
+        stats.match_lengths.values().sum::<usize>(),
+        stats.matches_num
+    );
+    assert!(stats.repeat_matches_num > 0);
+    assert_eq!(
+        stats.match_distances.values().sum::<usize>() + stats.repeat_matches_num,
+        stats.matches_num
+    );
+    assert!(stats.code_lengths.values().sum::<usize>() > stats.literals_num + stats.matches_num);
+
+    let stages: Vec<&str> = stats.stage_times.iter().map(|(stage, _)| *stage).collect();
+    assert_eq!(stages, vec!["lz77", "huffman"]);
+});
+
+test!(stats_without_lz77, {
+    let config = DeflateConfig::builder()
+        .strategy(Strategy::HuffmanOnly)
+        .build()
+        .unwrap();
+    let text = text();
+    let (_, stats) = DeflateCompression::new(&config).compress_with_stats(&text);
+
+    assert_eq!(stats.literals_num, text.len());
+    assert_eq!(stats.matches_num, 0);
+    assert!(stats.match_lengths.is_empty());
+    assert_eq!(
+        stats.code_lengths.values().sum::<usize>(),
+        stats.literals_num + 1
+    );
+});
@@ -0,0 +1,71 @@
+#![cfg(feature = "tracing")]
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+
+use compression::{DeflateCompression, DeflateConfig};
+use tracing::{span, Event, Metadata, Subscriber};
+
+#[macro_use]
+mod utils;
+
+/// Records names of created spans and counts events.
+#[derive(Clone, Default)]
+struct Recorder {
+    spans: Arc<Mutex<Vec<&'static str>>>,
+    events_num: Arc<AtomicU64>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+        self.spans.lock().unwrap().push(span.metadata().name());
+        span::Id::from_u64(self.next_id.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+
+    fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+    fn event(&self, _event: &Event<'_>) {
+        self.events_num.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn enter(&self, _span: &span::Id) {}
+
+    fn exit(&self, _span: &span::Id) {}
+}
+
+test!(tracing_spans_of_compression, {
+    let recorder = Recorder::default();
+    // Global, since spans of frames and chunks are created on rayon threads.
+    tracing::subscriber::set_global_default(recorder.clone()).unwrap();
+
+    let config = DeflateConfig::builder().frame_size(3000).build().unwrap();
+    let compressor = DeflateCompression::new(&config);
+    let text = "tracing spans of compression ".repeat(300).into_bytes();
+    let compressed = compressor.compress(&text);
+    assert_eq!(compressor.decompress(&compressed).unwrap(), text);
+
+    let spans = recorder.spans.lock().unwrap().clone();
+    let count = |name: &str| spans.iter().filter(|span| **span == name).count();
+    let frames_num = text.len().div_ceil(3000);
+    assert_eq!(count("compress"), 1);
+    assert_eq!(count("decompress"), 1);
+    assert_eq!(count("compress_frame"), frames_num);
+    assert_eq!(count("decompress_frame"), frames_num);
+    assert_eq!(count("encode_stage"), 2 * frames_num);
+    assert!(count("lz77_chunk") >= frames_num);
+    assert_eq!(count("sliding_window"), count("lz77_chunk"));
+    assert_eq!(count("huffman"), frames_num);
+    assert_eq!(
+        recorder.events_num.load(Ordering::SeqCst),
+        spans.len() as u64
+    );
+});
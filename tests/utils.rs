@@ -10,3 +10,16 @@ macro_rules! test {
     };
 }
 
+/// Incompressible bytes of a xorshift generator, the same for the same `seed`.
+#[allow(dead_code)]
+pub fn random_bytes(len: usize, seed: u64) -> Vec<u8> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 24) as u8
+        })
+        .collect()
+}